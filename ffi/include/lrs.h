@@ -0,0 +1,102 @@
+#ifndef LRS_H
+#define LRS_H
+
+/* Generated by cbindgen from ffi/src/lib.rs; do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// The call succeeded; for `lrs_verify`, the signature is valid.
+#define LRS_OK 0
+
+// A pointer argument is null.
+#define LRS_ERR_NULL 1
+
+// The verifier key does not decode or is malformed.
+#define LRS_ERR_KEY 2
+
+// The ring does not decode, is empty or is larger than the verifier key allows.
+#define LRS_ERR_RING 3
+
+// The signature does not decode.
+#define LRS_ERR_SIGNATURE 4
+
+// The message is not UTF-8.
+#define LRS_ERR_MESSAGE 5
+
+// The signature does not verify.
+#define LRS_ERR_INVALID 6
+
+// The circuit of the verifier key has no link tag.
+#define LRS_ERR_NO_LINK_TAG 7
+
+// The library panicked, which is a bug.
+#define LRS_ERR_INTERNAL 8
+
+// The length of a link tag: a BN254 scalar, little-endian.
+#define LRS_LINK_TAG_LEN 32
+
+// A decoded ring of public keys.
+typedef struct LrsRing LrsRing;
+
+// A decoded and validated `LrsVKey`.
+typedef struct LrsVerifierKey LrsVerifierKey;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Decodes and validates a verifier key, and stores it in `*out`.
+//
+// # Safety
+// `bytes` must be valid for reads of `len` bytes and `out` for a pointer write.
+// The key must be released with `lrs_verifier_key_free`.
+int lrs_verifier_key_load(const uint8_t *bytes, size_t len, struct LrsVerifierKey **out);
+
+// Releases a key from `lrs_verifier_key_load`; a null `key` is ignored.
+//
+// # Safety
+// `key` must be null or come from `lrs_verifier_key_load`, and not be used afterwards.
+void lrs_verifier_key_free(struct LrsVerifierKey *key);
+
+// Decodes a ring and stores it in `*out`.
+//
+// # Safety
+// `bytes` must be valid for reads of `len` bytes and `out` for a pointer write.
+// The ring must be released with `lrs_ring_free`.
+int lrs_ring_load(const uint8_t *bytes, size_t len, struct LrsRing **out);
+
+// Releases a ring from `lrs_ring_load`; a null `ring` is ignored.
+//
+// # Safety
+// `ring` must be null or come from `lrs_ring_load`, and not be used afterwards.
+void lrs_ring_free(struct LrsRing *ring);
+
+// Verifies `signature` on `message` for `ring`, returning `LRS_OK` if it is valid.
+//
+// # Safety
+// `key` and `ring` must come from the loaders, and `message` and `signature` must be
+// valid for reads of `message_len` and `signature_len` bytes.
+int lrs_verify(const struct LrsVerifierKey *key,
+               const struct LrsRing *ring,
+               const uint8_t *message,
+               size_t message_len,
+               const uint8_t *signature,
+               size_t signature_len);
+
+// Writes the link tag of `signature` to the `LRS_LINK_TAG_LEN` bytes at `out`.
+// The tag is read from the signature as is; verify the signature before trusting it.
+//
+// # Safety
+// `key` must come from `lrs_verifier_key_load`, `signature` must be valid for reads of
+// `signature_len` bytes and `out` for writes of `LRS_LINK_TAG_LEN` bytes.
+int lrs_link_tag(const struct LrsVerifierKey *key,
+                 const uint8_t *signature,
+                 size_t signature_len,
+                 uint8_t *out);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* LRS_H */
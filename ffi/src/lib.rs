@@ -0,0 +1,361 @@
+//! A C ABI for verifying LRS signatures over BN254, declared in `include/lrs.h`.
+//!
+//! Verifier keys, rings and signatures cross the boundary as the compressed
+//! `ark-serialize` encodings of `LrsVKey<Bn254>`, `Vec<Fr>` and `Signature<Bn254>`.
+//! A ring is laid out as for `lrs::verify::verify`: its entry 0 is not a member.
+//! Every function returns `LRS_OK` or one of the `LRS_ERR_*` codes.
+//!
+//! The header is generated by `cbindgen` from this file and checked by
+//! `tests::test_header_is_current`; run it with `LRS_FFI_UPDATE_HEADER=1` to rewrite it.
+
+#![warn(unused, future_incompatible, nonstandard_style)]
+
+use ark_bn254::{Bn254, Fr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use lrs_v2::lrs::verify::verify_with_vkey;
+use lrs_v2::lrs::{LrsVKey, Signature, VerifyTime};
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+/// The call succeeded; for `lrs_verify`, the signature is valid.
+pub const LRS_OK: c_int = 0;
+/// A pointer argument is null.
+pub const LRS_ERR_NULL: c_int = 1;
+/// The verifier key does not decode or is malformed.
+pub const LRS_ERR_KEY: c_int = 2;
+/// The ring does not decode, is empty or is larger than the verifier key allows.
+pub const LRS_ERR_RING: c_int = 3;
+/// The signature does not decode.
+pub const LRS_ERR_SIGNATURE: c_int = 4;
+/// The message is not UTF-8.
+pub const LRS_ERR_MESSAGE: c_int = 5;
+/// The signature does not verify.
+pub const LRS_ERR_INVALID: c_int = 6;
+/// The circuit of the verifier key has no link tag.
+pub const LRS_ERR_NO_LINK_TAG: c_int = 7;
+/// The library panicked, which is a bug.
+pub const LRS_ERR_INTERNAL: c_int = 8;
+
+/// The length of a link tag: a BN254 scalar, little-endian.
+pub const LRS_LINK_TAG_LEN: usize = 32;
+
+/// A decoded and validated `LrsVKey`.
+pub struct LrsVerifierKey(LrsVKey<Bn254>);
+
+/// A decoded ring of public keys.
+pub struct LrsRing(Vec<Fr>);
+
+/// Runs `f`, mapping a panic to `on_panic`: unwinding into C is undefined behaviour.
+fn guard(on_panic: c_int, f: impl FnOnce() -> c_int) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+/// The `len` bytes at `ptr`, which may be null if `len` is 0.
+///
+/// # Safety
+/// A non-null `ptr` must be valid for reads of `len` bytes for `'a`.
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (ptr.is_null(), len) {
+        (true, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(ptr, len)),
+    }
+}
+
+/// Decodes and validates a verifier key, and stores it in `*out`.
+///
+/// # Safety
+/// `bytes` must be valid for reads of `len` bytes and `out` for a pointer write.
+/// The key must be released with `lrs_verifier_key_free`.
+#[no_mangle]
+pub unsafe extern "C" fn lrs_verifier_key_load(
+    bytes: *const u8,
+    len: usize,
+    out: *mut *mut LrsVerifierKey,
+) -> c_int {
+    let Some(data) = self::bytes(bytes, len) else {
+        return LRS_ERR_NULL;
+    };
+    if out.is_null() {
+        return LRS_ERR_NULL;
+    }
+    guard(LRS_ERR_INTERNAL, || {
+        let Ok(vkey) = LrsVKey::<Bn254>::deserialize_compressed(data) else {
+            return LRS_ERR_KEY;
+        };
+        if vkey.validate().is_err() {
+            return LRS_ERR_KEY;
+        }
+        *out = Box::into_raw(Box::new(LrsVerifierKey(vkey)));
+        LRS_OK
+    })
+}
+
+/// Releases a key from `lrs_verifier_key_load`; a null `key` is ignored.
+///
+/// # Safety
+/// `key` must be null or come from `lrs_verifier_key_load`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lrs_verifier_key_free(key: *mut LrsVerifierKey) {
+    if !key.is_null() {
+        drop(Box::from_raw(key));
+    }
+}
+
+/// Decodes a ring and stores it in `*out`.
+///
+/// # Safety
+/// `bytes` must be valid for reads of `len` bytes and `out` for a pointer write.
+/// The ring must be released with `lrs_ring_free`.
+#[no_mangle]
+pub unsafe extern "C" fn lrs_ring_load(
+    bytes: *const u8,
+    len: usize,
+    out: *mut *mut LrsRing,
+) -> c_int {
+    let Some(data) = self::bytes(bytes, len) else {
+        return LRS_ERR_NULL;
+    };
+    if out.is_null() {
+        return LRS_ERR_NULL;
+    }
+    guard(
+        LRS_ERR_INTERNAL,
+        || match Vec::<Fr>::deserialize_compressed(data) {
+            Ok(ring) if !ring.is_empty() => {
+                *out = Box::into_raw(Box::new(LrsRing(ring)));
+                LRS_OK
+            }
+            _ => LRS_ERR_RING,
+        },
+    )
+}
+
+/// Releases a ring from `lrs_ring_load`; a null `ring` is ignored.
+///
+/// # Safety
+/// `ring` must be null or come from `lrs_ring_load`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lrs_ring_free(ring: *mut LrsRing) {
+    if !ring.is_null() {
+        drop(Box::from_raw(ring));
+    }
+}
+
+/// Verifies `signature` on `message` for `ring`, returning `LRS_OK` if it is valid.
+///
+/// # Safety
+/// `key` and `ring` must come from the loaders, and `message` and `signature` must be
+/// valid for reads of `message_len` and `signature_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn lrs_verify(
+    key: *const LrsVerifierKey,
+    ring: *const LrsRing,
+    message: *const u8,
+    message_len: usize,
+    signature: *const u8,
+    signature_len: usize,
+) -> c_int {
+    let (Some(key), Some(ring), Some(message), Some(signature)) = (
+        key.as_ref(),
+        ring.as_ref(),
+        bytes(message, message_len),
+        bytes(signature, signature_len),
+    ) else {
+        return LRS_ERR_NULL;
+    };
+    guard(LRS_ERR_INTERNAL, || {
+        if ring.0.len() - 1 > key.0.crs_sma.ring_size_max {
+            return LRS_ERR_RING;
+        }
+        let Ok(message) = std::str::from_utf8(message) else {
+            return LRS_ERR_MESSAGE;
+        };
+        let Ok(signature) = Signature::<Bn254>::deserialize_compressed(signature) else {
+            return LRS_ERR_SIGNATURE;
+        };
+        let mut verify_time = VerifyTime::new();
+        if verify_with_vkey(&key.0, &ring.0, message, &signature, &mut verify_time) {
+            LRS_OK
+        } else {
+            LRS_ERR_INVALID
+        }
+    })
+}
+
+/// Writes the link tag of `signature` to the `LRS_LINK_TAG_LEN` bytes at `out`.
+/// The tag is read from the signature as is; verify the signature before trusting it.
+///
+/// # Safety
+/// `key` must come from `lrs_verifier_key_load`, `signature` must be valid for reads of
+/// `signature_len` bytes and `out` for writes of `LRS_LINK_TAG_LEN` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn lrs_link_tag(
+    key: *const LrsVerifierKey,
+    signature: *const u8,
+    signature_len: usize,
+    out: *mut u8,
+) -> c_int {
+    let (Some(key), Some(signature)) = (key.as_ref(), bytes(signature, signature_len)) else {
+        return LRS_ERR_NULL;
+    };
+    if out.is_null() {
+        return LRS_ERR_NULL;
+    }
+    guard(LRS_ERR_INTERNAL, || {
+        let Ok(signature) = Signature::<Bn254>::deserialize_compressed(signature) else {
+            return LRS_ERR_SIGNATURE;
+        };
+        let Some(tag) = key.0.link_tag(&signature) else {
+            return LRS_ERR_NO_LINK_TAG;
+        };
+        let out = slice::from_raw_parts_mut(out, LRS_LINK_TAG_LEN);
+        tag.serialize_compressed(out).unwrap();
+        LRS_OK
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lrs_v2::constants::*;
+    use lrs_v2::lrs::{setup, sign, verify, CircDescriptor, SignTime};
+    use lrs_v2::sma::ring_gen;
+    use std::ptr;
+
+    fn serialize(value: &impl CanonicalSerialize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        value.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_verify_matches_lrs() {
+        let msg = "test message";
+        let circ_desc = CircDescriptor {
+            num_pub_io: NUM_PUB_IO_LRS_SE,
+            num_commit_witness: NUM_COMMIT_WITNESS_LRS_SE,
+            ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
+            path_prefix: format!("{}/../{}", env!("CARGO_MANIFEST_DIR"), PATH_PREFIX_LRS_SE),
+            circuit_name: CIRCUIT_NAME_LRS_SE.to_string(),
+            hashed_pub_io: None,
+        };
+        let rng = &mut ark_std::test_rng();
+        let (lrs_pvkey, circuit) =
+            setup::setup_with_rng::<Bn254, _>("128".to_string(), 1 << 4, &circ_desc, rng).unwrap();
+
+        let signer_idx = 1usize;
+        let mut ring = ring_gen::<_, Bn254>(1 << 4, signer_idx, rng);
+        ring[signer_idx] = circuit.commit_witness[0];
+        let signature =
+            sign::sign_with_rng(&lrs_pvkey, &circuit, &ring, msg, &mut SignTime::new(), rng).unwrap();
+
+        let vkey_bytes = serialize(&lrs_pvkey.vkey());
+        let ring_bytes = serialize(&ring);
+        let sig_bytes = serialize(&signature);
+
+        unsafe {
+            let mut key = ptr::null_mut();
+            let mut c_ring = ptr::null_mut();
+            assert_eq!(
+                lrs_verifier_key_load(vkey_bytes.as_ptr(), vkey_bytes.len(), &mut key),
+                LRS_OK
+            );
+            assert_eq!(
+                lrs_ring_load(ring_bytes.as_ptr(), ring_bytes.len(), &mut c_ring),
+                LRS_OK
+            );
+
+            let c_verify = |msg: &[u8], sig: &[u8]| {
+                lrs_verify(
+                    key,
+                    c_ring,
+                    msg.as_ptr(),
+                    msg.len(),
+                    sig.as_ptr(),
+                    sig.len(),
+                )
+            };
+            let rust_verify =
+                |msg: &str| verify::verify(&lrs_pvkey, &ring, msg, &signature, &mut VerifyTime::new());
+
+            // Valid where `lrs::verify` accepts, invalid where it rejects
+            assert!(rust_verify(msg));
+            assert_eq!(c_verify(msg.as_bytes(), &sig_bytes), LRS_OK);
+            assert!(!rust_verify("another message"));
+            assert_eq!(c_verify(b"another message", &sig_bytes), LRS_ERR_INVALID);
+
+            assert_eq!(c_verify(&[0xff], &sig_bytes), LRS_ERR_MESSAGE);
+            assert_eq!(c_verify(msg.as_bytes(), &sig_bytes[1..]), LRS_ERR_SIGNATURE);
+            assert_eq!(
+                lrs_verify(
+                    ptr::null(),
+                    c_ring,
+                    msg.as_ptr(),
+                    msg.len(),
+                    sig_bytes.as_ptr(),
+                    0
+                ),
+                LRS_ERR_NULL
+            );
+
+            // The link tag is the `main.L` public input
+            let mut tag = [0u8; LRS_LINK_TAG_LEN];
+            assert_eq!(
+                lrs_link_tag(key, sig_bytes.as_ptr(), sig_bytes.len(), tag.as_mut_ptr()),
+                LRS_OK
+            );
+            assert_eq!(
+                Fr::deserialize_compressed(&tag[..]).unwrap(),
+                signature.instance[1]
+            );
+            assert_eq!(
+                lrs_link_tag(key, sig_bytes.as_ptr(), sig_bytes.len(), ptr::null_mut()),
+                LRS_ERR_NULL
+            );
+
+            lrs_verifier_key_free(key);
+            lrs_ring_free(c_ring);
+
+            // Keys and rings that do not decode are rejected
+            let mut bad_key = ptr::null_mut();
+            let truncated = &vkey_bytes[..vkey_bytes.len() - 1];
+            assert_eq!(
+                lrs_verifier_key_load(truncated.as_ptr(), truncated.len(), &mut bad_key),
+                LRS_ERR_KEY
+            );
+            assert!(bad_key.is_null());
+            let empty = serialize(&Vec::<Fr>::new());
+            let mut bad_ring = ptr::null_mut();
+            assert_eq!(
+                lrs_ring_load(empty.as_ptr(), empty.len(), &mut bad_ring),
+                LRS_ERR_RING
+            );
+            assert_eq!(lrs_ring_load(ptr::null(), 1, &mut bad_ring), LRS_ERR_NULL);
+        }
+    }
+
+    #[test]
+    fn test_header_is_current() {
+        let crate_dir = env!("CARGO_MANIFEST_DIR");
+        let path = format!("{}/include/lrs.h", crate_dir);
+        let config = cbindgen::Config::from_root_or_default(crate_dir);
+        let bindings = cbindgen::Builder::new()
+            .with_crate(crate_dir)
+            .with_config(config)
+            .generate()
+            .unwrap();
+        if std::env::var_os("LRS_FFI_UPDATE_HEADER").is_some() {
+            bindings.write_to_file(&path);
+        }
+        let mut generated = Vec::new();
+        bindings.write(&mut generated);
+        let current = std::fs::read(&path).unwrap_or_default();
+        assert!(
+            generated == current,
+            "{} is stale, set LRS_FFI_UPDATE_HEADER=1 to regenerate",
+            path
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use ark_bn254::Bn254;
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_std::UniformRand;
+use lrs_v2::cc;
+use lrs_v2::cc::utils::SyntheticCircuit;
+use std::time::Instant;
+
+type F = <Bn254 as Pairing>::ScalarField;
+
+/// Reads a `VmRSS`/`VmHWM` line of /proc/self/status, in MiB.
+fn status_mib(key: &str) -> f64 {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let kib: f64 = status
+        .lines()
+        .find(|line| line.starts_with(key))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap()
+        .parse()
+        .unwrap();
+    kib / 1024.0
+}
+
+#[cfg(target_env = "gnu")]
+extern "C" {
+    fn malloc_trim(pad: usize) -> i32;
+}
+
+/// Resets the peak RSS (`VmHWM`) to the current RSS, after returning freed heap pages to
+/// the OS so that the prover's allocations show up in the peak.
+fn reset_peak_rss() {
+    #[cfg(target_env = "gnu")]
+    unsafe {
+        malloc_trim(0);
+    }
+    std::fs::write("/proc/self/clear_refs", "5").expect("Failed to reset the peak RSS");
+}
+
+/// Measures the peak RSS of the cc prover on a `SyntheticCircuit` whose QAP domain has
+/// `2^log_size` elements, for the index-based and the synthesizing prover.
+///
+/// Usage: cc_mem [log_size, default 20]
+fn main() {
+    let log_size: usize = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("log_size must be an integer"))
+        .unwrap_or(20);
+    // One instance variable for the constant 1 and one for the output
+    let num_constraints = (1 << log_size) - 2;
+    let rng = &mut ark_std::test_rng();
+
+    let start = Instant::now();
+    let circuit = |x| SyntheticCircuit::<F> { x, num_constraints };
+    let params = cc::generate_random_parameters::<Bn254, _, _>(circuit(None), 2, rng).unwrap();
+    let index = cc::ProverIndex::new(circuit(None)).unwrap();
+    println!("{} constraints, setup in {:?}", num_constraints, start.elapsed());
+
+    let x = F::rand(rng);
+    let full_assignment = circuit(Some(x)).full_assignment().unwrap();
+    let instance = vec![full_assignment[1].into_bigint()];
+
+    reset_peak_rss();
+    let rss = status_mib("VmRSS");
+    let start = Instant::now();
+    let (proof, _, _) =
+        cc::create_proof_with_index(&index, &params.pk, &full_assignment, rng).unwrap();
+    let elapsed = start.elapsed();
+    let peak = status_mib("VmHWM");
+    assert!(cc::verify_proof(&params.vk, &proof, &instance).unwrap());
+    println!(
+        "create_proof_with_index: {:?}, RSS {:.0} MiB, peak {:.0} MiB (+{:.0} MiB)",
+        elapsed,
+        rss,
+        peak,
+        peak - rss
+    );
+    drop(index);
+    drop(full_assignment);
+
+    reset_peak_rss();
+    let rss = status_mib("VmRSS");
+    let start = Instant::now();
+    let (proof, _, _) = cc::create_random_proof(circuit(Some(x)), &params.pk, rng).unwrap();
+    let elapsed = start.elapsed();
+    let peak = status_mib("VmHWM");
+    assert!(cc::verify_proof(&params.vk, &proof, &instance).unwrap());
+    println!(
+        "create_random_proof: {:?}, RSS {:.0} MiB, peak {:.0} MiB (+{:.0} MiB)",
+        elapsed,
+        rss,
+        peak,
+        peak - rss
+    );
+}
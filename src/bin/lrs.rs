@@ -0,0 +1,447 @@
+//! The `lrs` command-line tool over BN254 and the `circoms/lrs` circuit layout.
+//!
+//! Every command prints a JSON object on stdout. It exits with 0 on success, with 1 if a
+//! signature does not verify or two signatures are not linked, and with 2 on any other
+//! failure, which is printed as `{"error": ...}` on stderr.
+//!
+//! Keys, rings and signatures are stored with `serialize_compressed`, so that a verifier
+//! key and a ring written here load in the C API of `ffi/`; key pairs and rosters are JSON
+//! with field elements as decimal strings, as circom writes them.
+
+use ark_bn254::{Bn254, Fr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::str::FromStr;
+use lrs_v2::constants::*;
+use lrs_v2::lrs::keys::{self, Keypair};
+use lrs_v2::lrs::lrs_circ::LRSCirc;
+use lrs_v2::lrs::witness::WitnessCalculator;
+use lrs_v2::lrs::{setup, sign, verify};
+use lrs_v2::lrs::{CircDescriptor, LrsPVKey, LrsVKey, LrsWitnessError, Signature};
+use lrs_v2::lrs::{SignTime, VerifyTime};
+use rand::rngs::OsRng;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env::args;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::exit;
+
+const USAGE: &str = "\
+usage:
+  lrs setup --circuit <dir> --ring-size-max <n> --pk <file> --vk <file>
+  lrs keygen --out <file>
+  lrs ring build --roster <file> --out <file>
+  lrs sign --circuit <dir> --pk <file> --key <file> --ring <file> --scope <n> --message <file> --out <file>
+  lrs verify --vk <file> --ring <file> --message <file> --signature <file>
+  lrs link --vk <file> --ring <file> <signature> <message> <signature> <message>";
+
+/// A JSON result, and whether it is positive (exit code 0) or negative (exit code 1).
+type CmdResult = Result<(Value, bool), String>;
+
+/// The positional arguments and `--name value` options of a command.
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = args.next().ok_or(format!("--{} needs a value", name))?;
+                    if options.insert(name.to_string(), value.clone()).is_some() {
+                        return Err(format!("--{} is given twice", name));
+                    }
+                }
+                None => positional.push(arg.clone()),
+            }
+        }
+        Ok(Args { positional, options })
+    }
+
+    fn get(&self, name: &str) -> Result<&str, String> {
+        self.options
+            .get(name)
+            .map(|value| value.as_str())
+            .ok_or(format!("missing --{}", name))
+    }
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn write(path: &str, bytes: &[u8]) -> Result<(), String> {
+    fs::write(path, bytes).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn load<T: CanonicalDeserialize>(path: &str) -> Result<T, String> {
+    T::deserialize_compressed(&read(path)?[..]).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn save<T: CanonicalSerialize>(path: &str, value: &T) -> Result<(), String> {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).unwrap();
+    write(path, &bytes)
+}
+
+fn load_json(path: &str) -> Result<Value, String> {
+    serde_json::from_slice(&read(path)?).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn parse_fr(value: &Value) -> Result<Fr, String> {
+    value
+        .as_str()
+        .and_then(|s| Fr::from_str(s).ok())
+        .ok_or(format!("{} is not a field element in decimal", value))
+}
+
+fn parse_point(value: &Value) -> Result<keys::Point, String> {
+    match value.as_array().map(|xy| &xy[..]) {
+        Some([x, y]) => Ok((parse_fr(x)?, parse_fr(y)?)),
+        _ => Err(format!("{} is not a point [x, y]", value)),
+    }
+}
+
+fn load_ring(path: &str) -> Result<Vec<Fr>, String> {
+    let ring: Vec<Fr> = load(path)?;
+    if ring.len() < 2 {
+        return Err(format!("{}: the ring has no members", path));
+    }
+    Ok(ring)
+}
+
+fn load_message(path: &str) -> Result<String, String> {
+    String::from_utf8(read(path)?).map_err(|_| format!("{}: the message is not UTF-8", path))
+}
+
+fn load_vkey(path: &str) -> Result<LrsVKey<Bn254>, String> {
+    let lrs_vkey: LrsVKey<Bn254> = load(path)?;
+    lrs_vkey.validate().map_err(|err| format!("{}: {:?}", path, err))?;
+    Ok(lrs_vkey)
+}
+
+/// The descriptor of the LRS circuit in `dir`, laid out as `circoms/lrs`: read from its
+/// `<name>.manifest.json` if there is one, else the signals of `circoms/lrs`.
+fn circ_desc(dir: &str) -> Result<CircDescriptor, String> {
+    let dir = dir.trim_end_matches('/');
+    let circuit_name = Path::new(dir)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(format!("{} is not a circuit directory", dir))?
+        .to_string();
+    for file in [
+        format!("{}.sym", circuit_name),
+        format!("{}_js/{}.wasm", circuit_name, circuit_name),
+    ] {
+        if !Path::new(dir).join(&file).is_file() {
+            return Err(format!("{}: missing {}", dir, file));
+        }
+    }
+    // `LRSCirc` reads the constraints from `_constraints.json`, else from the `.r1cs` file
+    let constraints = format!("{}_constraints.json", circuit_name);
+    let r1cs = format!("{}.r1cs", circuit_name);
+    if !Path::new(dir).join(&constraints).is_file() && !Path::new(dir).join(&r1cs).is_file() {
+        return Err(format!("{}: missing {} or {}", dir, constraints, r1cs));
+    }
+    let manifest = Path::new(dir).join(format!("{}.manifest.json", circuit_name));
+    if manifest.is_file() {
+        let manifest = manifest.to_string_lossy();
+        return CircDescriptor::from_manifest(&manifest).map_err(|err| format!("{}: {}", manifest, err));
+    }
+    Ok(CircDescriptor {
+        num_pub_io: NUM_PUB_IO_LRS_SE,
+        num_commit_witness: NUM_COMMIT_WITNESS_LRS_SE,
+        ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
+        path_prefix: format!("{}/", dir),
+        circuit_name,
+        hashed_pub_io: None,
+    })
+}
+
+fn cmd_setup(args: &Args) -> CmdResult {
+    let circ_desc = circ_desc(args.get("circuit")?)?;
+    // `setup` synthesizes the circuit with the example witness that circom writes
+    let witness = format!("{}{}_js/witness.wtns.json", circ_desc.path_prefix, circ_desc.circuit_name);
+    if !Path::new(&witness).is_file() {
+        return Err(format!("missing {}", witness));
+    }
+    let ring_size_max: usize = args
+        .get("ring-size-max")?
+        .parse()
+        .map_err(|_| "--ring-size-max is not a number".to_string())?;
+    if ring_size_max == 0 {
+        return Err("--ring-size-max must be positive".to_string());
+    }
+
+    let (lrs_pvkey, _) =
+        setup::setup_with_rng::<Bn254, _>("128".to_string(), ring_size_max, &circ_desc, &mut OsRng)
+            .map_err(|err| format!("{}: {:?}", circ_desc.circuit_name, err))?;
+    save(args.get("pk")?, &lrs_pvkey)?;
+    save(args.get("vk")?, &lrs_pvkey.vkey())?;
+    Ok((
+        json!({ "pk": args.get("pk")?, "vk": args.get("vk")?, "ring_size_max": ring_size_max }),
+        true,
+    ))
+}
+
+fn public_json(keypair: &Keypair) -> Value {
+    json!({
+        "pk": [keypair.pk.0.to_string(), keypair.pk.1.to_string()],
+        "phi": keypair.phi().to_string(),
+    })
+}
+
+fn cmd_keygen(args: &Args) -> CmdResult {
+    let out = args.get("out")?;
+    if Path::new(out).exists() {
+        return Err(format!("{}: refusing to overwrite", out));
+    }
+    let keypair = Keypair::generate(&mut OsRng);
+    let mut key = public_json(&keypair);
+    key["sk"] = json!(keypair.sk.to_string());
+
+    // The secret key is readable by its owner only
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(out)
+        .and_then(|mut file| file.write_all(serde_json::to_string_pretty(&key).unwrap().as_bytes()))
+        .map_err(|err| format!("{}: {}", out, err))?;
+    Ok((public_json(&keypair), true))
+}
+
+fn cmd_ring_build(args: &Args) -> CmdResult {
+    let roster = args.get("roster")?;
+    let entries = load_json(roster)?;
+    let entries = entries
+        .as_array()
+        .ok_or(format!("{}: the roster is not an array of public keys", roster))?;
+    let mut ring = vec![Fr::from(0u64)]; // entry 0 is not a member
+    for entry in entries {
+        let pk = parse_point(&entry["pk"])?;
+        if !keys::is_on_curve(&pk) {
+            return Err(format!("{}: {} is not on BabyJubjub", roster, entry["pk"]));
+        }
+        let phi = keys::phi(&pk);
+        if ring.contains(&phi) {
+            return Err(format!("{}: {} is listed twice", roster, entry["pk"]));
+        }
+        ring.push(phi);
+    }
+    if ring.len() < 2 {
+        return Err(format!("{}: the roster is empty", roster));
+    }
+    save(args.get("out")?, &ring)?;
+    Ok((json!({ "ring": args.get("out")?, "size": ring.len() - 1 }), true))
+}
+
+fn cmd_sign(args: &Args) -> CmdResult {
+    let circ_desc = circ_desc(args.get("circuit")?)?;
+    let pk_file = args.get("pk")?;
+    let lrs_pvkey: LrsPVKey<Bn254> = load(pk_file)?;
+    lrs_pvkey.validate().map_err(|err| format!("{}: {:?}", pk_file, err))?;
+    let ring = load_ring(args.get("ring")?)?;
+    if ring.len() - 1 > lrs_pvkey.crs_sma.ring_size_max {
+        return Err(format!(
+            "the ring has {} members but the keys allow {}",
+            ring.len() - 1,
+            lrs_pvkey.crs_sma.ring_size_max
+        ));
+    }
+    let message = load_message(args.get("message")?)?;
+    let sc = parse_fr(&json!(args.get("scope")?))?;
+
+    let key_file = args.get("key")?;
+    let key = load_json(key_file)?;
+    let keypair = Keypair::from_sk(parse_fr(&key["sk"])?);
+    if key.get("pk").is_some() && parse_point(&key["pk"])? != keypair.pk {
+        return Err(format!("{}: pk does not match sk", key_file));
+    }
+
+    let inputs = vec![
+        ("sk".to_string(), vec![keypair.sk]),
+        ("phi".to_string(), vec![keypair.phi()]),
+        ("sc".to_string(), vec![sc]),
+        ("L".to_string(), vec![keypair.link_tag(sc)]),
+    ];
+    let wasm = format!(
+        "{}{}_js/{}.wasm",
+        circ_desc.path_prefix, circ_desc.circuit_name, circ_desc.circuit_name
+    );
+    let witness = WitnessCalculator::from_file(&wasm)
+        .and_then(|mut calculator| calculator.calculate(&inputs))
+        .map_err(|err| format!("{}: {:?}", wasm, err))?;
+    let circuit = LRSCirc::construct_with_witness(&circ_desc, witness)
+        .map_err(|err| format!("{}: {}", circ_desc.circuit_name, err))?;
+
+    let mut sign_time = SignTime::new();
+    let signature = sign::sign_with_rng(&lrs_pvkey, &circuit, &ring, &message, &mut sign_time, &mut OsRng)
+        .map_err(|err| match err {
+            LrsWitnessError::NotInRing => format!("{}: the key is not a member of the ring", key_file),
+            err => format!("{}: {:?}", circ_desc.circuit_name, err),
+        })?;
+    save(args.get("out")?, &signature)?;
+    Ok((
+        json!({
+            "signature": args.get("out")?,
+            "link_tag": lrs_pvkey.vkey().link_tag(&signature).map(|tag| tag.to_string()),
+        }),
+        true,
+    ))
+}
+
+fn verifies(lrs_vkey: &LrsVKey<Bn254>, ring: &Vec<Fr>, message: &str, signature: &Signature<Bn254>) -> bool {
+    verify::verify_with_vkey(lrs_vkey, ring, message, signature, &mut VerifyTime::new())
+}
+
+/// The ring at `path`, if the verifier key allows its size.
+fn load_ring_for(lrs_vkey: &LrsVKey<Bn254>, path: &str) -> Result<Vec<Fr>, String> {
+    let ring = load_ring(path)?;
+    if ring.len() - 1 > lrs_vkey.crs_sma.ring_size_max {
+        return Err(format!(
+            "the ring has {} members but the keys allow {}",
+            ring.len() - 1,
+            lrs_vkey.crs_sma.ring_size_max
+        ));
+    }
+    Ok(ring)
+}
+
+fn cmd_verify(args: &Args) -> CmdResult {
+    let lrs_vkey = load_vkey(args.get("vk")?)?;
+    let ring = load_ring_for(&lrs_vkey, args.get("ring")?)?;
+    let message = load_message(args.get("message")?)?;
+    let signature: Signature<Bn254> = load(args.get("signature")?)?;
+
+    let valid = verifies(&lrs_vkey, &ring, &message, &signature);
+    Ok((
+        json!({
+            "valid": valid,
+            "link_tag": lrs_vkey.link_tag(&signature).map(|tag| tag.to_string()),
+        }),
+        valid,
+    ))
+}
+
+/// Verifies two signatures, each on its own message, and compares their link tags: they are
+/// linked if both are valid and the tags are equal.
+fn cmd_link(args: &Args) -> CmdResult {
+    let lrs_vkey = load_vkey(args.get("vk")?)?;
+    let ring = load_ring_for(&lrs_vkey, args.get("ring")?)?;
+    let [a, a_message, b, b_message] = &args.positional[..] else {
+        return Err("link takes two signatures, each followed by its message".to_string());
+    };
+    let mut valid = Vec::new();
+    let mut tags = Vec::new();
+    for (path, message) in [(a, a_message), (b, b_message)] {
+        let signature: Signature<Bn254> = load(path)?;
+        let tag = lrs_vkey
+            .link_tag(&signature)
+            .ok_or(format!("{}: the circuit has no link tag", path))?;
+        valid.push(verifies(&lrs_vkey, &ring, &load_message(message)?, &signature));
+        tags.push(tag);
+    }
+    let linked = valid[0] && valid[1] && tags[0] == tags[1];
+    Ok((
+        json!({
+            "linked": linked,
+            "valid": valid,
+            "link_tags": [tags[0].to_string(), tags[1].to_string()],
+        }),
+        linked,
+    ))
+}
+
+fn run(args: &[String]) -> CmdResult {
+    let (command, rest) = args.split_first().ok_or(USAGE.to_string())?;
+    match (command.as_str(), rest.first().map(|s| s.as_str())) {
+        ("ring", Some("build")) => cmd_ring_build(&Args::parse(&rest[1..])?),
+        ("setup", _) => cmd_setup(&Args::parse(rest)?),
+        ("keygen", _) => cmd_keygen(&Args::parse(rest)?),
+        ("sign", _) => cmd_sign(&Args::parse(rest)?),
+        ("verify", _) => cmd_verify(&Args::parse(rest)?),
+        ("link", _) => cmd_link(&Args::parse(rest)?),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = args().skip(1).collect();
+    match run(&args) {
+        Ok((result, positive)) => {
+            println!("{}", result);
+            exit(if positive { 0 } else { 1 });
+        }
+        Err(err) if err == USAGE => {
+            eprintln!("{}", USAGE);
+            exit(2);
+        }
+        Err(err) => {
+            eprintln!("{}", json!({ "error": err }));
+            exit(2);
+        }
+    }
+}
+
+#[test]
+fn test_lrs_cli() {
+    let dir = std::env::temp_dir().join(format!("lrs-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    let cli = |line: &str| {
+        let args = line.split(' ').map(|arg| match arg.strip_prefix('@') {
+            Some(name) => path(name),
+            None => arg.to_string(),
+        });
+        run(&args.collect::<Vec<_>>())
+    };
+
+    let (result, _) = cli("setup --circuit circoms/lrs --ring-size-max 16 --pk @pk --vk @vk").unwrap();
+    assert_eq!(result["ring_size_max"], 16);
+    let (alice, _) = cli("keygen --out @alice.json").unwrap();
+    let (bob, _) = cli("keygen --out @bob.json").unwrap();
+    assert!(cli("keygen --out @alice.json").is_err());
+
+    write(&path("roster.json"), json!([bob, alice]).to_string().as_bytes()).unwrap();
+    assert_eq!(cli("ring build --roster @roster.json --out @ring").unwrap().0["size"], 2);
+    write(&path("msg"), b"test message").unwrap();
+    write(&path("other"), b"another message").unwrap();
+
+    let sign = "sign --circuit circoms/lrs --pk @pk --ring @ring --message @msg";
+    let (signed, _) = cli(&format!("{} --key @alice.json --scope 7 --out @a1", sign)).unwrap();
+    cli(&format!("{} --key @alice.json --scope 7 --out @a2", sign)).unwrap();
+    cli(&format!("{} --key @alice.json --scope 8 --out @a3", sign)).unwrap();
+    cli(&format!("{} --key @bob.json --scope 7 --out @b1", sign)).unwrap();
+
+    let (result, valid) = cli("verify --vk @vk --ring @ring --message @msg --signature @a1").unwrap();
+    assert!(valid);
+    assert_eq!(result["link_tag"], signed["link_tag"]);
+    let (result, valid) = cli("verify --vk @vk --ring @ring --message @other --signature @a1").unwrap();
+    assert!(!valid && result["valid"] == false);
+
+    assert!(cli("link --vk @vk --ring @ring @a1 @msg @a2 @msg").unwrap().1);
+    assert!(!cli("link --vk @vk --ring @ring @a1 @msg @a3 @msg").unwrap().1);
+    assert!(!cli("link --vk @vk --ring @ring @a1 @msg @b1 @msg").unwrap().1);
+    // Equal tags do not link a signature that does not verify
+    let (result, linked) = cli("link --vk @vk --ring @ring @a1 @msg @a2 @other").unwrap();
+    assert!(!linked);
+    assert_eq!(result["valid"], json!([true, false]));
+    assert_eq!(result["link_tags"][0], result["link_tags"][1]);
+    assert!(cli("link --vk @vk --ring @ring @a1 @a2").is_err());
+
+    // Bob is not in a ring of Alice alone
+    write(&path("alice_only.json"), json!([alice]).to_string().as_bytes()).unwrap();
+    cli("ring build --roster @alice_only.json --out @ring1").unwrap();
+    let err = cli("sign --circuit circoms/lrs --pk @pk --ring @ring1 --message @msg --key @bob.json --scope 7 --out @x");
+    assert!(err.unwrap_err().contains("not a member"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
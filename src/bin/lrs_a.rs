@@ -1,140 +1,73 @@
-use ark_ec::pairing::Pairing;
-use ark_ff::PrimeField;
+use ark_bn254::Fr;
+use ark_std::rand::{rngs::StdRng, SeedableRng};
 use ark_std::time::Instant;
-use ark_std::One;
-use lrs_v2::cc;
-use lrs_v2::cc::helpers::mimc_constants_round91;
-use lrs_v2::cc::helpers::multi_mimc7;
-use lrs_v2::cc::{verify_proof, CcPVKey};
-use lrs_v2::constants::*;
-use lrs_v2::lrs::lrs_circ::LRSCirc;
-use lrs_v2::lrs::structures::CircDescriptor;
+use ark_std::UniformRand;
+use lrs_v2::lrs::keys::Keypair;
+use lrs_v2::lrs::merkle_variant::{self, MerkleTree};
 use std::env;
+use std::time::Duration;
 
-fn lrs_a(n_iters: usize) {
-    type E = ark_bn254::Bn254;
-    type F = <E as Pairing>::ScalarField;
-
+/// Benchmarks the Merkle-tree variant over the lrs_a circuits for trees of depth `low` to
+/// `high`, with full rings of `2^depth` members.
+fn lrs_a(n_iters: usize, low: usize, high: usize) {
     // 将println 输出重定向到文件
     // ./target/release/lrs_a > test_lrs_a.log
-    let ioputs_names = [
-        IOPUTS_NAME_LRS_A_3.to_vec(),
-        IOPUTS_NAME_LRS_A_4.to_vec(),
-        IOPUTS_NAME_LRS_A_5.to_vec(),
-        IOPUTS_NAME_LRS_A_6.to_vec(),
-        IOPUTS_NAME_LRS_A_7.to_vec(),
-        IOPUTS_NAME_LRS_A_8.to_vec(),
-        IOPUTS_NAME_LRS_A_9.to_vec(),
-        IOPUTS_NAME_LRS_A_10.to_vec(),
-        IOPUTS_NAME_LRS_A_11.to_vec(),
-        IOPUTS_NAME_LRS_A_12.to_vec(),
-        IOPUTS_NAME_LRS_A_13.to_vec(),
-        IOPUTS_NAME_LRS_A_14.to_vec(),
-        IOPUTS_NAME_LRS_A_15.to_vec(),
-        IOPUTS_NAME_LRS_A_16.to_vec(),
-        IOPUTS_NAME_LRS_A_17.to_vec(),
-        IOPUTS_NAME_LRS_A_18.to_vec(),
-        IOPUTS_NAME_LRS_A_19.to_vec(),
-        IOPUTS_NAME_LRS_A_20.to_vec(),
-    ];
-
-    let circuit_names = [
-        CIRCUIT_NAME_LRS_A_3,
-        CIRCUIT_NAME_LRS_A_4,
-        CIRCUIT_NAME_LRS_A_5,
-        CIRCUIT_NAME_LRS_A_6,
-        CIRCUIT_NAME_LRS_A_7,
-        CIRCUIT_NAME_LRS_A_8,
-        CIRCUIT_NAME_LRS_A_9,
-        CIRCUIT_NAME_LRS_A_10,
-        CIRCUIT_NAME_LRS_A_11,
-        CIRCUIT_NAME_LRS_A_12,
-        CIRCUIT_NAME_LRS_A_13,
-        CIRCUIT_NAME_LRS_A_14,
-        CIRCUIT_NAME_LRS_A_15,
-        CIRCUIT_NAME_LRS_A_16,
-        CIRCUIT_NAME_LRS_A_17,
-        CIRCUIT_NAME_LRS_A_18,
-        CIRCUIT_NAME_LRS_A_19,
-        CIRCUIT_NAME_LRS_A_20,
-    ];
-
-    let low = 3;
-    let high = 20;
-    for i in low..=high {
-        let circ_desc = CircDescriptor {
-            num_pub_io: NUM_PUB_IO_LRS_A,
-            num_commit_witness: NUM_COMMIT_WITNESS_LRS_A,
-            ioputs_name: ioputs_names[i - low].iter().map(|s| s.to_string()).collect(),
-            path_prefix: PATH_PREFIX_LRS_A.to_string(),
-            circuit_name: circuit_names[i - low].to_string(),
-        };
-
-        let circ = LRSCirc::<F>::construct(&circ_desc).unwrap();
-
-        println!("Circuit name: {:?}", circ_desc.circuit_name);
-        println!("Public inputs:");
-        for i in 0..circ_desc.num_pub_io {
-            let value: F = F::from_bigint(circ.instance[i].into_bigint()).unwrap();
-            println!("{}: {}", circ_desc.ioputs_name[i], value);
-        }
+    'depths: for depth in low..=high {
+        println!("Circuit name: lrs_a_{}", depth);
+        let rng = &mut StdRng::seed_from_u64(0u64);
+
+        // The signer, among random members
+        let keypair = Keypair::generate(rng);
+        let signer_idx = 1;
+        let mut ring = (0..1usize << depth).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+        ring[signer_idx] = keypair.phi();
+        let sc = Fr::from(20000928u64);
+        let message = "12345";
 
         let mut setup_times = Vec::new();
-        let mut proof_times = Vec::new();
+        let mut sign_times = Vec::new();
         let mut verify_times = Vec::new();
 
         for repeat in 0..n_iters {
-            let is_print = if repeat == 0 { Some(true) } else { None };
-
-            let rng = &mut ark_std::test_rng();
-
             let start = Instant::now();
-            let crs_cc: CcPVKey<E> =
-                cc::generate_random_parameters(circ.clone(), circ_desc.num_commit_witness, rng)
-                    .unwrap();
+            let pvkey = match merkle_variant::setup_with_rng(depth, rng) {
+                Ok(pvkey) => pvkey,
+                Err(err) => {
+                    // `lrs_a.sh` has not built this depth
+                    println!("Skipped: {:?}\n\n", err);
+                    continue 'depths;
+                }
+            };
             let setup_time = start.elapsed();
             setup_times.push(setup_time);
-            // println!("Repeat {:?}: LRS_A Setup time: {:?}", repeat, setup_time);
 
+            // Signing builds the tree of the ring and computes the witness
             let start = Instant::now();
-            let (cc_proof, _, _) = cc::create_random_proof(circ.clone(), &crs_cc.pk, rng).unwrap();
-            let c = mimc_constants_round91::<E>();
-            for _ in 0..(1 << i) - 1 {
-                let _ = multi_mimc7::<E>(&vec![F::one(), F::one()], 2, &c);
-            }
-            let proof_time = start.elapsed();
-            proof_times.push(proof_time);
-            // println!("Repeat {:?}: LRS_A Proof time: {:?}", repeat, proof_time);
+            let signature = merkle_variant::sign_with_rng(&pvkey, keypair.sk, &ring, message, sc, rng).unwrap();
+            let sign_time = start.elapsed();
+            sign_times.push(sign_time);
 
-            let instance = circ
-                .instance
-                .iter()
-                .map(|v| v.into_bigint())
-                .collect::<Vec<<F as ark_ff::PrimeField>::BigInt>>();
+            // The verifier recomputes the root of the ring
+            let vkey = pvkey.vkey();
             let start = Instant::now();
-            let result = verify_proof(&crs_cc.vk, &cc_proof, &instance);
-            // The verifier also need to recompute the Merkle root, which takes 2^15 - 1 times MIMC hash
-            let c = mimc_constants_round91::<E>();
-            for _ in 0..(1 << i) - 1 {
-                let _ = multi_mimc7::<E>(&vec![F::one(), F::one()], 2, &c);
-            }
+            let root = MerkleTree::new(&ring, depth).unwrap().root();
+            let result = merkle_variant::verify(&vkey, root, message, &signature);
             let verify_time = start.elapsed();
             verify_times.push(verify_time);
 
-            if is_print.unwrap_or(false) {
+            if repeat == 0 {
                 println!("LRS_A Setup time: {:?}", setup_time);
-                println!("LRS_A Proof time: {:?}", proof_time);
+                println!("LRS_A Sign time: {:?}", sign_time);
                 println!("LRS_A verification time: {:?}", verify_time);
             }
-            // println!("Repeat {:?}: LRS_A Verify time: {:?}\n", repeat, verify_time);
 
-            assert!(result.is_ok());
+            assert!(result);
         }
-        let avg_setup_time = setup_times.iter().sum::<std::time::Duration>() / n_iters as u32;
-        let avg_proof_time = proof_times.iter().sum::<std::time::Duration>() / n_iters as u32;
-        let avg_verify_time = verify_times.iter().sum::<std::time::Duration>() / n_iters as u32;
-        println!("Average Setup time for : {:?}", avg_setup_time);
-        println!("Average Proof time: {:?}", avg_proof_time);
+        let avg_setup_time = setup_times.iter().sum::<Duration>() / n_iters as u32;
+        let avg_sign_time = sign_times.iter().sum::<Duration>() / n_iters as u32;
+        let avg_verify_time = verify_times.iter().sum::<Duration>() / n_iters as u32;
+        println!("Average Setup time: {:?}", avg_setup_time);
+        println!("Average Sign time: {:?}", avg_sign_time);
         println!("Average Verify time: {:?}\n\n", avg_verify_time);
     }
 }
@@ -147,7 +80,10 @@ fn main() {
     } else {
         1
     };
-    println!("n_iters: {}", n_iters);
+    // The depths of the circuits `circoms/lrs_a/lrs_a.sh` built, 3 to 20 by default
+    let low: usize = args.get(2).and_then(|arg| arg.parse().ok()).unwrap_or(3);
+    let high: usize = args.get(3).and_then(|arg| arg.parse().ok()).unwrap_or(20);
+    println!("n_iters: {}, depths: {}..={}", n_iters, low, high);
 
-    lrs_a(n_iters);
+    lrs_a(n_iters, low, high);
 }
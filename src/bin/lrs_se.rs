@@ -24,6 +24,7 @@ fn lrs_signature(n_iters: usize, log2_low: usize, log2_high: usize) {
         ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
         path_prefix: PATH_PREFIX_LRS_SE.to_string(),
         circuit_name: CIRCUIT_NAME_LRS_SE.to_string(),
+        hashed_pub_io: None,
     };
 
     let signer_idx = 1usize;
@@ -33,7 +34,8 @@ fn lrs_signature(n_iters: usize, log2_low: usize, log2_high: usize) {
         let ring_size_max = 1 << ring_size_max_log;
         println!("Ring size: {}", ring_size_max);
         let (lrs_pvkey, circuit) =
-            setup::setup::<ark_bn254::Bn254>(security_par.clone(), ring_size_max, &circ_desc);
+            setup::setup_with_rng::<ark_bn254::Bn254, _>(security_par.clone(), ring_size_max, &circ_desc, rng)
+                .unwrap();
 
         println!("Public inputs:");
         for i in 0..circ_desc.num_pub_io {
@@ -62,7 +64,8 @@ fn lrs_signature(n_iters: usize, log2_low: usize, log2_high: usize) {
             let is_print = if _iter == 1 { Some(true) } else { None };
 
             let mut sign_time = SignTime::new();
-            let signature = sign::sign::<ark_bn254::Bn254>(&lrs_pvkey, &circuit, &ring, msg, &mut sign_time);
+            let signature = sign::sign_with_rng(&lrs_pvkey, &circuit, &ring, msg, &mut sign_time, rng)
+                .expect("The witness does not satisfy the circuit");
 
             let mut verify_time = VerifyTime::new();
             let result =
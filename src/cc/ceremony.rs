@@ -0,0 +1,604 @@
+use crate::cc::helpers::hash_to_field;
+use crate::cc::generator::{check_commit_indices, uncommitted_witness_indices};
+use crate::cc::{r1cs_to_qap::R1CStoQAP, CcPVKey, CommitSlot, ProvingKey, VerifyingKey};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup, Group, VariableBaseMSM};
+use ark_ff::{Field, One, UniformRand, Zero};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisError, SynthesisMode,
+};
+use ark_serialize::*;
+use ark_std::rand::RngCore;
+use ark_std::{cfg_iter, end_timer, start_timer};
+use ark_std::ops::Neg;
+use ark_std::vec::Vec;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// The circuit-independent powers a phase-2 ceremony starts from, laid out as the
+/// `tauG1`, `tauG2`, `alphaTauG1`, `betaTauG1` and `betaG2` sections of a snarkjs `.ptau`.
+///
+/// A circuit whose QAP domain has size `m` needs `2m - 1` powers in `tau_g1` and `m`
+/// powers in each of the others.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Phase1Powers<E: Pairing> {
+    pub tau_g1: Vec<E::G1Affine>,
+    pub tau_g2: Vec<E::G2Affine>,
+    pub alpha_tau_g1: Vec<E::G1Affine>,
+    pub beta_tau_g1: Vec<E::G1Affine>,
+    pub beta_g2: E::G2Affine,
+}
+
+impl<E: Pairing> Phase1Powers<E> {
+    /// Phase-1 powers for a domain of size `domain_size` from known toxic waste.
+    pub fn new(
+        domain_size: usize,
+        tau: E::ScalarField,
+        alpha: E::ScalarField,
+        beta: E::ScalarField,
+    ) -> Self {
+        let g1_generator = E::G1::generator();
+        let g2_generator = E::G2::generator();
+        let tau_pows = (0..2 * domain_size - 1)
+            .scan(E::ScalarField::one(), |state, _| {
+                let current = *state;
+                *state *= tau;
+                Some(current)
+            })
+            .collect::<Vec<_>>();
+
+        let scaled_g1 = |s: E::ScalarField, len: usize| {
+            let points = cfg_iter!(tau_pows[..len])
+                .map(|t_i| g1_generator * (s * t_i))
+                .collect::<Vec<_>>();
+            E::G1::normalize_batch(&points)
+        };
+        let tau_g2 = cfg_iter!(tau_pows[..domain_size])
+            .map(|t_i| g2_generator * t_i)
+            .collect::<Vec<_>>();
+
+        Self {
+            tau_g1: scaled_g1(E::ScalarField::one(), 2 * domain_size - 1),
+            tau_g2: E::G2::normalize_batch(&tau_g2),
+            alpha_tau_g1: scaled_g1(alpha, domain_size),
+            beta_tau_g1: scaled_g1(beta, domain_size),
+            beta_g2: (g2_generator * beta).into_affine(),
+        }
+    }
+}
+
+/// A public record of the update `s -> x * s` of one phase-2 trapdoor `s`.
+///
+/// `x_g1` commits to the secret factor, `value_g2` is `s * H` after the update, and
+/// `(pok_r, pok_z)` is a Schnorr proof of knowledge of `x` bound to the previous `s * H`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct TrapdoorUpdate<E: Pairing> {
+    pub x_g1: E::G1Affine,
+    pub value_g2: E::G2Affine,
+    pub pok_r: E::G1Affine,
+    pub pok_z: E::ScalarField,
+}
+
+/// A public record of one contribution to the cc phase 2, rerandomizing `delta` and
+/// the `gamma_i`, `eta_i` of every commitment slot. `delta_g1` is `pk.delta_g1` after the update.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CcContribution<E: Pairing> {
+    pub delta_g1: E::G1Affine,
+    pub delta: TrapdoorUpdate<E>,
+    pub gamma: Vec<TrapdoorUpdate<E>>,
+    pub eta: Vec<TrapdoorUpdate<E>>,
+}
+
+/// The contributions applied, in order, to the key returned by `initial_parameters`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CcTranscript<E: Pairing> {
+    pub contributions: Vec<CcContribution<E>>,
+}
+
+impl<E: Pairing> Default for CcTranscript<E> {
+    fn default() -> Self {
+        Self {
+            contributions: Vec::new(),
+        }
+    }
+}
+
+impl<E: Pairing> CcTranscript<E> {
+    /// `eta_i * H` of the latest key; `eta_i` only appears divided by `delta` and `gamma_i`
+    /// in the key itself.
+    fn eta_g2(&self, g2_generator: E::G2, num_slots: usize) -> Vec<E::G2Affine> {
+        self.contributions.last().map_or(
+            vec![g2_generator.into_affine(); num_slots],
+            |c| c.eta.iter().map(|update| update.value_g2).collect(),
+        )
+    }
+}
+
+/// Derives the key for `delta = gamma = eta = 1` from `phase1`; every ceremony for the
+/// circuit starts from it, and anyone holding the circuit and the phase-1 powers can recompute it.
+pub fn initial_parameters<E, C>(
+    circuit: C,
+    commit_witness_count: usize,
+    phase1: &Phase1Powers<E>,
+) -> crate::Result<CcPVKey<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    initial_parameters_with_slots(circuit, &[(0..commit_witness_count).collect()], phase1)
+}
+
+/// `initial_parameters` for a key with one commitment slot per list of witness indices.
+///
+/// This is `generate_parameters_with_slots` with the evaluation point `tau` only known in
+/// the exponent: the Lagrange bases `[L_j(tau)]` are interpolated from the powers of `tau`
+/// with an inverse FFT, and `[z(tau) * tau^i] = [tau^(m + i)] - [tau^i]` for the subgroup
+/// domain of size `m`.
+pub fn initial_parameters_with_slots<E, C>(
+    circuit: C,
+    commit_witness_indices: &[Vec<usize>],
+    phase1: &Phase1Powers<E>,
+) -> crate::Result<CcPVKey<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    type D<F> = GeneralEvaluationDomain<F>;
+
+    let setup_time = start_timer!(|| "Groth16::Phase2::Initial");
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+
+    let domain_size = cs.num_constraints() + cs.num_instance_variables();
+    let domain = D::<E::ScalarField>::new(domain_size)
+        .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+    let m = domain.size();
+    if phase1.tau_g1.len() < 2 * m - 1
+        || phase1.tau_g2.len() < m
+        || phase1.alpha_tau_g1.len() < m
+        || phase1.beta_tau_g1.len() < m
+    {
+        return Err(SynthesisError::PolynomialDegreeTooLarge.into());
+    }
+    let num_instance = cs.num_instance_variables();
+    let num_witness = cs.num_witness_variables();
+    check_commit_indices(commit_witness_indices, num_witness)?;
+
+    let lagrange_time = start_timer!(|| "Interpolate Lagrange bases");
+    let lagrange_g1 = |powers: &[E::G1Affine]| {
+        let powers = powers[..m].iter().map(|p| p.into_group()).collect::<Vec<_>>();
+        E::G1::normalize_batch(&domain.ifft(&powers))
+    };
+    let l_g1 = lagrange_g1(&phase1.tau_g1);
+    let alpha_l_g1 = lagrange_g1(&phase1.alpha_tau_g1);
+    let beta_l_g1 = lagrange_g1(&phase1.beta_tau_g1);
+    let l_g2 = {
+        let powers = phase1.tau_g2[..m].iter().map(|p| p.into_group()).collect::<Vec<_>>();
+        E::G2::normalize_batch(&domain.ifft(&powers))
+    };
+    end_timer!(lagrange_time);
+
+    let reduction_time = start_timer!(|| "R1CS to QAP Instance Map in the exponent");
+    let (a_query, b_g1_query, _) =
+        R1CStoQAP::instance_map_in_exponent::<E::G1>(cs.clone(), Some(&l_g1), Some(&l_g1), None)?;
+    let (_, b_g2_query, _) =
+        R1CStoQAP::instance_map_in_exponent::<E::G2>(cs.clone(), None, Some(&l_g2), None)?;
+    let (beta_a, alpha_b, c) = R1CStoQAP::instance_map_in_exponent::<E::G1>(
+        cs,
+        Some(&beta_l_g1),
+        Some(&alpha_l_g1),
+        Some(&l_g1),
+    )?;
+    end_timer!(reduction_time);
+
+    let abc = cfg_iter!(beta_a)
+        .zip(&alpha_b)
+        .zip(&c)
+        .map(|((a, b), c)| *a + b + c)
+        .collect::<Vec<_>>();
+    let abc = E::G1::normalize_batch(&abc);
+
+    let h_query = cfg_iter!(phase1.tau_g1[..m - 1])
+        .zip(&phase1.tau_g1[m..2 * m - 1])
+        .map(|(t_i, t_mi)| t_mi.into_group() - t_i)
+        .collect::<Vec<_>>();
+
+    let g1_generator = phase1.tau_g1[0].into_group();
+    let g2_generator = phase1.tau_g2[0].into_group();
+    let alpha_g1 = phase1.alpha_tau_g1[0];
+
+    let pk = ProvingKey {
+        g1_generator,
+        g2_generator,
+        alpha_g1,
+        beta_g1: phase1.beta_tau_g1[0],
+        beta_g2: phase1.beta_g2,
+        delta_g1: phase1.tau_g1[0],
+        delta_g2: phase1.tau_g2[0],
+        a_query: E::G1::normalize_batch(&a_query),
+        b_g1_query: E::G1::normalize_batch(&b_g1_query),
+        b_g2_query: E::G2::normalize_batch(&b_g2_query),
+        h_query: E::G1::normalize_batch(&h_query),
+        ucomm_wit_abc_query: uncommitted_witness_indices(commit_witness_indices, num_witness)
+            .into_iter()
+            .map(|i| abc[num_instance + i])
+            .collect(),
+        commit_slots: commit_witness_indices
+            .iter()
+            .map(|indices| CommitSlot {
+                indices: indices.clone(),
+                eta_delta_inv_g1: phase1.tau_g1[0],
+                eta_gamma_inv_g1: phase1.tau_g1[0],
+                comm_wit_abc_query: indices.iter().map(|i| abc[num_instance + i]).collect(),
+            })
+            .collect(),
+    };
+
+    let vk = VerifyingKey::<E> {
+        g1_generator,
+        g2_generator,
+        alpha_beta_gt: E::pairing(alpha_g1, phase1.beta_g2),
+        delta_g2_neg_pc: g2_generator.neg().into_affine(),
+        gamma_g2_neg_pc: vec![g2_generator.neg().into_affine(); commit_witness_indices.len()],
+        instance_abc_query: abc[..num_instance].to_vec(),
+        commit_witness_indices: commit_witness_indices.to_vec(),
+    };
+    end_timer!(setup_time);
+
+    Ok(CcPVKey { pk, vk })
+}
+
+/// Labels the trapdoor an update is for: `delta`, or `gamma_i`/`eta_i` of slot `i`.
+#[derive(Clone, Copy)]
+enum Trapdoor {
+    Delta,
+    Gamma(usize),
+    Eta(usize),
+}
+
+impl Trapdoor {
+    fn label(self) -> Vec<u8> {
+        let (tag, slot) = match self {
+            Trapdoor::Delta => (0u8, 0),
+            Trapdoor::Gamma(i) => (1, i),
+            Trapdoor::Eta(i) => (2, i),
+        };
+        let mut label = vec![tag];
+        label.extend_from_slice(&(slot as u64).to_le_bytes());
+        label
+    }
+}
+
+fn pok_challenge<E: Pairing>(
+    trapdoor: Trapdoor,
+    prev_value_g2: &E::G2Affine,
+    update: &TrapdoorUpdate<E>,
+) -> E::ScalarField {
+    let mut bytes = trapdoor.label();
+    prev_value_g2.serialize_compressed(&mut bytes).unwrap();
+    update.value_g2.serialize_compressed(&mut bytes).unwrap();
+    update.x_g1.serialize_compressed(&mut bytes).unwrap();
+    update.pok_r.serialize_compressed(&mut bytes).unwrap();
+    hash_to_field::<E>(bytes)
+}
+
+fn nonzero_scalar<R: RngCore, E: Pairing>(rng: &mut R) -> E::ScalarField {
+    let mut x = E::ScalarField::rand(rng);
+    while x.is_zero() {
+        x = E::ScalarField::rand(rng);
+    }
+    x
+}
+
+fn update_trapdoor<R: RngCore, E: Pairing>(
+    trapdoor: Trapdoor,
+    g1_generator: E::G1,
+    prev_value_g2: &E::G2Affine,
+    x: E::ScalarField,
+    rng: &mut R,
+) -> TrapdoorUpdate<E> {
+    let k = E::ScalarField::rand(rng);
+    let mut update = TrapdoorUpdate {
+        x_g1: (g1_generator * x).into_affine(),
+        value_g2: (*prev_value_g2 * x).into_affine(),
+        pok_r: (g1_generator * k).into_affine(),
+        pok_z: E::ScalarField::zero(),
+    };
+    update.pok_z = k + pok_challenge(trapdoor, prev_value_g2, &update) * x;
+    update
+}
+
+fn scale_query<G: AffineRepr>(query: &[G], s: G::ScalarField) -> Vec<G> {
+    let scaled = cfg_iter!(query).map(|q| *q * s).collect::<Vec<_>>();
+    G::Group::normalize_batch(&scaled)
+}
+
+/// Rerandomizes `key` by fresh secret factors, so that the new trapdoors are
+/// `x_delta * delta`, `x_gamma_i * gamma_i` and `x_eta_i * eta_i`, and appends the public
+/// record of the update to `transcript`. The factors are dropped on return.
+///
+/// The `gamma_i` are updated along with `delta`: the initial key has `gamma_i = 1`, and as
+/// the instance query is paired with the plain generator a public `gamma_i` would let a
+/// prover move instance terms into `D_i`. `eta_i` only blinds `D_i` and the matching term
+/// of `C`, and enters the key as `eta_i / delta` and `eta_i / gamma_i`, so it is
+/// rerandomized the same way.
+///
+/// `eta_gamma_inv_g1` changes with every contribution, so the link keys of an lrs setup
+/// must be derived from the final key.
+pub fn contribute<R: RngCore, E: Pairing>(
+    key: &CcPVKey<E>,
+    transcript: &mut CcTranscript<E>,
+    rng: &mut R,
+) -> CcPVKey<E> {
+    let num_slots = key.pk.commit_slots.len();
+    let x_delta = nonzero_scalar::<R, E>(rng);
+    let x_gamma = (0..num_slots).map(|_| nonzero_scalar::<R, E>(rng)).collect::<Vec<_>>();
+    let x_eta = (0..num_slots).map(|_| nonzero_scalar::<R, E>(rng)).collect::<Vec<_>>();
+    let x_delta_inv = x_delta.inverse().unwrap();
+
+    let pk = &key.pk;
+    let delta_g1 = (pk.delta_g1 * x_delta).into_affine();
+    let delta_g2 = (pk.delta_g2 * x_delta).into_affine();
+
+    let commit_slots = pk
+        .commit_slots
+        .iter()
+        .zip(x_gamma.iter().zip(&x_eta))
+        .map(|(slot, (x_gamma, x_eta))| {
+            let x_gamma_inv = x_gamma.inverse().unwrap();
+            CommitSlot {
+                indices: slot.indices.clone(),
+                eta_delta_inv_g1: (slot.eta_delta_inv_g1 * (*x_eta * x_delta_inv)).into_affine(),
+                eta_gamma_inv_g1: (slot.eta_gamma_inv_g1 * (*x_eta * x_gamma_inv)).into_affine(),
+                comm_wit_abc_query: scale_query(&slot.comm_wit_abc_query, x_gamma_inv),
+            }
+        })
+        .collect();
+    let gamma_g2_neg_pc = key
+        .vk
+        .gamma_g2_neg_pc
+        .iter()
+        .zip(&x_gamma)
+        .map(|(g, x)| (*g * x).into_affine())
+        .collect();
+
+    let next = CcPVKey {
+        pk: ProvingKey {
+            delta_g1,
+            delta_g2,
+            h_query: scale_query(&pk.h_query, x_delta_inv),
+            ucomm_wit_abc_query: scale_query(&pk.ucomm_wit_abc_query, x_delta_inv),
+            commit_slots,
+            ..pk.clone()
+        },
+        vk: VerifyingKey {
+            delta_g2_neg_pc: delta_g2.into_group().neg().into_affine(),
+            gamma_g2_neg_pc,
+            ..key.vk.clone()
+        },
+    };
+
+    let prev_eta_g2 = transcript.eta_g2(pk.g2_generator, num_slots);
+    let delta = update_trapdoor(Trapdoor::Delta, pk.g1_generator, &pk.delta_g2, x_delta, rng);
+    let gamma = key
+        .vk
+        .gamma_g2_neg_pc
+        .iter()
+        .zip(&x_gamma)
+        .enumerate()
+        .map(|(i, (g, x))| {
+            let prev_gamma_g2 = g.into_group().neg().into_affine();
+            update_trapdoor(Trapdoor::Gamma(i), pk.g1_generator, &prev_gamma_g2, *x, rng)
+        })
+        .collect();
+    let eta = prev_eta_g2
+        .iter()
+        .zip(&x_eta)
+        .enumerate()
+        .map(|(i, (prev, x))| update_trapdoor(Trapdoor::Eta(i), pk.g1_generator, prev, *x, rng))
+        .collect();
+    transcript.contributions.push(CcContribution {
+        delta_g1,
+        delta,
+        gamma,
+        eta,
+    });
+
+    next
+}
+
+fn verify_update<E: Pairing>(
+    trapdoor: Trapdoor,
+    g1_generator: E::G1,
+    prev_value_g2: &E::G2Affine,
+    update: &TrapdoorUpdate<E>,
+) -> bool {
+    if update.x_g1.is_zero() || update.value_g2.is_zero() {
+        return false;
+    }
+
+    // Proof of knowledge of x
+    let c = pok_challenge(trapdoor, prev_value_g2, update);
+    if g1_generator * update.pok_z != update.pok_r + update.x_g1 * c {
+        return false;
+    }
+
+    // value_g2 = x * prev_value_g2
+    E::multi_pairing(
+        [update.x_g1.into_group(), -g1_generator],
+        [prev_value_g2.into_group(), update.value_g2.into_group()],
+    )
+    .is_zero()
+}
+
+/// Checks a single contribution on top of a key with `delta * H`, and `gamma_i * H`,
+/// `eta_i * H` for every slot.
+pub fn verify_contribution<E: Pairing>(
+    g1_generator: E::G1,
+    g2_generator: E::G2,
+    prev_delta_g2: &E::G2Affine,
+    prev_gamma_g2: &[E::G2Affine],
+    prev_eta_g2: &[E::G2Affine],
+    contribution: &CcContribution<E>,
+) -> bool {
+    contribution.gamma.len() == prev_gamma_g2.len()
+        && contribution.eta.len() == prev_eta_g2.len()
+        && verify_update(Trapdoor::Delta, g1_generator, prev_delta_g2, &contribution.delta)
+        && prev_gamma_g2
+            .iter()
+            .zip(&contribution.gamma)
+            .enumerate()
+            .all(|(i, (prev, update))| verify_update(Trapdoor::Gamma(i), g1_generator, prev, update))
+        && prev_eta_g2
+            .iter()
+            .zip(&contribution.eta)
+            .enumerate()
+            .all(|(i, (prev, update))| verify_update(Trapdoor::Eta(i), g1_generator, prev, update))
+        && E::multi_pairing(
+            [contribution.delta_g1.into_group(), -g1_generator],
+            [g2_generator, contribution.delta.value_g2.into_group()],
+        )
+        .is_zero()
+}
+
+/// Checks `e(sum_i rho^i * after_i, value_g2) == e(sum_i rho^i * before_i, H)`,
+/// i.e. that `after` is `before` divided by the trapdoor in `value_g2`.
+fn same_query_over<E: Pairing>(
+    rho: E::ScalarField,
+    before: &[E::G1Affine],
+    after: &[E::G1Affine],
+    value_g2: &E::G2Affine,
+    g2_generator: E::G2,
+) -> bool {
+    let rho_pows = (0..before.len())
+        .scan(E::ScalarField::one(), |state, _| {
+            let current = *state;
+            *state *= rho;
+            Some(current)
+        })
+        .collect::<Vec<_>>();
+    let before_acc = E::G1::msm(before, &rho_pows).unwrap();
+    let after_acc = E::G1::msm(after, &rho_pows).unwrap();
+
+    E::multi_pairing([after_acc, -before_acc], [value_g2.into_group(), g2_generator]).is_zero()
+}
+
+/// Verifies that `key` results from applying `transcript` in order to `initial`, the key
+/// `initial_parameters` derives for the circuit from verified phase-1 powers.
+pub fn verify_transcript<E: Pairing>(
+    initial: &CcPVKey<E>,
+    transcript: &CcTranscript<E>,
+    key: &CcPVKey<E>,
+) -> bool {
+    let (pk0, pk) = (&initial.pk, &key.pk);
+    let (vk0, vk) = (&initial.vk, &key.vk);
+    let num_slots = pk0.commit_slots.len();
+
+    // Everything but delta, gamma and eta is fixed by the circuit and phase 1
+    if pk.g1_generator != pk0.g1_generator
+        || pk.g2_generator != pk0.g2_generator
+        || pk.alpha_g1 != pk0.alpha_g1
+        || pk.beta_g1 != pk0.beta_g1
+        || pk.beta_g2 != pk0.beta_g2
+        || pk.a_query != pk0.a_query
+        || pk.b_g1_query != pk0.b_g1_query
+        || pk.b_g2_query != pk0.b_g2_query
+        || pk.h_query.len() != pk0.h_query.len()
+        || pk.ucomm_wit_abc_query.len() != pk0.ucomm_wit_abc_query.len()
+        || pk.commit_slots.len() != num_slots
+        || pk
+            .commit_slots
+            .iter()
+            .zip(&pk0.commit_slots)
+            .any(|(slot, slot0)| {
+                slot.indices != slot0.indices
+                    || slot.comm_wit_abc_query.len() != slot0.comm_wit_abc_query.len()
+            })
+        || vk.g1_generator != pk.g1_generator
+        || vk.g2_generator != pk.g2_generator
+        || vk.alpha_beta_gt != vk0.alpha_beta_gt
+        || vk.instance_abc_query != vk0.instance_abc_query
+        || vk.commit_witness_indices != vk0.commit_witness_indices
+        || vk.gamma_g2_neg_pc.len() != num_slots
+    {
+        return false;
+    }
+
+    let neg = |p: &E::G2Affine| p.into_group().neg().into_affine();
+    let mut delta_g2 = pk0.delta_g2;
+    let mut gamma_g2 = vk0.gamma_g2_neg_pc.iter().map(neg).collect::<Vec<_>>();
+    let mut eta_g2 = vec![pk0.g2_generator.into_affine(); num_slots];
+    for contribution in &transcript.contributions {
+        if !verify_contribution(
+            pk.g1_generator,
+            pk.g2_generator,
+            &delta_g2,
+            &gamma_g2,
+            &eta_g2,
+            contribution,
+        ) {
+            return false;
+        }
+        delta_g2 = contribution.delta.value_g2;
+        gamma_g2 = contribution.gamma.iter().map(|u| u.value_g2).collect();
+        eta_g2 = contribution.eta.iter().map(|u| u.value_g2).collect();
+    }
+
+    let delta_g1 = transcript
+        .contributions
+        .last()
+        .map_or(pk0.delta_g1, |c| c.delta_g1);
+    if pk.delta_g1 != delta_g1
+        || pk.delta_g2 != delta_g2
+        || vk.delta_g2_neg_pc != neg(&delta_g2)
+        || vk.gamma_g2_neg_pc != gamma_g2.iter().map(neg).collect::<Vec<_>>()
+    {
+        return false;
+    }
+
+    let mut bytes = Vec::new();
+    key.serialize_compressed(&mut bytes).unwrap();
+    let rho = hash_to_field::<E>(bytes);
+
+    if !same_query_over::<E>(rho, &pk0.h_query, &pk.h_query, &delta_g2, pk.g2_generator)
+        || !same_query_over::<E>(
+            rho,
+            &pk0.ucomm_wit_abc_query,
+            &pk.ucomm_wit_abc_query,
+            &delta_g2,
+            pk.g2_generator,
+        )
+    {
+        return false;
+    }
+
+    pk.commit_slots
+        .iter()
+        .zip(&pk0.commit_slots)
+        .zip(gamma_g2.iter().zip(&eta_g2))
+        .all(|((slot, slot0), (gamma_g2, eta_g2))| {
+            // eta_i / delta and eta_i / gamma_i
+            E::multi_pairing(
+                [slot.eta_delta_inv_g1.into_group(), -pk.g1_generator],
+                [delta_g2.into_group(), eta_g2.into_group()],
+            )
+            .is_zero()
+                && E::multi_pairing(
+                    [slot.eta_gamma_inv_g1.into_group(), -pk.g1_generator],
+                    [gamma_g2.into_group(), eta_g2.into_group()],
+                )
+                .is_zero()
+                && same_query_over::<E>(
+                    rho,
+                    &slot0.comm_wit_abc_query,
+                    &slot.comm_wit_abc_query,
+                    gamma_g2,
+                    pk.g2_generator,
+                )
+        })
+}
@@ -4,10 +4,13 @@ use ark_relations::r1cs::SynthesisError;
 pub enum Error {
     SynthesisError(SynthesisError),
     VectorLongerThanExpected(usize, usize),
+    /// Two inputs that must have the same length do not.
+    LengthMismatch { expected: usize, got: usize },
     InvalidProof,
     InvalidLinkCommitment,
     InvalidWitnessCommitment,
     InsufficientWitnessesForCommitment(usize, usize),
+    InvalidCommitSlot(usize),
 }
 
 impl From<SynthesisError> for Error {
@@ -1,15 +1,15 @@
-use crate::cc::{r1cs_to_qap::R1CStoQAP, ProvingKey, VerifyingKey, CcPVKey};
+use crate::cc::{r1cs_to_qap::R1CStoQAP, CommitSlot, Error, ProvingKey, VerifyingKey, CcPVKey};
 use ark_ec::{pairing::Pairing, scalar_mul::fixed_base::FixedBase, CurveGroup, Group};
 use ark_ff::{Field, PrimeField, UniformRand, Zero};
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 use ark_relations::r1cs::{
-    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, Result as R1CSResult,
-    SynthesisError, SynthesisMode,
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisError, SynthesisMode,
 };
 use ark_std::rand::Rng;
 use ark_std::{cfg_into_iter, cfg_iter, start_timer, end_timer};
-use std::ops::Mul;
-use std::ops::Neg;
+use ark_std::ops::Mul;
+use ark_std::ops::Neg;
+use ark_std::vec::Vec;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
@@ -21,7 +21,22 @@ pub fn generate_random_parameters<E, C, R>(
     commit_witness_count: usize,
     rng: &mut R,
 
-) -> R1CSResult<CcPVKey<E>>
+) -> crate::Result<CcPVKey<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    generate_random_parameters_with_slots::<E, C, R>(circuit, &[(0..commit_witness_count).collect()], rng)
+}
+
+/// Generates a random common reference string for a circuit, with one commitment
+/// slot per list of witness indices in `commit_witness_indices`.
+pub fn generate_random_parameters_with_slots<E, C, R>(
+    circuit: C,
+    commit_witness_indices: &[Vec<usize>],
+    rng: &mut R,
+) -> crate::Result<CcPVKey<E>>
 where
     E: Pairing,
     C: ConstraintSynthesizer<E::ScalarField>,
@@ -30,10 +45,21 @@ where
     let alpha = E::ScalarField::rand(rng);
     let beta = E::ScalarField::rand(rng);
     let delta = E::ScalarField::rand(rng);
-    let eta = E::ScalarField::rand(rng);
-    let gamma = E::ScalarField::rand(rng);
-
-    generate_parameters::<E, C, R>(circuit, commit_witness_count, alpha, beta, delta, eta, gamma, rng)
+    let (etas, gammas): (Vec<_>, Vec<_>) = commit_witness_indices
+        .iter()
+        .map(|_| (E::ScalarField::rand(rng), E::ScalarField::rand(rng)))
+        .unzip();
+
+    generate_parameters_with_slots::<E, C, R>(
+        circuit,
+        commit_witness_indices,
+        alpha,
+        beta,
+        delta,
+        &etas,
+        &gammas,
+        rng,
+    )
 }
 
 /// Create parameters for a circuit, given some toxic waste.
@@ -46,7 +72,94 @@ pub fn generate_parameters<E, C, R>(
     eta: E::ScalarField,
     gamma: E::ScalarField,
     rng: &mut R,
-) -> R1CSResult<CcPVKey<E>>
+) -> crate::Result<CcPVKey<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    generate_parameters_with_slots::<E, C, R>(
+        circuit,
+        &[(0..commit_witness_count).collect()],
+        alpha,
+        beta,
+        delta,
+        &[eta],
+        &[gamma],
+        rng,
+    )
+}
+
+/// Checks that the commitment slots are disjoint and within the `num_witness` witnesses,
+/// and that no slot commits to a witness twice.
+pub(crate) fn check_commit_indices(
+    commit_witness_indices: &[Vec<usize>],
+    num_witness: usize,
+) -> crate::Result<()> {
+    let mut committed = vec![false; num_witness];
+    for (i, indices) in commit_witness_indices.iter().enumerate() {
+        for &index in indices {
+            if index >= num_witness {
+                return Err(Error::InsufficientWitnessesForCommitment(num_witness, index + 1));
+            }
+            if committed[index] {
+                return Err(Error::InvalidCommitSlot(i));
+            }
+            committed[index] = true;
+        }
+    }
+    Ok(())
+}
+
+/// The witness indices outside all commitment slots, in increasing order.
+pub(crate) fn uncommitted_witness_indices(
+    commit_witness_indices: &[Vec<usize>],
+    num_witness: usize,
+) -> Vec<usize> {
+    uncommitted_witness_segments(commit_witness_indices, num_witness)
+        .into_iter()
+        .flat_map(|(start, end)| start..end)
+        .collect()
+}
+
+/// The maximal runs `start..end` of witnesses outside every commitment slot, in increasing order.
+pub(crate) fn uncommitted_witness_segments(
+    commit_witness_indices: &[Vec<usize>],
+    num_witness: usize,
+) -> Vec<(usize, usize)> {
+    let mut committed = commit_witness_indices.concat();
+    committed.sort_unstable();
+
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for index in committed {
+        if start < index {
+            segments.push((start, index));
+        }
+        start = start.max(index + 1);
+    }
+    if start < num_witness {
+        segments.push((start, num_witness));
+    }
+    segments
+}
+
+/// Create parameters for a circuit with one commitment slot per list of witness indices,
+/// given some toxic waste; `etas[i]` and `gammas[i]` belong to slot `i`.
+///
+/// Index 0 is the first witness variable after the instance. A slot commits to its
+/// witnesses in the order listed, and may pick them from anywhere in the witness.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_with_slots<E, C, R>(
+    circuit: C,
+    commit_witness_indices: &[Vec<usize>],
+    alpha: E::ScalarField,
+    beta: E::ScalarField,
+    delta: E::ScalarField,
+    etas: &[E::ScalarField],
+    gammas: &[E::ScalarField],
+    rng: &mut R,
+) -> crate::Result<CcPVKey<E>>
 where
     E: Pairing,
     C: ConstraintSynthesizer<E::ScalarField>,
@@ -54,6 +167,14 @@ where
 {
     type D<F> = GeneralEvaluationDomain<F>;
 
+    if etas.len() != commit_witness_indices.len() || gammas.len() != commit_witness_indices.len() {
+        let got = if etas.len() != commit_witness_indices.len() { etas.len() } else { gammas.len() };
+        return Err(Error::LengthMismatch {
+            expected: commit_witness_indices.len(),
+            got,
+        });
+    }
+
     let setup_time = start_timer!(|| "Groth16::Generator");
     let cs = ConstraintSystem::new_ref();
     cs.set_optimization_goal(OptimizationGoal::Constraints);
@@ -78,7 +199,8 @@ where
     end_timer!(domain_time);
     ///////////////////////////////////////////////////////////////////////////
     let num_instance = cs.num_instance_variables();
-    // let num_witness = cs.num_witness_variables();
+    let num_witness = cs.num_witness_variables();
+    check_commit_indices(commit_witness_indices, num_witness)?;
 
     let reduction_time = start_timer!(|| "R1CS to QAP Instance Map with Evaluation");
     let (a, b, c, zt, qap_num_variables, m_raw) =
@@ -96,7 +218,10 @@ where
 
     let scalar_bits = E::ScalarField::MODULUS_BIT_SIZE as usize;
 
-    let gamma_inverse = gamma.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
+    let gamma_inverses = gammas
+        .iter()
+        .map(|gamma| gamma.inverse().ok_or(SynthesisError::UnexpectedIdentity))
+        .collect::<Result<Vec<_>, _>>()?;
     let delta_inverse = delta.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
 
     let g1_generator = E::G1::generator();
@@ -166,26 +291,28 @@ where
     );
     drop(instance_abc);
 
-    // Compute the commit witness query
-    let comm_wit_abc = cfg_iter!(a[(num_instance)..(num_instance+commit_witness_count)])
-    .zip(&b[(num_instance)..(num_instance+commit_witness_count)])
-    .zip(&c[(num_instance)..(num_instance+commit_witness_count)])
-    .map(|((a, b), c)| (beta * a + &(alpha * b) + c) * &gamma_inverse)
-    .collect::<Vec<_>>();
-
-    let comm_wit_abc_query = FixedBase::msm::<E::G1>(
-        scalar_bits,
-        g1_window,
-        &g1_table,
-        &comm_wit_abc,
-    );
-    drop(comm_wit_abc);
+    // Compute the commit witness query of every slot
+    let comm_wit_abc_queries = commit_witness_indices
+        .iter()
+        .zip(&gamma_inverses)
+        .map(|(indices, gamma_inverse)| {
+            let comm_wit_abc = cfg_iter!(indices)
+                .map(|i| {
+                    let i = num_instance + i;
+                    (beta * a[i] + alpha * b[i] + c[i]) * gamma_inverse
+                })
+                .collect::<Vec<_>>();
+
+            FixedBase::msm::<E::G1>(scalar_bits, g1_window, &g1_table, &comm_wit_abc)
+        })
+        .collect::<Vec<_>>();
 
     // Compute the uncommitted witness query
-    let ucomm_wit_abc = cfg_iter!(a[(num_instance+commit_witness_count)..])
-        .zip(&b[(num_instance+commit_witness_count)..])
-        .zip(&c[(num_instance+commit_witness_count)..])
-        .map(|((a, b), c)| (beta * a + &(alpha * b) + c) * &delta_inverse)
+    let ucomm_wit_abc = cfg_into_iter!(uncommitted_witness_indices(commit_witness_indices, num_witness))
+        .map(|i| {
+            let i = num_instance + i;
+            (beta * a[i] + alpha * b[i] + c[i]) * delta_inverse
+        })
         .collect::<Vec<_>>();
     drop(b);
     drop(a);
@@ -206,7 +333,6 @@ where
     let beta_g2 = g2_generator.mul_bigint(beta.into_bigint());
     let delta_g1 = g1_generator.mul_bigint(delta.into_bigint());
     let delta_g2 = g2_generator.mul_bigint(delta.into_bigint());
-    let gamma_g2 = g2_generator.mul_bigint(gamma.into_bigint());
 
     end_timer!(proving_key_time);
 
@@ -215,7 +341,6 @@ where
     let b_g1_query = E::G1::normalize_batch(&b_g1_query);
     let b_g2_query = E::G2::normalize_batch(&b_g2_query);
     let h_query = E::G1::normalize_batch(&h_query);
-    let comm_wit_abc_query = E::G1::normalize_batch(&comm_wit_abc_query);
     let ucomm_wit_abc_query = E::G1::normalize_batch(&ucomm_wit_abc_query);
     let instance_abc_query = E::G1::normalize_batch(&instance_abc_query);
     end_timer!(batch_normalization_time);
@@ -228,15 +353,22 @@ where
         beta_g2: E::G2Affine::from(beta_g2),
         delta_g1: E::G1Affine::from(delta_g1),
         delta_g2: E::G2Affine::from(delta_g2),
-        eta_delta_inv_g1: E::G1Affine::from(g1_generator.mul(eta * delta_inverse)),
-        eta_gamma_inv_g1: E::G1Affine::from(g1_generator.mul(eta * gamma_inverse)),
         a_query,
         b_g1_query,
         b_g2_query,
         h_query,
-        comm_wit_abc_query,
         ucomm_wit_abc_query,
-        commit_witness_num: commit_witness_count,
+        commit_slots: commit_witness_indices
+            .iter()
+            .zip(etas.iter().zip(&gamma_inverses))
+            .zip(&comm_wit_abc_queries)
+            .map(|((indices, (eta, gamma_inverse)), query)| CommitSlot {
+                indices: indices.clone(),
+                eta_delta_inv_g1: E::G1Affine::from(g1_generator.mul(*eta * delta_inverse)),
+                eta_gamma_inv_g1: E::G1Affine::from(g1_generator.mul(*eta * gamma_inverse)),
+                comm_wit_abc_query: E::G1::normalize_batch(query),
+            })
+            .collect(),
     };
 
     // Generate R1CS verification key
@@ -250,9 +382,12 @@ where
         g2_generator,
         alpha_beta_gt: E::pairing(alpha_g1, beta_g2),
         delta_g2_neg_pc: delta_g2.neg().into_affine().into(),
-        gamma_g2_neg_pc: gamma_g2.neg().into_affine().into(),
+        gamma_g2_neg_pc: gammas
+            .iter()
+            .map(|gamma| g2_generator.mul_bigint(gamma.into_bigint()).neg().into_affine())
+            .collect(),
         instance_abc_query,
-        commit_witness_num: commit_witness_count,
+        commit_witness_indices: commit_witness_indices.to_vec(),
     };
     end_timer!(setup_time);
 
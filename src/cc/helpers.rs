@@ -168,6 +168,15 @@ where
     r[ninputs]
 }
 
+/// The compressed instance of a circuit whose single public input is the hash of its
+/// logical public inputs `inputs`, i.e. circomlib's `MultiMiMC7(inputs.len(), 91)` with `k = 1`.
+pub fn hash_instance<E: Pairing>(inputs: &[E::ScalarField]) -> E::ScalarField
+where
+    <E::ScalarField as FromStr>::Err: Debug,
+{
+    multi_mimc7::<E>(&inputs.to_vec(), inputs.len(), &mimc_constants_round91::<E>())
+}
+
 pub fn hash_to_field<E: Pairing>(input: Vec<u8>) -> E::ScalarField
 {
     let mut hasher = Sha256::new();
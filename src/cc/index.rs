@@ -0,0 +1,186 @@
+use ark_ff::PrimeField;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, Matrix, OptimizationGoal, Result as R1CSResult,
+    SynthesisError, SynthesisMode,
+};
+use crate::json;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::vec::Vec;
+use serde::de::Error;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A constraint matrix in compressed sparse row form: the terms `(coeff, variable)` of
+/// row `i` are `entries[row_ptr[i]..row_ptr[i + 1]]`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CsrMatrix<F: PrimeField> {
+    pub row_ptr: Vec<usize>,
+    pub entries: Vec<(F, usize)>,
+}
+
+impl<F: PrimeField> CsrMatrix<F> {
+    pub fn num_rows(&self) -> usize {
+        self.row_ptr.len() - 1
+    }
+
+    pub fn row(&self, i: usize) -> &[(F, usize)] {
+        &self.entries[self.row_ptr[i]..self.row_ptr[i + 1]]
+    }
+}
+
+impl<F: PrimeField> From<Matrix<F>> for CsrMatrix<F> {
+    fn from(matrix: Matrix<F>) -> Self {
+        let mut row_ptr = Vec::with_capacity(matrix.len() + 1);
+        row_ptr.push(0);
+        let mut entries = Vec::with_capacity(matrix.iter().map(|row| row.len()).sum());
+        for row in matrix {
+            entries.extend(row);
+            row_ptr.push(entries.len());
+        }
+        Self { row_ptr, entries }
+    }
+}
+
+/// The circuit-dependent data the cc prover needs besides the assignment, synthesized
+/// once so that `create_proof_with_index` can skip constraint synthesis.
+///
+/// Variables are indexed as in `ark_relations`: the constant `1`, the instance, then the witness.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ProverIndex<F: PrimeField> {
+    pub a: CsrMatrix<F>,
+    pub b: CsrMatrix<F>,
+    pub c: CsrMatrix<F>,
+    pub num_instance_variables: usize,
+    pub num_witness_variables: usize,
+    pub domain: GeneralEvaluationDomain<F>,
+}
+
+impl<F: PrimeField> ProverIndex<F> {
+    /// Synthesizes `circuit` in setup mode, the same way `generate_parameters` does.
+    pub fn new<C: ConstraintSynthesizer<F>>(circuit: C) -> R1CSResult<Self> {
+        let cs = ConstraintSystem::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        cs.set_mode(SynthesisMode::Setup);
+        circuit.generate_constraints(cs.clone())?;
+        cs.finalize();
+
+        let num_instance_variables = cs.num_instance_variables();
+        let num_witness_variables = cs.num_witness_variables();
+        let domain = GeneralEvaluationDomain::new(cs.num_constraints() + num_instance_variables)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let matrices = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+
+        Ok(Self {
+            a: matrices.a.into(),
+            b: matrices.b.into(),
+            c: matrices.c.into(),
+            num_instance_variables,
+            num_witness_variables,
+            domain,
+        })
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.a.num_rows()
+    }
+
+    /// The length of a full assignment `(1, instance, witness)`.
+    pub fn num_variables(&self) -> usize {
+        self.num_instance_variables + self.num_witness_variables
+    }
+
+    /// Whether `full_assignment` satisfies every constraint `<A_i, z> * <B_i, z> = <C_i, z>`.
+    pub fn is_satisfied(&self, full_assignment: &[F]) -> bool {
+        let eval = |row: &[(F, usize)]| -> F {
+            row.iter()
+                .map(|(coeff, var)| *coeff * full_assignment[*var])
+                .sum()
+        };
+        (0..self.num_constraints()).all(|i| {
+            eval(self.a.row(i)) * eval(self.b.row(i)) == eval(self.c.row(i))
+        })
+    }
+}
+
+/// A term `[coeff, variable]` of a constraint, with `coeff` in decimal.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct Term<F: PrimeField>(#[serde(with = "json::field")] F, usize);
+
+/// The array of the rows, each the array of its terms.
+impl<F: PrimeField> Serialize for CsrMatrix<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            (0..self.num_rows()).map(|i| self.row(i).iter().map(|(coeff, var)| Term(*coeff, *var)).collect::<Vec<_>>()),
+        )
+    }
+}
+
+impl<'de, F: PrimeField> Deserialize<'de> for CsrMatrix<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rows = Vec::<Vec<Term<F>>>::deserialize(deserializer)?;
+        let matrix: Matrix<F> = rows
+            .into_iter()
+            .map(|row| row.into_iter().map(|Term(coeff, var)| (coeff, var)).collect())
+            .collect();
+        Ok(matrix.into())
+    }
+}
+
+/// `ProverIndex` as encoded in JSON: the domain is given by its size.
+#[derive(Deserialize)]
+#[serde(bound = "", deny_unknown_fields)]
+struct ProverIndexJson<F: PrimeField> {
+    a: CsrMatrix<F>,
+    b: CsrMatrix<F>,
+    c: CsrMatrix<F>,
+    num_instance_variables: usize,
+    num_witness_variables: usize,
+    domain_size: usize,
+}
+
+impl<F: PrimeField> Serialize for ProverIndex<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut index = serializer.serialize_struct("ProverIndex", 6)?;
+        index.serialize_field("a", &self.a)?;
+        index.serialize_field("b", &self.b)?;
+        index.serialize_field("c", &self.c)?;
+        index.serialize_field("num_instance_variables", &self.num_instance_variables)?;
+        index.serialize_field("num_witness_variables", &self.num_witness_variables)?;
+        index.serialize_field("domain_size", &self.domain.size())?;
+        index.end()
+    }
+}
+
+/// Rejects matrices of different heights or with variables out of range, and domains
+/// that are not the one `ProverIndex::new` picks, so that decoding gives an index the
+/// prover can use.
+impl<'de, F: PrimeField> Deserialize<'de> for ProverIndex<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = ProverIndexJson::<F>::deserialize(deserializer)?;
+        let num_constraints = raw.a.num_rows();
+        if raw.b.num_rows() != num_constraints || raw.c.num_rows() != num_constraints {
+            return Err(D::Error::custom("the constraint matrices have different numbers of rows"));
+        }
+        if raw.num_instance_variables == 0 {
+            return Err(D::Error::custom("the instance lacks the constant 1"));
+        }
+        let num_variables = raw.num_instance_variables + raw.num_witness_variables;
+        if [&raw.a, &raw.b, &raw.c].iter().any(|m| m.entries.iter().any(|(_, var)| *var >= num_variables)) {
+            return Err(D::Error::custom(format_args!("a constraint has a variable out of {}", num_variables)));
+        }
+        let domain = GeneralEvaluationDomain::new(num_constraints + raw.num_instance_variables)
+            .filter(|domain| domain.size() == raw.domain_size)
+            .ok_or_else(|| D::Error::custom(format_args!("{} is not the domain size of the circuit", raw.domain_size)))?;
+
+        Ok(Self {
+            a: raw.a,
+            b: raw.b,
+            c: raw.c,
+            num_instance_variables: raw.num_instance_variables,
+            num_witness_variables: raw.num_witness_variables,
+            domain,
+        })
+    }
+}
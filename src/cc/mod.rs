@@ -1,14 +1,19 @@
+pub mod ceremony;
 pub mod error;
 pub mod generator;
 pub mod helpers;
+pub mod index;
 pub mod prover;
 pub mod r1cs_to_qap;
 pub mod structures;
 pub mod verifier;
+#[cfg(feature = "std")]
 pub mod utils;
 
+pub use ceremony::*;
 pub use error::*;
 pub use generator::*;
+pub use index::*;
 pub use prover::*;
 pub use structures::*;
 pub use verifier::*;
@@ -33,6 +38,41 @@ pub mod tests {
         cc_prove_and_verify_3::<ark_bn254::Bn254>(1);
     }
 
+    #[test]
+    fn test_cc_prove_with_index() {
+        cc_prove_and_verify_with_index::<ark_bn254::Bn254>(2);
+    }
+
+    #[test]
+    fn test_cc_rerandomize() {
+        cc_rerandomize_and_verify::<ark_bn254::Bn254>(2);
+    }
+
+    #[test]
+    fn test_cc_prove_synthetic() {
+        cc_prove_and_verify_synthetic::<ark_bn254::Bn254>(100);
+    }
+
+    #[test]
+    fn test_cc_hashed_instance() {
+        cc_prove_and_verify_hashed_instance::<ark_bn254::Bn254>(3);
+    }
+
+    #[test]
+    fn test_cc_commit_slots() {
+        cc_prove_and_verify_slots::<ark_bn254::Bn254>(2);
+    }
+
+    #[test]
+    fn test_cc_verify_proofs_batch() {
+        cc_prove_and_verify_batch::<ark_bn254::Bn254>(4);
+    }
+
+    #[test]
+    fn test_cc_ceremony() {
+        cc_ceremony_prove_and_verify::<ark_bn254::Bn254>(2);
+    }
+
     #[test]
     fn test_cc_prove_and_verify_lrs() {
         cc_prove_and_verify_lrs::<ark_bn254::Bn254>(1);
@@ -1,22 +1,33 @@
-use crate::cc::{helpers::hash_to_field, r1cs_to_qap::R1CStoQAP, Proof, ProvingKey};
-use crate::link::scalar_vector_mult;
-use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
+use crate::cc::generator::{check_commit_indices, uncommitted_witness_segments};
+use crate::cc::verifier::proof_hash;
+use crate::cc::{helpers::hash_to_field, r1cs_to_qap::R1CStoQAP, Proof, ProverIndex, ProvingKey};
+use crate::cc::VerifyingKey;
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
 use ark_ff::Field;
 use ark_ff::{PrimeField, UniformRand, Zero};
-use ark_poly::GeneralEvaluationDomain;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 use ark_relations::r1cs::{
     ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, Result as R1CSResult,
+    SynthesisError,
 };
 use ark_serialize::CanonicalSerialize;
 use ark_std::rand::Rng;
 use ark_std::{cfg_iter, end_timer, start_timer, vec::Vec};
-use std::ops::Mul;
+use ark_std::ops::Mul;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
+/// The number of terms per MSM in the prover. Each chunk of scalars is converted and
+/// decomposed on its own, which bounds the prover's scratch memory independently of the
+/// circuit size at a small cost in MSM speed.
+pub const MSM_CHUNK_SIZE: usize = 1 << 18;
+
 /// Create a Groth16 proof that is zero-knowledge.
 /// This method samples randomness for zero knowledges via `rng`.
+///
+/// For a key with a single commitment slot; returns the committed witnesses and the
+/// blinding `v` of `D`.
 pub fn create_random_proof<E, C, R>(
     circuit: C,
     pk: &ProvingKey<E>,
@@ -36,7 +47,7 @@ where
         .map(|(proof, committed_witnesses)| (proof, committed_witnesses, v))
 }
 
-/// Create a Groth16 proof using randomness `r_a` and `r_b`.
+/// Create a Groth16 proof using randomness `r_a` and `r_b`, for a key with a single commitment slot.
 #[inline]
 pub fn create_proof<E, C>(
     circuit: C,
@@ -46,12 +57,62 @@ pub fn create_proof<E, C>(
     xi: E::ScalarField,
     v: E::ScalarField,
 ) -> R1CSResult<(Proof<E>, Vec<E::ScalarField>)>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    create_proof_with_slots::<E, C>(circuit, pk, r_a, r_b, xi, &[v])
+        .map(|(proof, mut committed_witnesses)| (proof, committed_witnesses.remove(0)))
+}
+
+/// Create a zero-knowledge proof for a key with any number of commitment slots.
+/// Returns the committed witnesses and the blinding `v_i` of `D_i` for every slot.
+#[allow(clippy::type_complexity)]
+pub fn create_random_proof_with_slots<E, C, R>(
+    circuit: C,
+    pk: &ProvingKey<E>,
+    rng: &mut R,
+) -> R1CSResult<(Proof<E>, Vec<Vec<E::ScalarField>>, Vec<E::ScalarField>)>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    let r_a = E::ScalarField::rand(rng);
+    let r_b = E::ScalarField::rand(rng);
+    let xi = E::ScalarField::rand(rng);
+    let vs = pk
+        .commit_slots
+        .iter()
+        .map(|_| E::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+
+    create_proof_with_slots::<E, C>(circuit, pk, r_a, r_b, xi, &vs)
+        .map(|(proof, committed_witnesses)| (proof, committed_witnesses, vs))
+}
+
+/// Create a proof using randomness `r_a` and `r_b`, blinding `D_i` with `vs[i]`; fails with
+/// `AssignmentMissing` unless `vs` has one blinding per commitment slot.
+#[allow(clippy::type_complexity)]
+pub fn create_proof_with_slots<E, C>(
+    circuit: C,
+    pk: &ProvingKey<E>,
+    r_a: E::ScalarField,
+    r_b: E::ScalarField,
+    xi: E::ScalarField,
+    vs: &[E::ScalarField],
+) -> R1CSResult<(Proof<E>, Vec<Vec<E::ScalarField>>)>
 where
     E: Pairing,
     C: ConstraintSynthesizer<E::ScalarField>,
 {
     type D<F> = GeneralEvaluationDomain<F>;
 
+    // One blinding per commitment slot
+    if vs.len() != pk.commit_slots.len() {
+        return Err(SynthesisError::AssignmentMissing);
+    }
+
     let prover_time = start_timer!(|| "Groth16::Prover");
     let cs = ConstraintSystem::new_ref();
 
@@ -61,7 +122,9 @@ where
     // Synthesize the circuit.
     let synthesis_time = start_timer!(|| "Constraint synthesis");
     circuit.generate_constraints(cs.clone())?; // Drives generation of new constraints inside cs
-    debug_assert!(cs.is_satisfied().unwrap());
+    if !cs.is_satisfied()? {
+        return Err(SynthesisError::Unsatisfiable);
+    }
     end_timer!(synthesis_time);
 
     // Finalize the constraint system (either by outlining or inlining, if an optimization goal is set).
@@ -70,31 +133,143 @@ where
     end_timer!(lc_time);
 
     let witness_map_time = start_timer!(|| "R1CS to QAP witness map");
-    let h = R1CStoQAP::witness_map::<E::ScalarField, D<E::ScalarField>>(cs.clone())?;
+    let matrices = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+    let num_instance = cs.num_instance_variables();
+    let num_constraints = cs.num_constraints();
+    // Move the assignment out, and free the rest of the constraint system before the MSMs
+    let full_assignment = {
+        let cs = cs.into_inner().ok_or(SynthesisError::MissingCS)?;
+        let mut full_assignment = cs.instance_assignment;
+        full_assignment.extend(cs.witness_assignment);
+        full_assignment
+    };
+    let domain = D::<E::ScalarField>::new(num_constraints + num_instance)
+        .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+    let h = R1CStoQAP::witness_map_from_rows(
+        &domain,
+        num_instance,
+        num_constraints,
+        |i| &matrices.a[i],
+        |i| &matrices.b[i],
+        |i| &matrices.c[i],
+        &full_assignment,
+    );
+    drop(matrices);
     end_timer!(witness_map_time);
 
-    // Prepare Information
-    let prover = cs.borrow().unwrap();
-    // let num_instance_vars = prover.num_instance_variables();
-    // let num_witness_vars = prover.num_witness_variables();
-    let instance_assignment_with_one_field = cfg_iter!(prover.instance_assignment)
-        .map(|s| s.into_bigint())
-        .collect::<Vec<_>>();
+    let proof = create_proof_from_assignment(pk, &h, &full_assignment, num_instance, r_a, r_b, xi, vs);
+    end_timer!(prover_time);
 
-    let instance_assignment = instance_assignment_with_one_field[1..].to_vec();
+    Ok(proof)
+}
 
-    let witness_assignment = cfg_iter!(prover.witness_assignment)
-        .map(|s| s.into_bigint())
+/// Create a zero-knowledge proof from the full assignment `(1, instance, witness)` of the
+/// circuit behind `index`, without synthesizing the circuit. `pk` must have been generated
+/// for the same circuit, see `check_key_matches_index`.
+/// Returns the committed witnesses and the blinding `v_i` of `D_i` for every slot.
+#[allow(clippy::type_complexity)]
+pub fn create_proof_with_index<E, R>(
+    index: &ProverIndex<E::ScalarField>,
+    pk: &ProvingKey<E>,
+    full_assignment: &[E::ScalarField],
+    rng: &mut R,
+) -> R1CSResult<(Proof<E>, Vec<Vec<E::ScalarField>>, Vec<E::ScalarField>)>
+where
+    E: Pairing,
+    R: Rng,
+{
+    if full_assignment.len() != index.num_variables() {
+        return Err(SynthesisError::AssignmentMissing);
+    }
+    check_key_matches_index(index, pk)?;
+    if !index.is_satisfied(full_assignment) {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    let r_a = E::ScalarField::rand(rng);
+    let r_b = E::ScalarField::rand(rng);
+    let xi = E::ScalarField::rand(rng);
+    let vs = pk
+        .commit_slots
+        .iter()
+        .map(|_| E::ScalarField::rand(rng))
         .collect::<Vec<_>>();
 
-    let committed_witnesses = &witness_assignment[..pk.commit_witness_num];
+    let prover_time = start_timer!(|| "Groth16::Prover with index");
+    let witness_map_time = start_timer!(|| "R1CS to QAP witness map");
+    let h = R1CStoQAP::witness_map_from_rows(
+        &index.domain,
+        index.num_instance_variables,
+        index.num_constraints(),
+        |i| index.a.row(i),
+        |i| index.b.row(i),
+        |i| index.c.row(i),
+        full_assignment,
+    );
+    end_timer!(witness_map_time);
 
-    let mut uncommitted_witnesses = witness_assignment[pk.commit_witness_num..].to_vec();
+    let (proof, committed_witnesses) = create_proof_from_assignment(
+        pk,
+        &h,
+        full_assignment,
+        index.num_instance_variables,
+        r_a,
+        r_b,
+        xi,
+        &vs,
+    );
+    end_timer!(prover_time);
 
-    let assignment = [&instance_assignment[..], &witness_assignment[..]].concat();
+    Ok((proof, committed_witnesses, vs))
+}
 
-    drop(prover);
-    drop(cs);
+/// Checks that `pk` was generated for the circuit behind `index`: its queries have one
+/// element per variable, or per constraint for `h_query`, and its slots commit to distinct
+/// witnesses of the circuit. Fails with `MalformedVerifyingKey` otherwise.
+fn check_key_matches_index<E: Pairing>(
+    index: &ProverIndex<E::ScalarField>,
+    pk: &ProvingKey<E>,
+) -> R1CSResult<()> {
+    let num_variables = index.num_variables();
+    let num_witness = index.num_witness_variables;
+    let commit_witness_indices = pk.commit_slots.iter().map(|slot| slot.indices.clone()).collect::<Vec<_>>();
+    let num_committed = commit_witness_indices.iter().map(|indices| indices.len()).sum::<usize>();
+    let matches = pk.a_query.len() == num_variables
+        && pk.b_g1_query.len() == num_variables
+        && pk.b_g2_query.len() == num_variables
+        && pk.h_query.len() + 1 == index.domain.size()
+        && check_commit_indices(&commit_witness_indices, num_witness).is_ok()
+        && pk.commit_slots.iter().all(|slot| slot.comm_wit_abc_query.len() == slot.indices.len())
+        && pk.ucomm_wit_abc_query.len() + num_committed == num_witness;
+    if matches {
+        Ok(())
+    } else {
+        Err(SynthesisError::MalformedVerifyingKey)
+    }
+}
+
+/// The part of the prover after the witness map: builds the proof from the coefficients
+/// `h` of the quotient polynomial and the full assignment `(1, instance, witness)`.
+#[allow(clippy::too_many_arguments)]
+fn create_proof_from_assignment<E: Pairing>(
+    pk: &ProvingKey<E>,
+    h: &[E::ScalarField],
+    full_assignment: &[E::ScalarField],
+    num_instance: usize,
+    r_a: E::ScalarField,
+    r_b: E::ScalarField,
+    xi: E::ScalarField,
+    vs: &[E::ScalarField],
+) -> (Proof<E>, Vec<Vec<E::ScalarField>>) {
+    // (instance without the constant 1, witness), and the witness alone
+    let assignment = &full_assignment[1..];
+    let witness_assignment = &full_assignment[num_instance..];
+
+    let committed_witnesses = pk
+        .commit_slots
+        .iter()
+        .map(|slot| slot.indices.iter().map(|i| witness_assignment[*i]).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
 
     let delta_g1_prime = pk.delta_g1.mul(xi);
     let delta_g2_prime = pk.delta_g2.mul(xi);
@@ -105,7 +280,7 @@ where
         delta_g1_prime.mul(r_a),
         &pk.a_query,
         pk.alpha_g1,
-        &assignment,
+        assignment,
     );
     end_timer!(a_acc_time);
 
@@ -115,7 +290,7 @@ where
         delta_g1_prime.mul(r_b),
         &pk.b_g1_query,
         pk.beta_g1,
-        &assignment,
+        assignment,
     );
     end_timer!(b_g1_acc_time);
 
@@ -125,22 +300,31 @@ where
         delta_g2_prime.mul(r_b),
         &pk.b_g2_query,
         pk.beta_g2,
-        &assignment,
+        assignment,
     );
     end_timer!(b_g2_acc_time);
-    drop(assignment);
 
-    // Compute [D]_1
+    // Compute [D_i]_1
     let d_acc_time = start_timer!(|| "Compute D");
-    let mut g_d = pk.eta_gamma_inv_g1.mul(v);
-    g_d += E::G1::msm_bigint(&pk.comm_wit_abc_query, &committed_witnesses);
+    let g_d = pk
+        .commit_slots
+        .iter()
+        .zip(vs)
+        .zip(&committed_witnesses)
+        .map(|((slot, v), witnesses)| {
+            slot.eta_gamma_inv_g1.mul(v) + chunked_msm::<E::G1>(&slot.comm_wit_abc_query, witnesses, None)
+        })
+        .collect::<Vec<_>>();
+    let g_d = E::G1::normalize_batch(&g_d);
     end_timer!(d_acc_time);
 
     // Compute [C]_1
     let mut bytes = Vec::new();
     g_a.serialize_compressed(&mut bytes).unwrap();
     g2_b.serialize_compressed(&mut bytes).unwrap();
-    g_d.serialize_compressed(&mut bytes).unwrap();
+    for d in &g_d {
+        d.serialize_compressed(&mut bytes).unwrap();
+    }
     let m_hash = hash_to_field::<E>(bytes);
 
     let inv_xi_m_hash = (xi + m_hash).inverse().unwrap();
@@ -148,25 +332,18 @@ where
     let s_b: E::ScalarField = xi * r_b * inv_xi_m_hash;
 
     assert!(h[h.len() - 1] == E::ScalarField::zero()); // last element is always zero
-    let h_assignment = scalar_vector_mult::<E>(&inv_xi_m_hash, &h, h.len());
-    let h_assignment = h_assignment
-        .iter()
-        .map(|s| s.into_bigint())
-        .collect::<Vec<_>>();
+    let h_acc = chunked_msm::<E::G1>(&pk.h_query, h, Some(inv_xi_m_hash));
 
-    let h_acc = E::G1::msm_bigint(&pk.h_query, &h_assignment);
-    drop(h_assignment);
-    drop(h);
-
-    for i in 0..uncommitted_witnesses.len() {
-        uncommitted_witnesses[i] = (inv_xi_m_hash
-            * E::ScalarField::from_bigint(uncommitted_witnesses[i]).unwrap())
-        .into_bigint();
+    // `ucomm_wit_abc_query` follows the uncommitted witnesses, segment by segment
+    let commit_witness_indices = pk.commit_slots.iter().map(|slot| slot.indices.clone()).collect::<Vec<_>>();
+    let mut ucomm_wit_abc_acc = E::G1::zero();
+    let mut offset = 0;
+    for (start, end) in uncommitted_witness_segments(&commit_witness_indices, witness_assignment.len()) {
+        let query = &pk.ucomm_wit_abc_query[offset..offset + end - start];
+        ucomm_wit_abc_acc += chunked_msm::<E::G1>(query, &witness_assignment[start..end], Some(inv_xi_m_hash));
+        offset += end - start;
     }
 
-    let ucomm_wit_abc_acc = E::G1::msm_bigint(&pk.ucomm_wit_abc_query, &uncommitted_witnesses);
-    drop(uncommitted_witnesses);
-
     let c_time: ark_std::perf_trace::TimerInfo = start_timer!(|| "Finish C");
     let mut g_c = E::G1::zero();
     g_c += &ucomm_wit_abc_acc;
@@ -174,31 +351,93 @@ where
     g_c += g_a.mul(s_b);
     g_c += g1_b.mul(s_a);
     g_c -= &pk.delta_g1.mul(s_a * s_b * (xi + m_hash));
-    g_c -= &pk.eta_delta_inv_g1.mul(v * inv_xi_m_hash);
+    for (slot, v) in pk.commit_slots.iter().zip(vs) {
+        g_c -= &slot.eta_delta_inv_g1.mul(*v * inv_xi_m_hash);
+    }
     end_timer!(c_time);
-    end_timer!(prover_time);
 
-    Ok((
+    (
         Proof {
             a: g_a.into_affine(),
             b: g2_b.into_affine(),
             c: g_c.into_affine(),
-            d: g_d.into_affine(),
+            d: g_d,
             delta_prime: delta_g2_prime.into_affine(),
         },
-        committed_witnesses
-            .iter()
-            .map(|b| E::ScalarField::from_bigint(*b).unwrap())
-            .collect(),
-    ))
+        committed_witnesses,
+    )
+}
+
+/// Rerandomize `proof` into a fresh proof for the same instance, sampling randomness via `rng`,
+/// without the witness. The commitments `D_i` are kept, so link proofs about them stay valid.
+///
+/// With `Delta = delta' + m * delta`, a proof satisfies `e(A, B) = e(C, Delta) * P` for a `P`
+/// that depends only on the vk, the instance and `D`. For random `r_1, r_2, s` this sets
+/// `A' = A / r_1`, `B' = r_1 * (B + r_2 * Delta)`, so `e(A', B') = e(A, B) * e(r_2 * A, Delta)`,
+/// then `delta'' = s * Delta - m' * delta` for the new hash `m' = hash(A', B', D)` and
+/// `C' = (C + r_2 * A) / s`. `A'`, `B'` and `delta''` are uniform, and `C'` is determined by
+/// them, as in a fresh proof with the same `D`.
+///
+/// Soundness is unaffected: the output satisfies the verification equation only for the
+/// instance and `D` of the input, so it proves no statement that was not already proven.
+/// The `m_hash` binding keeps `C` from being mauled on its own, but it does not make proofs
+/// strongly non-malleable; like Groth16, cc proofs are only weakly simulation-extractable.
+pub fn rerandomize<E, R>(vk: &VerifyingKey<E>, proof: &Proof<E>, rng: &mut R) -> Proof<E>
+where
+    E: Pairing,
+    R: Rng,
+{
+    let r_1 = E::ScalarField::rand(rng);
+    let r_2 = E::ScalarField::rand(rng);
+    let s = E::ScalarField::rand(rng);
+
+    let m_hash = proof_hash(proof);
+    let big_delta: E::G2 = proof.delta_prime.into_group() - vk.delta_g2_neg_pc.mul(m_hash);
+
+    let a = proof.a.mul(r_1.inverse().unwrap()).into_affine();
+    let b = ((proof.b.into_group() + big_delta.mul(r_2)) * r_1).into_affine();
+    let mut rerandomized = Proof {
+        a,
+        b,
+        c: ((proof.c.into_group() + proof.a.mul(r_2)) * s.inverse().unwrap()).into_affine(),
+        d: proof.d.clone(),
+        delta_prime: E::G2Affine::default(),
+    };
+
+    let m_hash = proof_hash(&rerandomized);
+    let delta_prime: E::G2 = big_delta * s + vk.delta_g2_neg_pc * m_hash;
+    rerandomized.delta_prime = delta_prime.into_affine();
+    rerandomized
 }
 
 fn calculate_coeff<G: AffineRepr>(
     initial: G::Group,
     query: &[G],
     vk_param: G,
-    assignment: &[<G::ScalarField as PrimeField>::BigInt],
+    assignment: &[G::ScalarField],
 ) -> G::Group {
-    let acc = G::Group::msm_bigint(&query[1..], assignment);
+    let acc = chunked_msm::<G::Group>(&query[1..], assignment, None);
     initial + query[0] + acc + vk_param
 }
+
+/// `sum_i scale * scalars[i] * bases[i]`, over chunks of `MSM_CHUNK_SIZE` terms, so that only
+/// one chunk of scalars is converted to `BigInt` and decomposed by the MSM at a time.
+fn chunked_msm<G: CurveGroup>(
+    bases: &[G::Affine],
+    scalars: &[G::ScalarField],
+    scale: Option<G::ScalarField>,
+) -> G {
+    bases
+        .chunks(MSM_CHUNK_SIZE)
+        .zip(scalars.chunks(MSM_CHUNK_SIZE))
+        .map(|(bases, scalars)| {
+            let scalars = cfg_iter!(scalars)
+                .map(|s| match scale {
+                    Some(scale) => (scale * s).into_bigint(),
+                    None => s.into_bigint(),
+                })
+                .collect::<Vec<_>>();
+            G::msm_bigint(bases, &scalars)
+        })
+        .sum()
+}
@@ -1,3 +1,4 @@
+use ark_ec::CurveGroup;
 use ark_ff::{One, PrimeField, Zero};
 use ark_poly::EvaluationDomain;
 use ark_std::{cfg_iter, cfg_iter_mut, vec};
@@ -6,7 +7,8 @@ use ark_std::end_timer;
 
 // use crate::Vec;
 use ark_relations::r1cs::{ConstraintSystemRef, Result as R1CSResult, SynthesisError};
-use core::ops::{AddAssign, Deref};
+use core::ops::AddAssign;
+use ark_std::vec::Vec;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
@@ -92,25 +94,71 @@ impl R1CStoQAP {
         Ok((a, b, c, zt, qap_num_variables, domain_size))
     }
 
-    #[inline]
-    pub(crate) fn witness_map<F: PrimeField, D: EvaluationDomain<F>>(
-        prover: ConstraintSystemRef<F>,
-    ) -> R1CSResult<Vec<F>> {
-        let matrices = prover.to_matrices().unwrap();
+    /// The reduction of `instance_map_with_evaluation` when the Lagrange coefficients
+    /// `u_j = L_j(t)` are only known in the exponent, as in a phase-2 setup.
+    ///
+    /// Each of `A`, `B`, `C` is evaluated over its own basis, so that e.g. the bases
+    /// `[beta * L_j(t)]`, `[alpha * L_j(t)]`, `[L_j(t)]` give the terms of the
+    /// `beta * a_i + alpha * b_i + c_i` queries. A matrix without a basis yields an empty vector.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn instance_map_in_exponent<G: CurveGroup>(
+        cs: ConstraintSystemRef<G::ScalarField>,
+        lagrange_a: Option<&[G::Affine]>,
+        lagrange_b: Option<&[G::Affine]>,
+        lagrange_c: Option<&[G::Affine]>,
+    ) -> R1CSResult<(Vec<G>, Vec<G>, Vec<G>)> {
+        let matrices = cs.to_matrices().unwrap();
+        let num_constraints = cs.num_constraints();
+        let num_instance = cs.num_instance_variables();
+        let num_variables = num_instance + cs.num_witness_variables();
+
+        let evaluate = |matrix: &[Vec<(G::ScalarField, usize)>], u: &[G::Affine]| {
+            let mut acc = vec![G::zero(); num_variables];
+            for (u_i, row) in u.iter().zip(matrix) {
+                for &(ref coeff, index) in row {
+                    if coeff.is_one() {
+                        acc[index] += u_i;
+                    } else {
+                        acc[index] += *u_i * coeff;
+                    }
+                }
+            }
+            acc
+        };
+
+        let a = lagrange_a.map_or_else(Vec::new, |u| {
+            let mut a = evaluate(&matrices.a, u);
+            for (a_i, u_i) in a[..num_instance]
+                .iter_mut()
+                .zip(&u[num_constraints..num_constraints + num_instance])
+            {
+                *a_i += u_i;
+            }
+            a
+        });
+        let b = lagrange_b.map_or_else(Vec::new, |u| evaluate(&matrices.b, u));
+        let c = lagrange_c.map_or_else(Vec::new, |u| evaluate(&matrices.c, u));
+
+        Ok((a, b, c))
+    }
+
+    /// The coefficients of the quotient polynomial `h = (a * b - c) / t` for the assignment
+    /// `full_assignment`, with the `i`-th row of each matrix given by `a_row`, `b_row` and `c_row`,
+    /// so that it runs both on `ConstraintMatrices` and on the matrices of a `ProverIndex`.
+    ///
+    /// At most two vectors of the domain size are alive at a time: `c` is only built once
+    /// `a * b` has been computed in place and `b` freed.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn witness_map_from_rows<'a, F: PrimeField, D: EvaluationDomain<F>>(
+        domain: &D,
+        num_inputs: usize,
+        num_constraints: usize,
+        a_row: impl Fn(usize) -> &'a [(F, usize)] + Sync,
+        b_row: impl Fn(usize) -> &'a [(F, usize)] + Sync,
+        c_row: impl Fn(usize) -> &'a [(F, usize)] + Sync,
+        full_assignment: &[F],
+    ) -> Vec<F> {
         let zero = F::zero();
-        let num_inputs = prover.num_instance_variables();
-        let num_constraints = prover.num_constraints();
-        let cs = prover.borrow().unwrap();
-        let prover = cs.deref();
-
-        let full_assignment = [
-            prover.instance_assignment.as_slice(),
-            prover.witness_assignment.as_slice(),
-        ]
-        .concat();
-
-        let domain =
-            D::new(num_constraints + num_inputs).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
         let domain_size = domain.size();
 
         let mut a = vec![zero; domain_size];
@@ -118,11 +166,10 @@ impl R1CStoQAP {
 
         cfg_iter_mut!(a[..num_constraints])
             .zip(cfg_iter_mut!(b[..num_constraints]))
-            .zip(cfg_iter!(&matrices.a))
-            .zip(cfg_iter!(&matrices.b))
-            .for_each(|(((a, b), at_i), bt_i)| {
-                *a = evaluate_constraint(&at_i, &full_assignment);
-                *b = evaluate_constraint(&bt_i, &full_assignment);
+            .enumerate()
+            .for_each(|(i, (a, b))| {
+                *a = evaluate_constraint(a_row(i), full_assignment);
+                *b = evaluate_constraint(b_row(i), full_assignment);
             });
 
         {
@@ -131,23 +178,25 @@ impl R1CStoQAP {
             a[start..end].clone_from_slice(&full_assignment[..num_inputs]);
         }
 
-        let mut c = vec![zero; domain_size];
-        cfg_iter_mut!(c[..num_constraints])
-            .enumerate()
-            .for_each(|(i, c)| {
-                *c = evaluate_constraint(&matrices.c[i], &full_assignment);
-            });
-
         let coset_domain = domain.get_coset(F::GENERATOR).unwrap();
 
-        let mut arr = [&mut a, &mut b, &mut c];
+        let mut arr = [&mut a, &mut b];
         cfg_iter_mut!(arr).for_each(|mut x| domain.ifft_in_place(&mut x));
         cfg_iter_mut!(arr).for_each(|mut x| coset_domain.fft_in_place(&mut x));
 
-        let mut ab = domain.mul_polynomials_in_evaluation_domain(&a, &b);
-        drop(a);
+        let mut ab = a;
+        cfg_iter_mut!(ab).zip(cfg_iter!(b)).for_each(|(ab_i, b_i)| *ab_i *= b_i);
         drop(b);
 
+        let mut c = vec![zero; domain_size];
+        cfg_iter_mut!(c[..num_constraints])
+            .enumerate()
+            .for_each(|(i, c)| {
+                *c = evaluate_constraint(c_row(i), full_assignment);
+            });
+        domain.ifft_in_place(&mut c);
+        coset_domain.fft_in_place(&mut c);
+
         let vanishing_polynomial_over_coset = domain
             .evaluate_vanishing_polynomial(F::GENERATOR)
             .inverse()
@@ -160,6 +209,6 @@ impl R1CStoQAP {
 
         coset_domain.ifft_in_place(&mut ab);
 
-        Ok(ab)
+        ab
     }
 }
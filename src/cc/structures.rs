@@ -1,21 +1,29 @@
+use crate::json::{self, JsonPairing};
 use ark_ec::pairing::{Pairing, PairingOutput};
 use ark_serialize::*;
 use ark_std::vec::Vec;
+use serde::{Deserialize, Serialize};
 
 /// Modifying Paper `Simulation Extractable Versions of Groth’s zk-SNARK Revisited` to the commit-carry version
 
 /// A proof in the Groth16 SNARK.
-#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
 pub struct Proof<E: Pairing> {
     /// The `A` element in `G1`.
+    #[serde(with = "json::affine")]
     pub a: E::G1Affine,
     /// The `B` element in `G2`.
+    #[serde(with = "json::affine")]
     pub b: E::G2Affine,
     /// The `C` element in `G1`.
+    #[serde(with = "json::affine")]
     pub c: E::G1Affine,
-    /// The `D` element in `G1`.
-    pub d: E::G1Affine,
+    /// The `D_i` elements in `G1`, one per commitment slot.
+    #[serde(with = "json::affines")]
+    pub d: Vec<E::G1Affine>,
     /// The `delta_prime` element in `G2`.
+    #[serde(with = "json::affine")]
     pub delta_prime: E::G2Affine,
 }
 
@@ -25,7 +33,7 @@ impl<E: Pairing> Default for Proof<E> {
             a: E::G1Affine::default(),
             b: E::G2Affine::default(),
             c: E::G1Affine::default(),
-            d: E::G1Affine::default(),
+            d: Vec::new(),
             delta_prime: E::G2Affine::default(),
         }
     }
@@ -35,17 +43,25 @@ impl<E: Pairing> Default for Proof<E> {
 ////////////////////////////////////////////////////////////////////////////////
 
 /// A verification key in the Groth16 SNARK.
-#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
 pub struct VerifyingKey<E: Pairing> {
+    #[serde(with = "json::projective")]
     pub g1_generator: E::G1,
+    #[serde(with = "json::projective")]
     pub g2_generator: E::G2,
     /// The e(`alpha * G`, `beta * H`) element in `E::GT`.
+    #[serde(with = "json::target")]
     pub alpha_beta_gt: PairingOutput<E>,
+    #[serde(with = "json::affine")]
     pub delta_g2_neg_pc: E::G2Affine,
-    pub gamma_g2_neg_pc: E::G2Affine,
+    /// The elements `- gamma_i * H`, one per commitment slot.
+    #[serde(with = "json::affines")]
+    pub gamma_g2_neg_pc: Vec<E::G2Affine>,
+    #[serde(with = "json::affines")]
     pub instance_abc_query: Vec<E::G1Affine>,
-    /// The num of witness to commit
-    pub commit_witness_num: usize,
+    /// The witness indices of the commitment slots
+    pub commit_witness_indices: Vec<Vec<usize>>,
 }
 
 impl<E: Pairing> Default for VerifyingKey<E> {
@@ -55,47 +71,124 @@ impl<E: Pairing> Default for VerifyingKey<E> {
             g2_generator: E::G2::default(),
             alpha_beta_gt: PairingOutput::<E>::default(),
             delta_g2_neg_pc: E::G2Affine::default(),
-            gamma_g2_neg_pc: E::G2Affine::default(),
+            gamma_g2_neg_pc: Vec::new(),
             instance_abc_query: Vec::new(),
-            commit_witness_num: 0,
+            commit_witness_indices: Vec::new(),
         }
     }
 }
 
+/// Preprocessed verification key parameters that enable faster verification
+/// at the expense of larger size in memory.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PreparedVerifyingKey<E: Pairing> {
+    /// The unprepared verification key.
+    pub vk: VerifyingKey<E>,
+    /// The element `- delta * H` in `E::G2`, prepared for use in pairings.
+    pub delta_g2_neg_pc: E::G2Prepared,
+    /// The elements `- gamma_i * H` in `E::G2`, prepared for use in pairings.
+    pub gamma_g2_neg_pc: Vec<E::G2Prepared>,
+    /// The element `- H` in `E::G2`, prepared for use in pairings.
+    pub g2_generator_neg_pc: E::G2Prepared,
+}
+
+impl<E: Pairing> From<VerifyingKey<E>> for PreparedVerifyingKey<E> {
+    fn from(vk: VerifyingKey<E>) -> Self {
+        Self {
+            delta_g2_neg_pc: vk.delta_g2_neg_pc.into(),
+            gamma_g2_neg_pc: vk.gamma_g2_neg_pc.iter().map(|g| (*g).into()).collect(),
+            g2_generator_neg_pc: E::G2Prepared::from(-vk.g2_generator),
+            vk,
+        }
+    }
+}
+
+impl<E: Pairing> Default for PreparedVerifyingKey<E> {
+    fn default() -> Self {
+        VerifyingKey::default().into()
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////
 
 /// The prover key for for the Groth16 zkSNARK.
-#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
 pub struct ProvingKey<E: Pairing> {
+    #[serde(with = "json::projective")]
     pub g1_generator: E::G1,
+    #[serde(with = "json::projective")]
     pub g2_generator: E::G2,
     /// The element `alpha * G` in `E::G1`.
+    #[serde(with = "json::affine")]
     pub alpha_g1: E::G1Affine,
     /// The element `beta * G` in `E::G1`.
+    #[serde(with = "json::affine")]
     pub beta_g1: E::G1Affine,
     /// The element `beta * H` in `E::G2`.
+    #[serde(with = "json::affine")]
     pub beta_g2: E::G2Affine,
     /// The element `delta * G` in `E::G1`.
+    #[serde(with = "json::affine")]
     pub delta_g1: E::G1Affine,
     /// The element `delta * G` in `E::G2`.
+    #[serde(with = "json::affine")]
     pub delta_g2: E::G2Affine,
-    pub eta_delta_inv_g1: E::G1Affine,
-    pub eta_gamma_inv_g1: E::G1Affine,
     /// The elements `a_i * G` in `E::G1`.
+    #[serde(with = "json::affines")]
     pub a_query: Vec<E::G1Affine>,
     /// The elements `b_i * G` in `E::G1`.
+    #[serde(with = "json::affines")]
     pub b_g1_query: Vec<E::G1Affine>,
     /// The elements `b_i * H` in `E::G2`.
+    #[serde(with = "json::affines")]
     pub b_g2_query: Vec<E::G2Affine>,
     /// The elements `h_i * G` in `E::G1`.
+    #[serde(with = "json::affines")]
     pub h_query: Vec<E::G1Affine>,
-    /// The committed witness query of C
-    pub comm_wit_abc_query: Vec<E::G1Affine>,
     /// The uncommitted witness query of C
+    #[serde(with = "json::affines")]
     pub ucomm_wit_abc_query: Vec<E::G1Affine>,
-    /// The num of witness to commit
-    pub commit_witness_num: usize,
+    /// The commitment slots, each opened by one `D_i` of the proof
+    pub commit_slots: Vec<CommitSlot<E>>,
+}
+
+impl<E: Pairing> ProvingKey<E> {
+    /// The total num of committed witness over all slots
+    pub fn commit_witness_num(&self) -> usize {
+        self.commit_slots.iter().map(|slot| slot.len()).sum()
+    }
+}
+
+/// The part of the proving key for one committed-witness group.
+///
+/// `indices` are the witness indices the slot commits to, in the order of the commitment,
+/// where index 0 is the first witness after the instance.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
+pub struct CommitSlot<E: Pairing> {
+    pub indices: Vec<usize>,
+    /// The element `eta_i / delta * G` in `E::G1`.
+    #[serde(with = "json::affine")]
+    pub eta_delta_inv_g1: E::G1Affine,
+    /// The element `eta_i / gamma_i * G` in `E::G1`.
+    #[serde(with = "json::affine")]
+    pub eta_gamma_inv_g1: E::G1Affine,
+    /// The committed witness query of C, divided by `gamma_i`
+    #[serde(with = "json::affines")]
+    pub comm_wit_abc_query: Vec<E::G1Affine>,
+}
+
+impl<E: Pairing> CommitSlot<E> {
+    /// The num of witness the slot commits to
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
 }
 
 impl<E: Pairing> Default for ProvingKey<E> {
@@ -108,20 +201,18 @@ impl<E: Pairing> Default for ProvingKey<E> {
             beta_g2: E::G2Affine::default(),
             delta_g1: E::G1Affine::default(),
             delta_g2: E::G2Affine::default(),
-            eta_delta_inv_g1: E::G1Affine::default(),
-            eta_gamma_inv_g1: E::G1Affine::default(),
             a_query: Vec::new(),
             b_g1_query: Vec::new(),
             b_g2_query: Vec::new(),
             h_query: Vec::new(),
-            comm_wit_abc_query: Vec::new(),
             ucomm_wit_abc_query: Vec::new(),
-            commit_witness_num: 0,
+            commit_slots: Vec::new(),
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
 pub struct CcPVKey<E: Pairing> {
     pub pk: ProvingKey<E>,
     pub vk: VerifyingKey<E>,
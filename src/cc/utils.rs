@@ -1,4 +1,12 @@
 use super::{create_random_proof, generate_random_parameters, verify_proof, CcPVKey};
+use super::{contribute, initial_parameters, verify_transcript, CcTranscript, Phase1Powers};
+use super::{prepare_verifying_key, verify_proof_with_prepared_vk, verify_proofs_batch};
+use super::{create_random_proof_with_slots, generate_random_parameters_with_slots, initial_parameters_with_slots};
+use super::create_proof_with_slots;
+use super::{create_proof_with_index, rerandomize, verify_proof_with_hashed_instance, ProverIndex};
+use super::helpers::{hash_instance, mimc_constants_round91};
+use super::generator::{uncommitted_witness_indices, uncommitted_witness_segments};
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
 use crate::cc;
 use crate::constants::*;
 use crate::lrs::lrs_circ::LRSCirc;
@@ -6,6 +14,7 @@ use crate::lrs::structures::CircDescriptor;
 use ark_ec::pairing::Pairing;
 use ark_ff::PrimeField;
 use ark_relations::lc;
+use ark_relations::r1cs::{LinearCombination, Variable};
 use ark_relations::r1cs::ConstraintSynthesizer;
 use ark_relations::r1cs::ConstraintSystemRef;
 use ark_relations::r1cs::Field;
@@ -23,10 +32,10 @@ where
     let rng = &mut ark_std::test_rng();
 
     let crs_cc: CcPVKey<E> =
-        cc::generate_random_parameters(lrs_circ.clone(), circ_desc.num_commit_witness, rng)
+        cc::generate_random_parameters(lrs_circ, circ_desc.num_commit_witness, rng)
             .unwrap();
 
-    let (cc_proof, _, _) = cc::create_random_proof(lrs_circ.clone(), &crs_cc.pk, rng).unwrap();
+    let (cc_proof, _, _) = cc::create_random_proof(lrs_circ, &crs_cc.pk, rng).unwrap();
 
     let instance = lrs_circ
         .instance
@@ -140,6 +149,122 @@ impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for TestCircuit2<Con
     }
 }
 
+/// The chain `x_{i+1} = x_i * x_i + x_0` for `i < num_constraints`, with `x_0, ..., x_{n-1}`
+/// as the witness and `x_n` as the single public input. Used to measure the prover at scale.
+pub struct SyntheticCircuit<F: Field> {
+    pub x: Option<F>,
+    pub num_constraints: usize,
+}
+
+impl<F: Field> SyntheticCircuit<F> {
+    /// The assignment `(1, x_n, x_0, ..., x_{n-1})`, without synthesizing the circuit.
+    pub fn full_assignment(&self) -> Result<Vec<F>, SynthesisError> {
+        let x_0 = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+        let mut assignment = Vec::with_capacity(self.num_constraints + 2);
+        assignment.extend([F::one(), F::zero(), x_0]);
+        let mut x = x_0;
+        for _ in 0..self.num_constraints {
+            x = x.square() + x_0;
+            assignment.push(x);
+        }
+        assignment[1] = assignment.pop().unwrap();
+        Ok(assignment)
+    }
+}
+
+impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for SyntheticCircuit<ConstraintF> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        let x_0_value = self.x;
+        let x_0 = cs.new_witness_variable(|| x_0_value.ok_or(SynthesisError::AssignmentMissing))?;
+        let mut x = x_0;
+        let mut x_value = x_0_value;
+        for i in 0..self.num_constraints {
+            let next_value = x_value.map(|x_i| x_i.square() + x_0_value.unwrap());
+            let next = if i + 1 == self.num_constraints {
+                cs.new_input_variable(|| next_value.ok_or(SynthesisError::AssignmentMissing))?
+            } else {
+                cs.new_witness_variable(|| next_value.ok_or(SynthesisError::AssignmentMissing))?
+            };
+            cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + next - x_0)?;
+            x = next;
+            x_value = next_value;
+        }
+
+        Ok(())
+    }
+}
+
+/// A linear combination together with its value, if known.
+type Signal<F> = (LinearCombination<F>, Option<F>);
+
+fn add_signals<F: Field>(a: &Signal<F>, b: &Signal<F>) -> Signal<F> {
+    (a.0.clone() + &b.0, a.1.zip(b.1).map(|(a, b)| a + b))
+}
+
+fn mul_signals<F: Field>(
+    cs: &ConstraintSystemRef<F>,
+    a: &Signal<F>,
+    b: &Signal<F>,
+) -> Result<Signal<F>, SynthesisError> {
+    let value = a.1.zip(b.1).map(|(a, b)| a * b);
+    let var = cs.new_witness_variable(|| value.ok_or(SynthesisError::AssignmentMissing))?;
+    cs.enforce_constraint(a.0.clone(), b.0.clone(), lc!() + var)?;
+    Ok((lc!() + var, value))
+}
+
+/// circomlib's `MultiMiMC7(n, 91)` with `k = 1` over the witnesses `inputs`, exposed as the
+/// single public input: a circuit with a compressed instance, for `cc::helpers::hash_instance`.
+pub struct HashedInstanceCircuit<F: Field> {
+    pub inputs: Vec<Option<F>>,
+    /// The round constants, as given by `mimc_constants_round91`
+    pub constants: Vec<F>,
+}
+
+impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for HashedInstanceCircuit<ConstraintF> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        let constant = |c: ConstraintF| (lc!() + (c, Variable::One), Some(c));
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|x| {
+                let var = cs.new_witness_variable(|| x.ok_or(SynthesisError::AssignmentMissing))?;
+                Ok((lc!() + var, *x))
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        let num_rounds = self.constants.len();
+        let mut r = constant(ConstraintF::one());
+        for x in &inputs {
+            // MiMC7(x, k = r)
+            let mut t7 = constant(ConstraintF::zero());
+            for i in 0..num_rounds {
+                let t = if i == 0 {
+                    add_signals(x, &r)
+                } else {
+                    add_signals(&add_signals(&t7, &r), &constant(self.constants[i]))
+                };
+                let t2 = mul_signals(&cs, &t, &t)?;
+                let t4 = mul_signals(&cs, &t2, &t2)?;
+                let t6 = mul_signals(&cs, &t4, &t2)?;
+                t7 = mul_signals(&cs, &t6, &t)?;
+            }
+            let mimc = add_signals(&t7, &r);
+            r = add_signals(&add_signals(&r, x), &mimc);
+        }
+
+        let hash = cs.new_input_variable(|| r.1.ok_or(SynthesisError::AssignmentMissing))?;
+        cs.enforce_constraint(r.0, lc!() + Variable::One, lc!() + hash)?;
+
+        Ok(())
+    }
+}
+
 /// 2 public inputs, 4 uncommitted witness
 pub fn cc_prove_and_verify_2<E>(n_iters: usize)
 where
@@ -228,6 +353,507 @@ where
     }
 }
 
+/// 2 public inputs, witnesses `a` and `(f, e)` committed in two slots, `b` uncommitted
+pub fn cc_prove_and_verify_slots<E>(n_iters: usize)
+where
+    E: Pairing,
+{
+    let rng = &mut ark_std::test_rng();
+    let circuit = || TestCircuit2::<E::ScalarField> {
+        a: None,
+        b: None,
+        e: None,
+        f: None,
+    };
+    // `b` sits between the slots, and the second slot commits to `f` before `e`
+    let slots = [vec![0], vec![3, 2]];
+
+    assert_eq!(
+        generate_random_parameters_with_slots::<E, _, _>(circuit(), &[vec![0, 1], vec![2, 1]], rng),
+        Err(cc::Error::InvalidCommitSlot(1))
+    );
+    assert_eq!(
+        generate_random_parameters_with_slots::<E, _, _>(circuit(), &[vec![2, 2]], rng),
+        Err(cc::Error::InvalidCommitSlot(0))
+    );
+    assert_eq!(
+        generate_random_parameters_with_slots::<E, _, _>(circuit(), &[vec![4]], rng),
+        Err(cc::Error::InsufficientWitnessesForCommitment(4, 5))
+    );
+
+    let params: CcPVKey<E> =
+        generate_random_parameters_with_slots::<E, _, _>(circuit(), &slots, rng).unwrap();
+    assert_eq!(params.pk.commit_witness_num(), 3);
+    assert_eq!(params.pk.ucomm_wit_abc_query.len(), 1);
+
+    // One blinding per slot
+    let one = E::ScalarField::from(1u64);
+    let full = TestCircuit2 {
+        a: Some(one),
+        b: Some(one),
+        e: Some(one),
+        f: Some(one),
+    };
+    assert_eq!(
+        create_proof_with_slots(full, &params.pk, one, one, one, &[one]).map(|_| ()),
+        Err(SynthesisError::AssignmentMissing)
+    );
+
+    // The same slots out of a phase-2 ceremony
+    let phase1 = Phase1Powers::<E>::new(
+        8,
+        E::ScalarField::rand(rng),
+        E::ScalarField::rand(rng),
+        E::ScalarField::rand(rng),
+    );
+    let initial = initial_parameters_with_slots(circuit(), &slots, &phase1).unwrap();
+    let mut transcript = CcTranscript::default();
+    let ceremony_params = contribute(&initial, &mut transcript, rng);
+    assert!(verify_transcript(&initial, &transcript, &ceremony_params));
+
+    for key in [&params, &ceremony_params] {
+        for _ in 0..n_iters {
+            let a = E::ScalarField::rand(rng);
+            let b = E::ScalarField::rand(rng);
+            let e = E::ScalarField::rand(rng);
+            let f = E::ScalarField::rand(rng);
+            let (proof, committed, vs) = create_random_proof_with_slots(
+                TestCircuit2 {
+                    a: Some(a),
+                    b: Some(b),
+                    e: Some(e),
+                    f: Some(f),
+                },
+                &key.pk,
+                rng,
+            )
+            .unwrap();
+            assert_eq!(committed, vec![vec![a], vec![f, e]]);
+
+            // Each D_i opens to its own slot
+            for ((slot, d), (witnesses, v)) in key
+                .pk
+                .commit_slots
+                .iter()
+                .zip(&proof.d)
+                .zip(committed.iter().zip(&vs))
+            {
+                let opening = slot.eta_gamma_inv_g1 * v
+                    + E::G1::msm(&slot.comm_wit_abc_query, witnesses).unwrap();
+                assert_eq!(opening.into_affine(), *d);
+            }
+
+            let instance = vec![(a * b).into_bigint(), (e * f).into_bigint()];
+            let pvk = prepare_verifying_key(&key.vk);
+            assert!(verify_proof_with_prepared_vk(&pvk, &proof, &instance).unwrap());
+
+            let mut swapped = proof.clone();
+            swapped.d.swap(0, 1);
+            assert!(!verify_proof_with_prepared_vk(&pvk, &swapped, &instance).unwrap());
+
+            // Moving mass between the slots breaks the per-slot gamma_i terms
+            let mut shifted = proof.clone();
+            let shift = key.pk.g1_generator;
+            shifted.d[0] = (shifted.d[0].into_group() + shift).into_affine();
+            shifted.d[1] = (shifted.d[1].into_group() - shift).into_affine();
+            assert!(!verify_proof_with_prepared_vk(&pvk, &shifted, &instance).unwrap());
+
+            let mut dropped = proof.clone();
+            dropped.d.pop();
+            assert!(!verify_proof_with_prepared_vk(&pvk, &dropped, &instance).unwrap());
+        }
+    }
+}
+
+/// 2 public inputs, 2 committed witness, 2 uncommitted witness, `batch_size` proofs verified at once
+pub fn cc_prove_and_verify_batch<E>(batch_size: usize)
+where
+    E: Pairing,
+{
+    let rng = &mut ark_std::test_rng();
+    let params: CcPVKey<E> = generate_random_parameters::<E, _, _>(
+        TestCircuit2 {
+            a: None,
+            b: None,
+            e: None,
+            f: None,
+        },
+        2,
+        rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let mut proofs = Vec::new();
+    let mut instances = Vec::new();
+    for _ in 0..batch_size {
+        let a = E::ScalarField::rand(rng);
+        let b = E::ScalarField::rand(rng);
+        let e = E::ScalarField::rand(rng);
+        let f = E::ScalarField::rand(rng);
+        let (proof, _, _) = create_random_proof(
+            TestCircuit2 {
+                a: Some(a),
+                b: Some(b),
+                e: Some(e),
+                f: Some(f),
+            },
+            &params.pk,
+            rng,
+        )
+        .unwrap();
+
+        let instance = vec![(a * b).into_bigint(), (e * f).into_bigint()];
+        assert!(verify_proof_with_prepared_vk(&pvk, &proof, &instance).unwrap());
+        proofs.push(proof);
+        instances.push(instance);
+    }
+    assert!(verify_proofs_batch(&pvk, &proofs, &instances, rng).unwrap());
+    assert!(verify_proofs_batch(&pvk, &proofs[..0], &instances[..0], rng).unwrap());
+    assert!(verify_proofs_batch(&pvk, &proofs[1..], &instances, rng).is_err());
+    let mut short = instances.clone();
+    short[1].pop();
+    assert_eq!(
+        verify_proofs_batch(&pvk, &proofs, &short, rng),
+        Err(cc::Error::LengthMismatch { expected: 2, got: 1 })
+    );
+
+    // A single corrupted proof or instance fails the whole batch
+    let last = batch_size - 1;
+    let mut corrupted = proofs.clone();
+    corrupted[last].c = proofs[0].c;
+    assert!(!verify_proof_with_prepared_vk(&pvk, &corrupted[last], &instances[last]).unwrap());
+    assert_eq!(
+        verify_proof_with_prepared_vk(&pvk, &proofs[0], &instances[0][1..]),
+        Err(cc::Error::LengthMismatch { expected: 2, got: 1 })
+    );
+    assert!(!verify_proofs_batch(&pvk, &corrupted, &instances, rng).unwrap());
+
+    let mut corrupted = proofs.clone();
+    corrupted[last].delta_prime = proofs[0].delta_prime;
+    assert!(!verify_proofs_batch(&pvk, &corrupted, &instances, rng).unwrap());
+
+    let mut corrupted = proofs.clone();
+    corrupted[last].d = proofs[0].d.clone();
+    assert!(!verify_proofs_batch(&pvk, &corrupted, &instances, rng).unwrap());
+
+    let mut corrupted = instances.clone();
+    corrupted[last].swap(0, 1);
+    assert!(!verify_proofs_batch(&pvk, &proofs, &corrupted, rng).unwrap());
+
+    // Swapping the `delta'` of two proofs keeps every aggregated term but must still fail
+    let mut swapped = proofs.clone();
+    swapped[0].delta_prime = proofs[last].delta_prime;
+    swapped[last].delta_prime = proofs[0].delta_prime;
+    assert!(!verify_proofs_batch(&pvk, &swapped, &instances, rng).unwrap());
+}
+
+/// 2 public inputs, 2 committed witness, 2 uncommitted witness, with the key from a phase-2 ceremony
+pub fn cc_ceremony_prove_and_verify<E>(n_contributions: usize)
+where
+    E: Pairing,
+{
+    let rng = &mut ark_std::test_rng();
+    let circuit = || TestCircuit2::<E::ScalarField> {
+        a: None,
+        b: None,
+        e: None,
+        f: None,
+    };
+
+    // TestCircuit2 has 4 constraints and 3 instance variables
+    let phase1 = Phase1Powers::<E>::new(
+        8,
+        E::ScalarField::rand(rng),
+        E::ScalarField::rand(rng),
+        E::ScalarField::rand(rng),
+    );
+    let initial = initial_parameters(circuit(), 2, &phase1).unwrap();
+    let too_short = Phase1Powers::<E>::new(4, 1u64.into(), 1u64.into(), 1u64.into());
+    assert!(initial_parameters(circuit(), 2, &too_short).is_err());
+
+    let mut transcript = CcTranscript::default();
+    let mut params = initial.clone();
+    for _ in 0..n_contributions {
+        params = contribute(&params, &mut transcript, rng);
+    }
+    assert!(verify_transcript(&initial, &transcript, &params));
+
+    for key in [&initial, &params] {
+        let a = E::ScalarField::rand(rng);
+        let b = E::ScalarField::rand(rng);
+        let e = E::ScalarField::rand(rng);
+        let f = E::ScalarField::rand(rng);
+        let (proof, _, _) = create_random_proof(
+            TestCircuit2 {
+                a: Some(a),
+                b: Some(b),
+                e: Some(e),
+                f: Some(f),
+            },
+            &key.pk,
+            rng,
+        )
+        .unwrap();
+
+        let instance = vec![(a * b).into_bigint(), (e * f).into_bigint()];
+        assert!(verify_proof(&key.vk, &proof, &instance).unwrap());
+    }
+
+    // The key must match the last contribution
+    let mut skipped = transcript.clone();
+    skipped.contributions.pop();
+    assert!(!verify_transcript(&initial, &skipped, &params));
+
+    let mut tampered = params.clone();
+    tampered.pk.h_query[0] = tampered.pk.h_query[1];
+    assert!(!verify_transcript(&initial, &transcript, &tampered));
+
+    let mut tampered = params.clone();
+    tampered.pk.commit_slots[0].eta_gamma_inv_g1 = tampered.pk.commit_slots[0].eta_delta_inv_g1;
+    assert!(!verify_transcript(&initial, &transcript, &tampered));
+
+    let mut forged = transcript.clone();
+    forged.contributions[0].eta[0].pok_z += E::ScalarField::from(1u64);
+    assert!(!verify_transcript(&initial, &forged, &params));
+}
+
+/// 2 public inputs, 2 committed witness, 2 uncommitted witness, proving from a `ProverIndex`
+pub fn cc_prove_and_verify_with_index<E>(n_iters: usize)
+where
+    E: Pairing,
+{
+    let rng = &mut ark_std::test_rng();
+    let circuit = || TestCircuit2::<E::ScalarField> {
+        a: None,
+        b: None,
+        e: None,
+        f: None,
+    };
+    let params: CcPVKey<E> = generate_random_parameters::<E, _, _>(circuit(), 2, rng).unwrap();
+    let index = ProverIndex::<E::ScalarField>::new(circuit()).unwrap();
+    assert_eq!(index.num_constraints(), 4);
+    assert_eq!(index.num_variables(), 7);
+
+    for _ in 0..n_iters {
+        let a = E::ScalarField::rand(rng);
+        let b = E::ScalarField::rand(rng);
+        let e = E::ScalarField::rand(rng);
+        let f = E::ScalarField::rand(rng);
+        let c = a * b;
+        let d = e * f;
+
+        // (1, instance, witness)
+        let full_assignment = vec![E::ScalarField::from(1u64), c, d, a, b, e, f];
+        let (proof, committed, _) =
+            create_proof_with_index(&index, &params.pk, &full_assignment, rng).unwrap();
+        assert_eq!(committed, vec![vec![a, b]]);
+
+        let instance = vec![c.into_bigint(), d.into_bigint()];
+        assert!(verify_proof(&params.vk, &proof, &instance).unwrap());
+
+        assert!(create_proof_with_index(&index, &params.pk, &full_assignment[1..], rng).is_err());
+
+        let mut wrong_assignment = full_assignment.clone();
+        wrong_assignment[5] += E::ScalarField::from(1u64);
+        assert!(!index.is_satisfied(&wrong_assignment));
+        assert_eq!(
+            create_proof_with_index(&index, &params.pk, &wrong_assignment, rng).unwrap_err(),
+            SynthesisError::Unsatisfiable
+        );
+    }
+
+    // A key for another circuit, or with slots out of the witness, is rejected
+    let full_assignment = vec![E::ScalarField::from(1u64); 7];
+    let mut other = params.pk.clone();
+    other.commit_slots[0].indices = vec![0, 4];
+    assert_eq!(
+        create_proof_with_index(&index, &other, &full_assignment, rng).map(|_| ()),
+        Err(SynthesisError::MalformedVerifyingKey)
+    );
+    let mut other = params.pk.clone();
+    other.a_query.pop();
+    assert_eq!(
+        create_proof_with_index(&index, &other, &full_assignment, rng).map(|_| ()),
+        Err(SynthesisError::MalformedVerifyingKey)
+    );
+}
+
+/// Rerandomized proofs verify for the same instance, and keep `D`
+pub fn cc_rerandomize_and_verify<E>(n_iters: usize)
+where
+    E: Pairing,
+{
+    let rng = &mut ark_std::test_rng();
+    let params: CcPVKey<E> = generate_random_parameters_with_slots::<E, _, _>(
+        TestCircuit2 {
+            a: None,
+            b: None,
+            e: None,
+            f: None,
+        },
+        &[vec![0], vec![2, 3]],
+        rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    for _ in 0..n_iters {
+        let a = E::ScalarField::rand(rng);
+        let b = E::ScalarField::rand(rng);
+        let e = E::ScalarField::rand(rng);
+        let f = E::ScalarField::rand(rng);
+        let (proof, _, _) = create_random_proof_with_slots(
+            TestCircuit2 {
+                a: Some(a),
+                b: Some(b),
+                e: Some(e),
+                f: Some(f),
+            },
+            &params.pk,
+            rng,
+        )
+        .unwrap();
+        let instance = vec![(a * b).into_bigint(), (e * f).into_bigint()];
+
+        let rerandomized = rerandomize(&params.vk, &proof, rng);
+        assert!(verify_proof_with_prepared_vk(&pvk, &rerandomized, &instance).unwrap());
+        assert_ne!(rerandomized.a, proof.a);
+        assert_ne!(rerandomized.b, proof.b);
+        assert_ne!(rerandomized.c, proof.c);
+        assert_ne!(rerandomized.delta_prime, proof.delta_prime);
+        assert_eq!(rerandomized.d, proof.d);
+
+        let twice = rerandomize(&params.vk, &rerandomized, rng);
+        assert!(verify_proof_with_prepared_vk(&pvk, &twice, &instance).unwrap());
+        assert_ne!(twice, rerandomized);
+        let proofs = [proof.clone(), rerandomized, twice];
+        assert!(verify_proofs_batch(&pvk, &proofs, &vec![instance.clone(); 3], rng).unwrap());
+
+        // Still bound to the instance, and invalid proofs stay invalid
+        let wrong_instance = vec![(a * b).into_bigint(), (e * f + b).into_bigint()];
+        let rerandomized = rerandomize(&params.vk, &proof, rng);
+        assert!(!verify_proof_with_prepared_vk(&pvk, &rerandomized, &wrong_instance).unwrap());
+
+        let mut corrupted = proof.clone();
+        corrupted.d.swap(0, 1);
+        let rerandomized = rerandomize(&params.vk, &corrupted, rng);
+        assert!(!verify_proof_with_prepared_vk(&pvk, &rerandomized, &instance).unwrap());
+    }
+}
+
+/// Both provers on a `SyntheticCircuit`, and the witness segments they stream over
+pub fn cc_prove_and_verify_synthetic<E>(num_constraints: usize)
+where
+    E: Pairing,
+{
+    for (slots, num_witness, segments) in [
+        (vec![], 5, vec![(0, 5)]),
+        (vec![vec![0, 1]], 5, vec![(2, 5)]),
+        (vec![vec![4, 3]], 5, vec![(0, 3)]),
+        (vec![vec![5, 4], vec![0]], 8, vec![(1, 4), (6, 8)]),
+        (vec![vec![1], vec![2]], 4, vec![(0, 1), (3, 4)]),
+        (vec![vec![6, 1, 3]], 8, vec![(0, 1), (2, 3), (4, 6), (7, 8)]),
+    ] {
+        assert_eq!(uncommitted_witness_segments(&slots, num_witness), segments);
+        let indices = segments.iter().flat_map(|(start, end)| *start..*end).collect::<Vec<_>>();
+        assert_eq!(indices, uncommitted_witness_indices(&slots, num_witness));
+    }
+
+    let rng = &mut ark_std::test_rng();
+    let circuit = |x| SyntheticCircuit::<E::ScalarField> { x, num_constraints };
+    let params: CcPVKey<E> = generate_random_parameters::<E, _, _>(circuit(None), 2, rng).unwrap();
+    let index = ProverIndex::<E::ScalarField>::new(circuit(None)).unwrap();
+    assert_eq!(index.num_constraints(), num_constraints);
+
+    let x = E::ScalarField::rand(rng);
+    let full_assignment = circuit(Some(x)).full_assignment().unwrap();
+    let instance = vec![full_assignment[1].into_bigint()];
+
+    let (proof, committed, _) =
+        create_proof_with_index(&index, &params.pk, &full_assignment, rng).unwrap();
+    assert_eq!(committed, vec![full_assignment[2..4].to_vec()]);
+    assert!(verify_proof(&params.vk, &proof, &instance).unwrap());
+
+    let (proof, committed, _) = create_random_proof(circuit(Some(x)), &params.pk, rng).unwrap();
+    assert_eq!(committed, full_assignment[2..4].to_vec());
+    assert!(verify_proof(&params.vk, &proof, &instance).unwrap());
+
+    // Committing to the last witness, then the first
+    let last = full_assignment.len() - 1;
+    let params: CcPVKey<E> =
+        generate_random_parameters_with_slots::<E, _, _>(circuit(None), &[vec![last - 2, 0]], rng).unwrap();
+    let expected = vec![full_assignment[last], full_assignment[2]];
+
+    let (proof, committed, _) =
+        create_proof_with_index(&index, &params.pk, &full_assignment, rng).unwrap();
+    assert_eq!(committed, vec![expected.clone()]);
+    assert!(verify_proof(&params.vk, &proof, &instance).unwrap());
+
+    let (proof, committed, _) = create_random_proof(circuit(Some(x)), &params.pk, rng).unwrap();
+    assert_eq!(committed, expected);
+    assert!(verify_proof(&params.vk, &proof, &instance).unwrap());
+}
+
+/// A compressed instance verifies from the logical public inputs, at a constant instance size
+pub fn cc_prove_and_verify_hashed_instance<E>(num_inputs: usize)
+where
+    E: Pairing,
+    <E::ScalarField as FromStr>::Err: Debug,
+{
+    let rng = &mut ark_std::test_rng();
+    let constants = mimc_constants_round91::<E>();
+    let circuit = |inputs| HashedInstanceCircuit::<E::ScalarField> {
+        inputs,
+        constants: constants.clone(),
+    };
+    let params: CcPVKey<E> =
+        generate_random_parameters::<E, _, _>(circuit(vec![None; num_inputs]), 2, rng).unwrap();
+    assert_eq!(params.vk.instance_abc_query.len(), 2);
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let inputs = (0..num_inputs)
+        .map(|_| E::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+    let (proof, committed, _) =
+        create_random_proof(circuit(inputs.iter().copied().map(Some).collect()), &params.pk, rng)
+            .unwrap();
+    assert_eq!(committed, inputs[..2].to_vec());
+
+    // The native hash is the circuit's public input
+    let instance = vec![hash_instance::<E>(&inputs).into_bigint()];
+    assert!(verify_proof(&params.vk, &proof, &instance).unwrap());
+    assert!(verify_proof_with_hashed_instance(&pvk, &proof, &inputs).unwrap());
+
+    let mut wrong_inputs = inputs.clone();
+    wrong_inputs.swap(0, 1);
+    assert!(!verify_proof_with_hashed_instance(&pvk, &proof, &wrong_inputs).unwrap());
+    assert!(!verify_proof_with_hashed_instance(&pvk, &proof, &inputs[1..]).unwrap());
+
+    // Keys with an uncompressed instance are rejected
+    let params: CcPVKey<E> = generate_random_parameters::<E, _, _>(
+        TestCircuit2 {
+            a: None,
+            b: None,
+            e: None,
+            f: None,
+        },
+        2,
+        rng,
+    )
+    .unwrap();
+    let mut pvk = prepare_verifying_key(&params.vk);
+    assert_eq!(
+        verify_proof_with_hashed_instance(&pvk, &proof, &inputs),
+        Err(cc::Error::LengthMismatch { expected: 1, got: 2 })
+    );
+    pvk.vk.instance_abc_query.clear();
+    assert_eq!(
+        verify_proof_with_hashed_instance(&pvk, &proof, &inputs),
+        Err(cc::Error::LengthMismatch { expected: 1, got: 0 })
+    );
+}
+
 pub fn cc_prove_and_verify_lrs<E>(n_iters: usize)
 where
     E: Pairing,
@@ -239,6 +865,7 @@ where
         ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
         path_prefix: PATH_PREFIX_LRS_SE.to_string(),
         circuit_name: CIRCUIT_NAME_LRS_SE.to_string(),
+        hashed_pub_io: None,
     };
 
     let lrs_circ = LRSCirc::<E::ScalarField>::construct(&circ_desc).unwrap();
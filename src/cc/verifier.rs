@@ -1,12 +1,38 @@
-use crate::cc::helpers::hash_to_field;
-use crate::cc::{Proof, VerifyingKey};
+use crate::cc::helpers::{hash_instance, hash_to_field};
+use crate::cc::{Error, PreparedVerifyingKey, Proof, VerifyingKey};
 use ark_ec::pairing::Pairing;
-use ark_ff::PrimeField;
+use ark_ff::{PrimeField, UniformRand};
 use ark_serialize::CanonicalSerialize;
 
 use ark_relations::r1cs::SynthesisError;
-use ark_ec::{CurveGroup, VariableBaseMSM};
-use std::ops::Neg;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_std::fmt::Debug;
+use ark_std::rand::Rng;
+use ark_std::str::FromStr;
+use ark_std::vec::Vec;
+
+/// Prepare the verifying key `vk` for use in proof verification.
+pub fn prepare_verifying_key<E: Pairing>(vk: &VerifyingKey<E>) -> PreparedVerifyingKey<E> {
+    vk.clone().into()
+}
+
+/// The hash `m` of `(A, B, D_1, ..., D_s)` that binds `C` to the rest of the proof.
+pub(crate) fn proof_hash<E: Pairing>(proof: &Proof<E>) -> E::ScalarField {
+    let mut bytes = Vec::new();
+    proof.a.serialize_compressed(&mut bytes).unwrap();
+    proof.b.serialize_compressed(&mut bytes).unwrap();
+    for d in &proof.d {
+        d.serialize_compressed(&mut bytes).unwrap();
+    }
+    hash_to_field::<E>(bytes)
+}
+
+fn instance_acc<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    instance: &[<E::ScalarField as PrimeField>::BigInt],
+) -> E::G1 {
+    vk.instance_abc_query[0] + E::G1::msm_bigint(&vk.instance_abc_query[1..], instance)
+}
 
 /// Verify a Groth16 proof `proof` against the prepared verification key `vk`,
 /// with respect to the instance `public_inputs`.
@@ -15,33 +41,148 @@ pub fn verify_proof<E: Pairing>(
     proof: &Proof<E>,
     instance: &[<E::ScalarField as PrimeField>::BigInt],
 ) -> crate::Result<bool> {
-    // Compute [C]_1
-    let mut bytes = Vec::new();
-    proof.a.serialize_compressed(&mut bytes).unwrap();
-    proof.b.serialize_compressed(&mut bytes).unwrap();
-    proof.d.serialize_compressed(&mut bytes).unwrap();
-    let m_hash = hash_to_field::<E>(bytes);
-
-    let instance_acc = vk.instance_abc_query[0] + E::G1::msm_bigint(&vk.instance_abc_query[1..], instance);
-
-    let qap = E::multi_miller_loop(
-        [
-            <E::G1Prepared>::from(proof.a),
-            <E::G1Prepared>::from(proof.c),
-            <E::G1Prepared>::from(proof.d),
-            <E::G1Prepared>::from(instance_acc),
-        ],
-        [
-            proof.b.into(),
-            (vk.delta_g2_neg_pc * m_hash - proof.delta_prime).into(),
-            vk.gamma_g2_neg_pc.into(),
-            vk.g2_generator.neg().into_affine()
-        ],
-    );
-
-    let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
-    let result = test == vk.alpha_beta_gt;
+    let result = verify_proof_with_prepared_vk(&prepare_verifying_key(vk), proof, instance)?;
     assert!(result, "Verification failed");
 
     Ok(result)
 }
+
+/// Verify a proof `proof` against the prepared verification key `pvk`,
+/// with respect to the instance `public_inputs`.
+///
+/// The per-proof term `e(C, m * (- delta * H) - delta')` is split as
+/// `e(m * C, - delta * H) * e(- C, delta')`, so that only `B` and `delta'` are prepared per call.
+pub fn verify_proof_with_prepared_vk<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    instance: &[<E::ScalarField as PrimeField>::BigInt],
+) -> crate::Result<bool> {
+    let num_instance = pvk.vk.instance_abc_query.len().saturating_sub(1);
+    if instance.len() != num_instance {
+        return Err(Error::LengthMismatch {
+            expected: num_instance,
+            got: instance.len(),
+        });
+    }
+    if proof.d.len() != pvk.gamma_g2_neg_pc.len() {
+        return Ok(false);
+    }
+    let m_hash = proof_hash(proof);
+
+    let mut g1 = vec![
+        <E::G1Prepared>::from(proof.a),
+        <E::G1Prepared>::from(proof.c * m_hash),
+        <E::G1Prepared>::from(-proof.c.into_group()),
+        <E::G1Prepared>::from(instance_acc(&pvk.vk, instance)),
+    ];
+    let mut g2 = vec![
+        proof.b.into(),
+        pvk.delta_g2_neg_pc.clone(),
+        proof.delta_prime.into(),
+        pvk.g2_generator_neg_pc.clone(),
+    ];
+    for (d, gamma_g2_neg) in proof.d.iter().zip(&pvk.gamma_g2_neg_pc) {
+        g1.push(<E::G1Prepared>::from(*d));
+        g2.push(gamma_g2_neg.clone());
+    }
+
+    let qap = E::multi_miller_loop(g1, g2);
+
+    let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
+
+    Ok(test == pvk.vk.alpha_beta_gt)
+}
+
+/// Verify a proof `proof` for a circuit whose single public input is `hash_instance(inputs)`,
+/// given the logical public inputs `inputs`. The pairing check is the same for any number of
+/// logical inputs.
+pub fn verify_proof_with_hashed_instance<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    inputs: &[E::ScalarField],
+) -> crate::Result<bool>
+where
+    <E::ScalarField as FromStr>::Err: Debug,
+{
+    let num_instance = pvk.vk.instance_abc_query.len().saturating_sub(1);
+    if pvk.vk.instance_abc_query.len() != 2 {
+        return Err(Error::LengthMismatch { expected: 1, got: num_instance });
+    }
+    let instance = [hash_instance::<E>(inputs).into_bigint()];
+    verify_proof_with_prepared_vk(pvk, proof, &instance)
+}
+
+/// Verify the proofs `proofs[k]` for `instances[k]` at once against the prepared
+/// verification key `pvk`.
+///
+/// The equation of each proof is raised to an independent random `r_k` sampled via `rng`,
+/// so that a batch with an invalid proof passes with probability at most `1 / |F|`. The
+/// terms paired with `B_k` and `delta'_k` are per proof, all others are aggregated, which
+/// gives a single final exponentiation and `2N + s + 2` Miller loops for `s` commitment slots.
+pub fn verify_proofs_batch<E: Pairing, R: Rng>(
+    pvk: &PreparedVerifyingKey<E>,
+    proofs: &[Proof<E>],
+    instances: &[Vec<<E::ScalarField as PrimeField>::BigInt>],
+    rng: &mut R,
+) -> crate::Result<bool> {
+    if proofs.len() != instances.len() {
+        return Err(Error::LengthMismatch {
+            expected: proofs.len(),
+            got: instances.len(),
+        });
+    }
+    if proofs.is_empty() {
+        return Ok(true);
+    }
+    let num_instance = pvk.vk.instance_abc_query.len().saturating_sub(1);
+    if let Some(instance) = instances.iter().find(|instance| instance.len() != num_instance) {
+        return Err(Error::LengthMismatch {
+            expected: num_instance,
+            got: instance.len(),
+        });
+    }
+    let num_slots = pvk.gamma_g2_neg_pc.len();
+    if proofs.iter().any(|proof| proof.d.len() != num_slots) {
+        return Ok(false);
+    }
+
+    let r = (0..proofs.len())
+        .map(|_| E::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+    let r_m = proofs
+        .iter()
+        .zip(&r)
+        .map(|(proof, r_k)| proof_hash(proof) * r_k)
+        .collect::<Vec<_>>();
+
+    let c = proofs.iter().map(|proof| proof.c).collect::<Vec<_>>();
+    let instance_accs = instances
+        .iter()
+        .map(|instance| instance_acc(&pvk.vk, instance))
+        .collect::<Vec<_>>();
+    let instance_accs = E::G1::normalize_batch(&instance_accs);
+
+    let mut g1 = Vec::with_capacity(2 * proofs.len() + num_slots + 2);
+    let mut g2 = Vec::with_capacity(2 * proofs.len() + num_slots + 2);
+    for (proof, r_k) in proofs.iter().zip(&r) {
+        g1.push(<E::G1Prepared>::from(proof.a * r_k));
+        g2.push(<E::G2Prepared>::from(proof.b));
+        g1.push(<E::G1Prepared>::from(-(proof.c * r_k)));
+        g2.push(<E::G2Prepared>::from(proof.delta_prime));
+    }
+    g1.push(<E::G1Prepared>::from(E::G1::msm(&c, &r_m).unwrap()));
+    g2.push(pvk.delta_g2_neg_pc.clone());
+    for (i, gamma_g2_neg) in pvk.gamma_g2_neg_pc.iter().enumerate() {
+        let d = proofs.iter().map(|proof| proof.d[i]).collect::<Vec<_>>();
+        g1.push(<E::G1Prepared>::from(E::G1::msm(&d, &r).unwrap()));
+        g2.push(gamma_g2_neg.clone());
+    }
+    g1.push(<E::G1Prepared>::from(E::G1::msm(&instance_accs, &r).unwrap()));
+    g2.push(pvk.g2_generator_neg_pc.clone());
+
+    let qap = E::multi_miller_loop(g1, g2);
+    let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
+    let r_sum: E::ScalarField = r.iter().sum();
+
+    Ok(test == pvk.vk.alpha_beta_gt * r_sum)
+}
@@ -1,3 +1,6 @@
+use ark_std::string::{String, ToString};
+use ark_std::vec::Vec;
+
 // Information of LRS circuit for the CircDescriptor
 pub const NUM_PUB_IO_LRS_SE: usize = 2;
 pub const NUM_COMMIT_WITNESS_LRS_SE: usize = 1;
@@ -5,6 +8,9 @@ pub const IOPUTS_NAME_LRS_SE: [&str; 4] = ["main.sc", "main.L", "main.phi", "mai
 pub const PATH_PREFIX_LRS_SE: &str = "./circoms/lrs/";
 pub const CIRCUIT_NAME_LRS_SE: &str = "lrs";
 
+/// The public signal whose value links two signatures by the same signer
+pub const LINK_TAG_SIGNAL: &str = "main.L";
+
 pub const CIRCUIT_NAME_SCHNORR_VERIFY: &str = "schnorr_verify";
 
 macro_rules! define_input_and_circuit_names_lrs_a {
@@ -35,6 +41,24 @@ macro_rules! define_input_and_circuit_names_lrs_a {
 pub const NUM_PUB_IO_LRS_A: usize = 7;
 pub const NUM_COMMIT_WITNESS_LRS_A: usize = 0;
 pub const PATH_PREFIX_LRS_A: &str = "./circoms/lrs_a/lrs_a/lrs_a_circoms/";
+
+/// The signals of the lrs_a circuit for a Merkle tree of `depth`, as
+/// `define_input_and_circuit_names_lrs_a!` lists them, for any depth.
+pub fn ioputs_name_lrs_a(depth: usize) -> Vec<String> {
+    let names = [
+        "main.root", "main.sc", "main.L", "main.msg", "main.R[0]", "main.R[1]", "main.s", "main.sk", "main.pk[0]",
+        "main.pk[1]", "main.phi",
+    ];
+    let path_elements = (0..depth).map(|i| format!("main.pathElements[{}]", i));
+    let path_indices = (0..depth).map(|i| format!("main.pathIndices[{}]", i));
+    names.iter().map(|name| name.to_string()).chain(path_elements).chain(path_indices).collect()
+}
+
+/// The name of the lrs_a circuit for a Merkle tree of `depth`, as `circoms/lrs_a/lrs_a.sh` writes it.
+pub fn circuit_name_lrs_a(depth: usize) -> String {
+    format!("lrs_a_{}", depth)
+}
+
 define_input_and_circuit_names_lrs_a!(IOPUTS_NAME_LRS_A_3, CIRCUIT_NAME_LRS_A_3, 3, [0, 1, 2]);
 define_input_and_circuit_names_lrs_a!(IOPUTS_NAME_LRS_A_4, CIRCUIT_NAME_LRS_A_4, 4, [0, 1, 2, 3]);
 define_input_and_circuit_names_lrs_a!(
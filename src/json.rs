@@ -0,0 +1,224 @@
+//! JSON encodings of keys, rings and signatures, as circom and snarkjs write them: an element
+//! of a prime field is a decimal string, an element of an extension field the array of its
+//! coefficients, and a point `[x, y, 1]` in projective coordinates, or `[0, 1, 0]` at infinity.
+//!
+//! The modules below are serde adapters for `#[serde(with = "...")]`, through which the public
+//! artifacts derive `Serialize` and `Deserialize`. Decoding accepts nothing but the encoding
+//! `serialize` writes, so that decoding and re-encoding a value changes no byte, and rejects
+//! points outside the prime-order subgroup like `CanonicalDeserialize` does.
+
+use ark_ec::pairing::Pairing;
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ec::AffineRepr;
+use ark_ff::{Field, One, PrimeField, Zero};
+use ark_serialize::Valid;
+use ark_std::string::String;
+use ark_std::vec::Vec;
+use num_bigint::BigUint;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An affine point that can be rebuilt from its coordinates.
+pub trait JsonAffine: AffineRepr {
+    /// The point `(x, y)`, or `None` if it is not in the prime-order subgroup.
+    fn from_xy(x: Self::BaseField, y: Self::BaseField) -> Option<Self>;
+}
+
+impl<P: SWCurveConfig> JsonAffine for Affine<P> {
+    fn from_xy(x: P::BaseField, y: P::BaseField) -> Option<Self> {
+        let point = Affine::new_unchecked(x, y);
+        point.check().is_ok().then_some(point)
+    }
+}
+
+/// A pairing whose points have a JSON encoding.
+pub trait JsonPairing: Pairing<G1Affine: JsonAffine, G2Affine: JsonAffine> {}
+
+impl<E: Pairing<G1Affine: JsonAffine, G2Affine: JsonAffine>> JsonPairing for E {}
+
+/// An element of a prime field as a decimal string.
+struct Decimal<F>(F);
+
+impl<F: PrimeField> Serialize for Decimal<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0.into_bigint())
+    }
+}
+
+impl<'de, F: PrimeField> Deserialize<'de> for Decimal<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        // Digits only, without leading zeros, so that every element has one encoding
+        let canonical = !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) && (s == "0" || !s.starts_with('0'));
+        canonical
+            .then(|| s.parse::<BigUint>().ok())
+            .flatten()
+            .and_then(|n| F::BigInt::try_from(n).ok())
+            .and_then(F::from_bigint)
+            .map(Decimal)
+            .ok_or_else(|| D::Error::custom(format_args!("{:?} is not an element of the field in decimal", s)))
+    }
+}
+
+/// An element of a field as `Decimal`, or as the array of its coefficients over the prime field.
+struct Coordinate<F>(F);
+
+impl<F: Field> Serialize for Coordinate<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut coeffs = self.0.to_base_prime_field_elements().map(Decimal);
+        if F::extension_degree() == 1 {
+            coeffs.next().unwrap().serialize(serializer)
+        } else {
+            serializer.collect_seq(coeffs)
+        }
+    }
+}
+
+impl<'de, F: Field> Deserialize<'de> for Coordinate<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let coeffs: Vec<F::BasePrimeField> = if F::extension_degree() == 1 {
+            vec![Decimal::deserialize(deserializer)?.0]
+        } else {
+            Vec::<Decimal<_>>::deserialize(deserializer)?.into_iter().map(|c| c.0).collect()
+        };
+        F::from_base_prime_field_elems(&coeffs).map(Coordinate).ok_or_else(|| {
+            D::Error::custom(format_args!("expected {} coefficients, found {}", F::extension_degree(), coeffs.len()))
+        })
+    }
+}
+
+/// A point as `[x, y, 1]`, or `[0, 1, 0]` at infinity.
+struct Point<G>(G);
+
+impl<G: JsonAffine> Serialize for Point<G> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (x, y, z) = match self.0.xy() {
+            Some((x, y)) => (*x, *y, G::BaseField::one()),
+            None => (G::BaseField::zero(), G::BaseField::one(), G::BaseField::zero()),
+        };
+        [Coordinate(x), Coordinate(y), Coordinate(z)].serialize(serializer)
+    }
+}
+
+impl<'de, G: JsonAffine> Deserialize<'de> for Point<G> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let [x, y, z] = <[Coordinate<G::BaseField>; 3]>::deserialize(deserializer)?.map(|c| c.0);
+        if z.is_one() {
+            G::from_xy(x, y)
+                .map(Point)
+                .ok_or_else(|| D::Error::custom("the point is not in the prime-order subgroup"))
+        } else if z.is_zero() && x.is_zero() && y.is_one() {
+            Ok(Point(G::zero()))
+        } else {
+            Err(D::Error::custom("expected a point [x, y, 1], or [0, 1, 0] at infinity"))
+        }
+    }
+}
+
+/// A prime field element, e.g. a scalar, as a decimal string.
+pub mod field {
+    use super::*;
+
+    pub fn serialize<F: PrimeField, S: Serializer>(value: &F, serializer: S) -> Result<S::Ok, S::Error> {
+        Decimal(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(deserializer: D) -> Result<F, D::Error> {
+        Ok(Decimal::deserialize(deserializer)?.0)
+    }
+}
+
+/// A vector of prime field elements, e.g. a ring or the public inputs of a signature.
+pub mod fields {
+    use super::*;
+
+    pub fn serialize<F: PrimeField, S: Serializer>(values: &[F], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|value| Decimal(*value)))
+    }
+
+    pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<F>, D::Error> {
+        Ok(Vec::<Decimal<F>>::deserialize(deserializer)?.into_iter().map(|value| value.0).collect())
+    }
+}
+
+/// An affine point.
+pub mod affine {
+    use super::*;
+
+    pub fn serialize<G: JsonAffine, S: Serializer>(point: &G, serializer: S) -> Result<S::Ok, S::Error> {
+        Point(*point).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, G: JsonAffine, D: Deserializer<'de>>(deserializer: D) -> Result<G, D::Error> {
+        Ok(Point::deserialize(deserializer)?.0)
+    }
+}
+
+/// A vector of affine points.
+pub mod affines {
+    use super::*;
+
+    pub fn serialize<G: JsonAffine, S: Serializer>(points: &[G], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(points.iter().map(|point| Point(*point)))
+    }
+
+    pub fn deserialize<'de, G: JsonAffine, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<G>, D::Error> {
+        Ok(Vec::<Point<G>>::deserialize(deserializer)?.into_iter().map(|point| point.0).collect())
+    }
+}
+
+/// A matrix of affine points as the array of its columns, e.g. the link prover key.
+pub mod affine_columns {
+    use super::*;
+
+    pub fn serialize<G: JsonAffine, S: Serializer>(columns: &[Vec<G>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(columns.iter().map(|column| column.iter().map(|point| Point(*point)).collect::<Vec<_>>()))
+    }
+
+    pub fn deserialize<'de, G: JsonAffine, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<G>>, D::Error> {
+        Ok(Vec::<Vec<Point<G>>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|column| column.into_iter().map(|point| point.0).collect())
+            .collect())
+    }
+}
+
+/// A point in projective coordinates, encoded as its affine form.
+pub mod projective {
+    use super::*;
+    use ark_ec::CurveGroup;
+
+    pub fn serialize<G, S>(point: &G, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        G: CurveGroup<Affine: JsonAffine>,
+        S: Serializer,
+    {
+        Point(point.into_affine()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, G, D>(deserializer: D) -> Result<G, D::Error>
+    where
+        G: CurveGroup<Affine: JsonAffine>,
+        D: Deserializer<'de>,
+    {
+        Ok(Point::<G::Affine>::deserialize(deserializer)?.0.into_group())
+    }
+}
+
+/// An element of the pairing target group, as the array of its coefficients over the prime field.
+pub mod target {
+    use super::*;
+    use ark_ec::pairing::PairingOutput;
+
+    pub fn serialize<E: Pairing, S: Serializer>(value: &PairingOutput<E>, serializer: S) -> Result<S::Ok, S::Error> {
+        Coordinate(value.0).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, E: Pairing, D: Deserializer<'de>>(deserializer: D) -> Result<PairingOutput<E>, D::Error> {
+        let value = PairingOutput(Coordinate::deserialize(deserializer)?.0);
+        value
+            .check()
+            .map(|_| value)
+            .map_err(|_| D::Error::custom("the value is not in the pairing target group"))
+    }
+}
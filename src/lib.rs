@@ -4,11 +4,17 @@
 #![allow(ambiguous_glob_reexports)]
 #![forbid(unsafe_code)]
 
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+
 pub mod link;
 pub mod cc;
 pub mod sma;
 pub mod lrs;
 pub mod constants;
+pub mod time;
+pub mod json;
 
 use crate::cc::error;
 pub type Result<T> = core::result::Result<T, error::Error>;
\ No newline at end of file
@@ -1,6 +1,15 @@
-use ark_ec::{pairing::Pairing, CurveGroup};
-use ark_ff::Zero;
-use std::ops::{AddAssign, Mul};
+use ark_ec::{pairing::Pairing, CurveGroup, VariableBaseMSM};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_std::cfg_into_iter;
+use sha2::{Digest, Sha256};
+use ark_std::ops::Mul;
+use ark_std::vec::Vec;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 ///! This module contains the implementation of a sparse matrix
 /// The sparse matrix is represented as a vector of columns
@@ -20,11 +29,26 @@ use std::ops::{AddAssign, Mul};
 /// SparseMatrix::insert_row_slice(r, c_offset, vs) 
 ///     - insert a contiguous sequence of values at row r starting from c_offset
 /// 
+/// SparseMatrixBuilder::{insert_val, insert_row_slice, build}
+///     - the same, rejecting out-of-range and duplicate (r, c) entries
+/// 
+/// SparseMatrix::to_dense() / SparseMatrix::from_dense(rows)
+///     - convert to and from a vector of rows, absent entries being T::default()
+/// 
+/// SparseMatrix::iter_row_major()
+///     - iterate over the (r, c, v) entries in row-major order
+/// 
+/// SparseMatrix::digest()
+///     - SHA-256 of the shape and the non-identity entries, recorded in the link keys
+/// 
 /// SparseLinAlgebra::sparse_inner_product(v, w) 
 ///     - compute the inner product of a column of a sparse matrix and another (sparse) vector
 /// 
 /// SparseLinAlgebra::sparse_vector_matrix_mult(v, m, c) 
-///     - compute the product of a sparse vector and a sparse matrix
+///     - compute the product of a sparse vector and a sparse matrix, one MSM per column (in parallel)
+/// 
+/// SparseLinAlgebra::sparse_vector_matrix_mult_in::<G>(v, m, c)
+///     - the same for a matrix over G_1 or G_2
 /// 
 /// inner_product(v, w)
 ///    - compute the inner product of two vectors
@@ -33,10 +57,54 @@ use std::ops::{AddAssign, Mul};
 ///   - compute the product of a scalar and a vector
 
 /// CoeffPos: A struct to help build sparse matrices.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct CoeffPos<T> {
-    val: T,
-    pos: usize,
+    pub val: T,
+    pub pos: usize, // the row of `val`
+}
+
+impl<T: CanonicalSerialize> CanonicalSerialize for CoeffPos<T> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.pos.serialize_with_mode(&mut writer, compress)?;
+        self.val.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.pos.serialized_size(compress) + self.val.serialized_size(compress)
+    }
+}
+
+impl<T: Valid> Valid for CoeffPos<T> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.val.check()
+    }
+}
+
+impl<T: CanonicalDeserialize> CanonicalDeserialize for CoeffPos<T> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let pos = usize::deserialize_with_mode(&mut reader, compress, validate)?;
+        let val = T::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(CoeffPos { val, pos })
+    }
+}
+
+/// Reasons a `SparseMatrixBuilder` or `SparseMatrix::from_dense` rejects an entry.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SparseMatrixError {
+    /// The entry (r, c) is outside of the (nr, nc) matrix.
+    OutOfRange((usize, usize), (usize, usize)),
+    /// The entry (r, c) is already set.
+    Duplicate(usize, usize),
+    /// Row `r` of a dense matrix has (found, expected) entries.
+    RaggedRow(usize, usize, usize),
 }
 
 // a column is a vector of CoeffPos-s
@@ -78,6 +146,175 @@ impl<T: Copy> SparseMatrix<T> {
     pub fn get_col(&self, c: usize) -> &Col<T> {
         &self.cols[c]
     }
+
+    /// A builder for a `nr x nc` matrix that checks every entry.
+    pub fn builder(nr: usize, nc: usize) -> SparseMatrixBuilder<T> {
+        SparseMatrixBuilder { m: SparseMatrix::new(nr, nc) }
+    }
+
+    /// The entries as (row, column, value), sorted by row and then by column.
+    pub fn iter_row_major(&self) -> impl Iterator<Item = (usize, usize, &T)> {
+        let mut entries = self
+            .cols
+            .iter()
+            .enumerate()
+            .flat_map(|(c, col)| col.iter().map(move |entry| (entry.pos, c, &entry.val)))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(r, c, _)| (*r, *c));
+        entries.into_iter()
+    }
+
+    /// Checks that every entry is in range and set only once.
+    fn check_entries(&self) -> Result<(), SparseMatrixError> {
+        let mut builder = SparseMatrix::builder(self.nr, self.nc);
+        for (c, col) in self.cols.iter().enumerate() {
+            for coeff_pos in col {
+                builder.insert_val(coeff_pos.pos, c, &coeff_pos.val)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Copy + Default + PartialEq> SparseMatrix<T> {
+    /// The dense rows of the matrix; absent entries are `T::default()`, the identity for
+    /// curve points.
+    pub fn to_dense(&self) -> Vec<Vec<T>> {
+        let mut rows = vec![vec![T::default(); self.nc]; self.nr];
+        for (r, c, v) in self.iter_row_major() {
+            rows[r][c] = *v;
+        }
+        rows
+    }
+
+    /// The sparse matrix of the given rows, keeping the entries other than `T::default()`.
+    pub fn from_dense(rows: &[Vec<T>]) -> Result<SparseMatrix<T>, SparseMatrixError> {
+        let nc = rows.first().map_or(0, |row| row.len());
+        let mut builder = SparseMatrix::builder(rows.len(), nc);
+        for (r, row) in rows.iter().enumerate() {
+            if row.len() != nc {
+                return Err(SparseMatrixError::RaggedRow(r, row.len(), nc));
+            }
+            for (c, v) in row.iter().enumerate() {
+                if *v != T::default() {
+                    builder.insert_val(r, c, v)?;
+                }
+            }
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Two matrices are equal if they have the same shape and the same entries other than
+/// `T::default()`, whatever the order in which the entries were inserted.
+impl<T: Copy + Default + PartialEq> PartialEq for SparseMatrix<T> {
+    fn eq(&self, other: &Self) -> bool {
+        let non_default = |m: &Self| {
+            m.iter_row_major()
+                .filter(|(_, _, v)| **v != T::default())
+                .map(|(r, c, v)| (r, c, *v))
+                .collect::<Vec<_>>()
+        };
+        (self.nr, self.nc) == (other.nr, other.nc) && non_default(self) == non_default(other)
+    }
+}
+
+impl<T: Copy + Default + PartialEq + CanonicalSerialize> SparseMatrix<T> {
+    /// SHA-256 of the shape and the compressed (row, column, value) entries other than
+    /// `T::default()`, in row-major order, identifying the matrix a link key was generated
+    /// for. Equal matrices have the same digest, see `PartialEq`.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut bytes = Vec::new();
+        self.nr.serialize_compressed(&mut bytes).unwrap();
+        self.nc.serialize_compressed(&mut bytes).unwrap();
+        for (r, c, v) in self.iter_row_major().filter(|(_, _, v)| **v != T::default()) {
+            r.serialize_compressed(&mut bytes).unwrap();
+            c.serialize_compressed(&mut bytes).unwrap();
+            v.serialize_compressed(&mut bytes).unwrap();
+        }
+        Sha256::digest(&bytes).into()
+    }
+}
+
+impl<T: CanonicalSerialize> CanonicalSerialize for SparseMatrix<T> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.nr.serialize_with_mode(&mut writer, compress)?;
+        self.nc.serialize_with_mode(&mut writer, compress)?;
+        self.cols.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.nr.serialized_size(compress)
+            + self.nc.serialized_size(compress)
+            + self.cols.serialized_size(compress)
+    }
+}
+
+impl<T: Copy + Valid> Valid for SparseMatrix<T> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.check_entries().map_err(|_| SerializationError::InvalidData)?;
+        self.cols.check()
+    }
+}
+
+/// Rejects out-of-range and duplicate entries whatever the `validate` mode, which only
+/// decides whether the values are checked.
+impl<T: Copy + CanonicalDeserialize> CanonicalDeserialize for SparseMatrix<T> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let nr = usize::deserialize_with_mode(&mut reader, compress, validate)?;
+        let nc = usize::deserialize_with_mode(&mut reader, compress, validate)?;
+        let cols = Vec::<Col<T>>::deserialize_with_mode(&mut reader, compress, validate)?;
+        if cols.len() != nc {
+            return Err(SerializationError::InvalidData);
+        }
+        let m = SparseMatrix { cols, nr, nc };
+        m.check_entries().map_err(|_| SerializationError::InvalidData)?;
+        Ok(m)
+    }
+}
+
+/// Builds a `SparseMatrix`, rejecting out-of-range and duplicate (r, c) entries.
+#[derive(Clone, Debug)]
+pub struct SparseMatrixBuilder<T> {
+    m: SparseMatrix<T>,
+}
+
+impl<T: Copy> SparseMatrixBuilder<T> {
+    pub fn insert_val(&mut self, r: usize, c: usize, v: &T) -> Result<(), SparseMatrixError> {
+        if r >= self.m.nr || c >= self.m.nc {
+            return Err(SparseMatrixError::OutOfRange((r, c), (self.m.nr, self.m.nc)));
+        }
+        if self.m.cols[c].iter().any(|coeff_pos| coeff_pos.pos == r) {
+            return Err(SparseMatrixError::Duplicate(r, c));
+        }
+        self.m.insert_val(r, c, v);
+        Ok(())
+    }
+
+    // insert a contiguous sequence of values at row r starting from c_offset
+    pub fn insert_row_slice(
+        &mut self,
+        r: usize,
+        c_offset: usize,
+        vs: &[T],
+    ) -> Result<(), SparseMatrixError> {
+        for (i, x) in vs.iter().enumerate() {
+            self.insert_val(r, c_offset + i, x)?;
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> SparseMatrix<T> {
+        self.m
+    }
 }
 
 pub struct SparseLinAlgebra<PE: Pairing> {
@@ -87,44 +324,49 @@ pub struct SparseLinAlgebra<PE: Pairing> {
 impl<PE: Pairing> SparseLinAlgebra<PE> {
     // Inner product of a column of a sparse matrix and another (sparse) vector
     // this is basically a multi-exp
-    pub fn sparse_inner_product(v: &Vec<PE::ScalarField>, w: &Col<PE::G1Affine>) -> PE::G1Affine {
-        let mut res: PE::G1 = PE::G1::zero();
-        for coeffpos in w {
-            let g = coeffpos.val;
-            let i = coeffpos.pos;
-            // XXX: Should this be optimized for special cases
-            //         (e.g. 0 or 1) or is this already in .mul?
-            let tmp = g.mul(v[i]);
-
-            res.add_assign(&tmp);
-        }
-        res.into_affine()
+    pub fn sparse_inner_product(v: &[PE::ScalarField], w: &Col<PE::G1Affine>) -> PE::G1Affine {
+        Self::sparse_inner_product_projective::<PE::G1>(v, w).into_affine()
+    }
+
+    fn sparse_inner_product_projective<G>(v: &[PE::ScalarField], w: &Col<G::Affine>) -> G
+    where
+        G: CurveGroup<ScalarField = PE::ScalarField>,
+    {
+        let (bases, scalars): (Vec<_>, Vec<_>) =
+            w.iter().map(|coeffpos| (coeffpos.val, v[coeffpos.pos])).unzip();
+        G::msm_unchecked(&bases, &scalars)
     }
 
     pub fn sparse_vector_matrix_mult(
-        v: &Vec<PE::ScalarField>,
+        v: &[PE::ScalarField],
         m: &SparseMatrix<PE::G1Affine>,
         nc: usize,
     ) -> Vec<PE::G1Affine> {
+        Self::sparse_vector_matrix_mult_in::<PE::G1>(v, m, nc)
+    }
+
+    /// `sparse_vector_matrix_mult` for a matrix over `G_1` or `G_2`.
+    pub fn sparse_vector_matrix_mult_in<G>(
+        v: &[PE::ScalarField],
+        m: &SparseMatrix<G::Affine>,
+        nc: usize,
+    ) -> Vec<G::Affine>
+    where
+        G: CurveGroup<ScalarField = PE::ScalarField>,
+    {
         // the result should contain every column of m multiplied by v
         assert!(nc == m.nc);
         assert!(v.len() == m.nr);
-        let mut res: Vec<PE::G1Affine> = Vec::with_capacity(nc);
-        for c in 0..m.nc {
-            res.push(Self::sparse_inner_product(&v, &m.get_col(c)));
-        }
-        res
+        let res = cfg_into_iter!(0..m.nc)
+            .map(|c| Self::sparse_inner_product_projective::<G>(v, m.get_col(c)))
+            .collect::<Vec<_>>();
+        G::normalize_batch(&res)
     }
 }
 
 pub fn inner_product<PE: Pairing>(v: &[PE::ScalarField], w: &[PE::G1Affine]) -> PE::G1Affine {
     assert_eq!(v.len(), w.len());
-    let mut res: PE::G1 = PE::G1::zero();
-    for i in 0..v.len() {
-        let tmp = w[i].mul(v[i]);
-        res.add_assign(&tmp);
-    }
-    res.into_affine()
+    PE::G1::msm_unchecked(w, v).into_affine()
 }
 
 pub fn scalar_vector_mult<PE: Pairing>(a: &PE::ScalarField, v: &[PE::ScalarField], r: usize) -> Vec<PE::ScalarField> {
@@ -6,12 +6,19 @@ pub use snark::*;
 
 #[cfg(test)]
 mod tests {
-    use super::{PESubspaceSnark, SparseMatrix, SubspaceSnark, PP};
+    use super::{
+        G2SubspaceSnark, MddhSubspaceSnark, PESubspaceSnark, SparseMatrix, SparseMatrixError,
+        SubspaceSnark, PP,
+    };
     // use ark_bls12_381::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
     use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
     use ark_ec::{CurveGroup, Group};
     use ark_ff::{One, Zero};
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
     use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use ark_std::UniformRand;
+
+    const LABEL: &[u8] = b"label";
 
     #[test]
     fn test_basic_g2() {
@@ -34,10 +41,264 @@ mod tests {
 
         let evk = PESubspaceSnark::<Bn254>::keygen(&mut rng, &pp, &m);
 
-        let pi = PESubspaceSnark::<Bn254>::prove(&mut pp, &evk.ek, &x);
-        let pi_bad = PESubspaceSnark::<Bn254>::prove(&mut pp, &evk.ek, &x_bad);
+        let pi = PESubspaceSnark::<Bn254>::prove(&mut pp, &evk.ek, &x, LABEL);
+        let pi_bad = PESubspaceSnark::<Bn254>::prove(&mut pp, &evk.ek, &x_bad, LABEL);
+
+        assert!(PESubspaceSnark::<Bn254>::verify(&pp, &evk.vk, &y, &pi, LABEL));
+        assert!(!PESubspaceSnark::<Bn254>::verify(&pp, &evk.vk, &y, &pi_bad, LABEL));
+    }
+
+    /// A random `nr x nc` matrix `M` over `G_1`, a random `x` and `y = M x`.
+    fn random_instance(
+        rng: &mut StdRng,
+        nr: usize,
+        nc: usize,
+    ) -> (PP<G1Affine, G2Affine>, SparseMatrix<G1Affine>, Vec<Fr>, Vec<G1Affine>) {
+        let g1 = G1Projective::generator().into_affine();
+        let g2 = G2Projective::generator().into_affine();
+        let pp = PP::<G1Affine, G2Affine> { nr, nc, g1, g2 };
+
+        let rows = (0..nr)
+            .map(|_| (0..nc).map(|_| G1Projective::rand(rng).into_affine()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut m = SparseMatrix::new(nr, nc);
+        for (r, row) in rows.iter().enumerate() {
+            m.insert_row_slice(r, 0, row.clone());
+        }
+
+        let x = (0..nc).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+        let y = rows
+            .iter()
+            .map(|row| row.iter().zip(&x).map(|(m_ij, x_j)| *m_ij * x_j).sum::<G1Projective>())
+            .collect::<Vec<_>>();
+        (pp, m, x, G1Projective::normalize_batch(&y))
+    }
+
+    fn check_mddh<const K: usize>() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (pp, m, x, y) = random_instance(&mut rng, 3, 5);
+
+        let evk = MddhSubspaceSnark::<Bn254, K>::keygen(&mut rng, &pp, &m);
+        assert_eq!(evk.ek.p_0.len(), K + 1);
+        assert_eq!(evk.vk.a.len(), K);
+        assert!(evk.vk.c_0.iter().all(|col| col.len() == pp.nr));
+
+        let pi = MddhSubspaceSnark::<Bn254, K>::prove(&pp, &evk.ek, &x, LABEL);
+        assert_eq!(pi.len(), K + 1);
+        assert!(MddhSubspaceSnark::<Bn254, K>::verify(&pp, &evk.vk, &y, &pi, LABEL));
+
+        // y out of the span of M
+        let mut y_out = y.clone();
+        y_out[0] = (y_out[0] + pp.g1).into_affine();
+        assert!(!MddhSubspaceSnark::<Bn254, K>::verify(&pp, &evk.vk, &y_out, &pi, LABEL));
+
+        // A proof for another x, and a truncated proof
+        let mut x_bad = x.clone();
+        x_bad[0] += Fr::one();
+        let pi_bad = MddhSubspaceSnark::<Bn254, K>::prove(&pp, &evk.ek, &x_bad, LABEL);
+        assert!(!MddhSubspaceSnark::<Bn254, K>::verify(&pp, &evk.vk, &y, &pi_bad, LABEL));
+        let pi_short = pi[..K].to_vec();
+        assert!(!MddhSubspaceSnark::<Bn254, K>::verify(&pp, &evk.vk, &y, &pi_short, LABEL));
+
+        // The proof is bound to its label
+        let other = b"another label";
+        assert!(!MddhSubspaceSnark::<Bn254, K>::verify(&pp, &evk.vk, &y, &pi, other));
+        let pi_other = MddhSubspaceSnark::<Bn254, K>::prove(&pp, &evk.ek, &x, other);
+        assert!(MddhSubspaceSnark::<Bn254, K>::verify(&pp, &evk.vk, &y, &pi_other, other));
+        assert!(!MddhSubspaceSnark::<Bn254, K>::verify(&pp, &evk.vk, &y, &pi_other, LABEL));
+    }
+
+    #[test]
+    fn test_mddh_k1() {
+        check_mddh::<1>();
+
+        // PESubspaceSnark is the same scheme for k = 1
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (pp, m, x, y) = random_instance(&mut rng, 3, 5);
+        let evk = PESubspaceSnark::<Bn254>::keygen(&mut rng, &pp, &m);
+        let pi = PESubspaceSnark::<Bn254>::prove(&pp, &evk.ek, &x, LABEL);
+        assert!(PESubspaceSnark::<Bn254>::verify(&pp, &evk.vk, &y, &pi, LABEL));
+        let mut y_out = y.clone();
+        y_out[1] = (y_out[1] + pp.g1).into_affine();
+        assert!(!PESubspaceSnark::<Bn254>::verify(&pp, &evk.vk, &y_out, &pi, LABEL));
+        assert!(!PESubspaceSnark::<Bn254>::verify(&pp, &evk.vk, &y, &pi, b""));
+    }
+
+    #[test]
+    fn test_mddh_k2() {
+        check_mddh::<2>();
+
+        // A proof is k + 1 compressed G1 elements behind a length prefix
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (pp, m, x, _) = random_instance(&mut rng, 2, 4);
+        let evk_1 = MddhSubspaceSnark::<Bn254, 1>::keygen(&mut rng, &pp, &m);
+        let evk_2 = MddhSubspaceSnark::<Bn254, 2>::keygen(&mut rng, &pp, &m);
+        let pi_1 = MddhSubspaceSnark::<Bn254, 1>::prove(&pp, &evk_1.ek, &x, LABEL);
+        let pi_2 = MddhSubspaceSnark::<Bn254, 2>::prove(&pp, &evk_2.ek, &x, LABEL);
+        assert_eq!(
+            pi_2.compressed_size() - pi_1.compressed_size(),
+            G1Affine::default().compressed_size()
+        );
+    }
+
+    /// Proof size and verify time for k = 1 (SXDH) and k = 2 (DLIN), run with `--ignored`
+    #[test]
+    #[ignore]
+    fn test_mddh_compare() {
+        fn measure<const K: usize>() -> (usize, std::time::Duration) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (pp, m, x, y) = random_instance(&mut rng, 2, 4);
+            let evk = MddhSubspaceSnark::<Bn254, K>::keygen(&mut rng, &pp, &m);
+            let pi = MddhSubspaceSnark::<Bn254, K>::prove(&pp, &evk.ek, &x, LABEL);
+
+            let n_iters = 10;
+            let start = std::time::Instant::now();
+            for _ in 0..n_iters {
+                assert!(MddhSubspaceSnark::<Bn254, K>::verify(&pp, &evk.vk, &y, &pi, LABEL));
+            }
+            (pi.compressed_size(), start.elapsed() / n_iters)
+        }
+
+        let (size_1, time_1) = measure::<1>();
+        let (size_2, time_2) = measure::<2>();
+        println!("k = 1: proof {} bytes, verify {:?}", size_1, time_1);
+        println!("k = 2: proof {} bytes, verify {:?}", size_2, time_2);
+    }
+
+    /// Keygen and prove times for a link matrix with hundreds of columns, run with `--ignored`
+    #[test]
+    #[ignore]
+    fn test_link_many_columns() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (pp, m, x, y) = random_instance(&mut rng, 2, 512);
+
+        let start = std::time::Instant::now();
+        let evk = PESubspaceSnark::<Bn254>::keygen(&mut rng, &pp, &m);
+        let keygen_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let pi = PESubspaceSnark::<Bn254>::prove(&pp, &evk.ek, &x, LABEL);
+        let prove_time = start.elapsed();
+        println!("nc = {}: keygen {:?}, prove {:?}", pp.nc, keygen_time, prove_time);
+
+        assert!(PESubspaceSnark::<Bn254>::verify(&pp, &evk.vk, &y, &pi, LABEL));
+    }
+
+    #[test]
+    fn test_sparse_matrix() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let g = (0..4).map(|_| G1Projective::rand(&mut rng).into_affine()).collect::<Vec<_>>();
+        let zero = G1Affine::default();
+
+        let mut builder = SparseMatrix::builder(2, 3);
+        builder.insert_row_slice(0, 1, &[g[0], g[1]]).unwrap();
+        builder.insert_val(1, 0, &g[2]).unwrap();
+        assert_eq!(builder.insert_val(0, 2, &g[3]), Err(SparseMatrixError::Duplicate(0, 2)));
+        assert_eq!(
+            builder.insert_val(2, 0, &g[3]),
+            Err(SparseMatrixError::OutOfRange((2, 0), (2, 3)))
+        );
+        assert_eq!(
+            builder.insert_row_slice(1, 2, &[g[3], g[3]]),
+            Err(SparseMatrixError::OutOfRange((1, 3), (2, 3)))
+        );
+        let m = builder.build();
+
+        // The failed row slice kept the entries before the error
+        let entries = m.iter_row_major().map(|(r, c, v)| (r, c, *v)).collect::<Vec<_>>();
+        assert_eq!(entries, vec![(0, 1, g[0]), (0, 2, g[1]), (1, 0, g[2]), (1, 2, g[3])]);
+
+        let dense = m.to_dense();
+        assert_eq!(dense, vec![vec![zero, g[0], g[1]], vec![g[2], zero, g[3]]]);
+        assert_eq!(SparseMatrix::from_dense(&dense).unwrap(), m);
+        assert_eq!(
+            SparseMatrix::from_dense(&[vec![g[0]], vec![]]),
+            Err(SparseMatrixError::RaggedRow(1, 0, 1))
+        );
+
+        // Equality ignores the insertion order and explicit identity entries
+        let mut other = SparseMatrix::new(2, 3);
+        other.insert_val(1, 2, &g[3]);
+        other.insert_val(1, 0, &g[2]);
+        other.insert_val(1, 1, &zero);
+        other.insert_row_slice(0, 1, vec![g[0], g[1]]);
+        assert_eq!(other, m);
+        assert_eq!(other.digest(), m.digest());
+        other.insert_val(0, 0, &g[3]);
+        assert_ne!(other, m);
+        assert_ne!(other.digest(), m.digest());
+
+        let mut bytes = Vec::new();
+        m.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(SparseMatrix::<G1Affine>::deserialize_compressed(&bytes[..]).unwrap(), m);
+        assert_eq!(m.digest(), SparseMatrix::from_dense(&dense).unwrap().digest());
+
+        // Entries out of range or set twice do not deserialize
+        let mut bad = SparseMatrix::new(2, 3);
+        bad.insert_val(0, 0, &g[0]);
+        bad.insert_val(0, 0, &g[1]);
+        let mut bytes = Vec::new();
+        bad.serialize_compressed(&mut bytes).unwrap();
+        assert!(SparseMatrix::<G1Affine>::deserialize_compressed(&bytes[..]).is_err());
 
-        assert!(PESubspaceSnark::<Bn254>::verify(&pp, &evk.vk, &y, &pi) == true);
-        assert!(PESubspaceSnark::<Bn254>::verify(&pp, &evk.vk, &y, &pi_bad) == false);
+        let g1 = G1Projective::generator().into_affine();
+        let g2 = G2Projective::generator().into_affine();
+        let pp = PP::<G1Affine, G2Affine> { nr: 2, nc: 3, g1, g2 };
+        let evk = PESubspaceSnark::<Bn254>::keygen(&mut rng, &pp, &m);
+        assert_eq!(evk.matrix_digest, Some(m.digest()));
+    }
+
+    /// Runs a scheme through the `SubspaceSnark` trait on `y = M x`, where `y_out` is not in
+    /// the span of `M`.
+    fn check_subspace_snark<S: SubspaceSnark<InVec = Fr>>(
+        pp: &S::PP,
+        ek: &S::EK,
+        vk: &S::VK,
+        x: &[Fr],
+        y: &[S::OutVec],
+        y_out: &[S::OutVec],
+    ) {
+        let pi = S::prove(pp, ek, x, LABEL);
+        assert!(S::verify(pp, vk, y, &pi, LABEL));
+        assert!(!S::verify(pp, vk, y, &pi, b"another label"));
+        assert!(!S::verify(pp, vk, y_out, &pi, LABEL));
+
+        let mut x_bad = x.to_vec();
+        x_bad[0] += Fr::one();
+        assert!(!S::verify(pp, vk, y, &S::prove(pp, ek, &x_bad, LABEL), LABEL));
+    }
+
+    #[test]
+    fn test_g2_statement() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let g1 = G1Projective::generator().into_affine();
+        let g2 = G2Projective::generator().into_affine();
+        let (nr, nc) = (2, 4);
+        let pp = PP::<G1Affine, G2Affine> { nr, nc, g1, g2 };
+
+        let rows = (0..nr)
+            .map(|_| (0..nc).map(|_| G2Projective::rand(&mut rng).into_affine()).collect())
+            .collect::<Vec<Vec<_>>>();
+        let m = SparseMatrix::from_dense(&rows).unwrap();
+        let x = (0..nc).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+        let y = rows
+            .iter()
+            .map(|row| row.iter().zip(&x).map(|(m_ij, x_j)| *m_ij * x_j).sum::<G2Projective>())
+            .collect::<Vec<_>>();
+        let y = G2Projective::normalize_batch(&y);
+        let mut y_out = y.clone();
+        y_out[1] = (y_out[1] + g2).into_affine();
+
+        let evk = G2SubspaceSnark::<Bn254>::keygen(&mut rng, &pp, &m);
+        assert_eq!(evk.matrix_digest, Some(m.digest()));
+        assert_eq!(evk.ek.p_0[0].len(), nc);
+        check_subspace_snark::<G2SubspaceSnark<Bn254>>(&pp, &evk.ek, &evk.vk, &x, &y, &y_out);
+
+        // The same checks for the G_1 scheme
+        let (pp, m, x, y) = random_instance(&mut rng, nr, nc);
+        let mut y_out = y.clone();
+        y_out[1] = (y_out[1] + pp.g1).into_affine();
+        let evk = PESubspaceSnark::<Bn254>::keygen(&mut rng, &pp, &m);
+        check_subspace_snark::<PESubspaceSnark<Bn254>>(&pp, &evk.ek, &evk.vk, &x, &y, &y_out);
     }
 }
@@ -1,25 +1,27 @@
+use crate::cc::helpers::hash_to_field;
+use crate::json::{self, JsonAffine};
 use crate::link::matrix::*;
 use ark_ec::pairing::Pairing;
 use ark_ec::AffineRepr;
 use ark_ec::CurveGroup;
 use ark_ff::UniformRand;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{cfg_iter, rand::Rng, vec::Vec};
-use std::ops::Mul;
+use ark_std::{rand::Rng, vec::Vec};
+use ark_std::ops::Mul;
 use ark_std::Zero;
-use std::ops::Neg;
+use serde::{Deserialize, Serialize};
 
-#[cfg(feature = "parallel")]
-use rayon::prelude::*;
-
-#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "G1: JsonAffine, G2: JsonAffine")]
 pub struct PP<
     G1: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
     G2: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
 > {
     pub nr: usize, // # of rows
     pub nc: usize, // # of cols
+    #[serde(with = "json::affine")]
     pub g1: G1,
+    #[serde(with = "json::affine")]
     pub g2: G2,
 }
 
@@ -38,36 +40,73 @@ impl<
     }
 }
 
-#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize)]
-pub struct EK<
-    G1: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
-    ScalarField: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
-> {
-    pub tau: ScalarField,  // tau \in Z_p
+/// The evaluation key. The tag `tau` of a proof is derived from its label, see `label_to_tau`.
+#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "G1: JsonAffine")]
+pub struct EK<G1: Clone + Default + CanonicalSerialize + CanonicalDeserialize> {
+    #[serde(with = "json::affine_columns")]
     pub p_0: Vec<Vec<G1>>, // [C_0]_1 \in G_1^(nr \times (k+1)), k = 1
+    #[serde(with = "json::affine_columns")]
     pub p_1: Vec<Vec<G1>>, // [C_1]_1 \in G_1^(nr \times (k+1)), k = 1
 }
 
-#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize)]
-pub struct VK<
-    G2: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
-    ScalarField: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
-> {
-    pub tau: ScalarField, // tau \in Z_p
+#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "G2: JsonAffine")]
+pub struct VK<G2: Clone + Default + CanonicalSerialize + CanonicalDeserialize> {
+    #[serde(with = "json::affines")]
     pub c_0: Vec<G2>,     // [C_0]_1 \in G_2^(nr \times (k)), k = 1
+    #[serde(with = "json::affines")]
     pub c_1: Vec<G2>,     // [C_1]_1 \in G_2^(nr \times (k)), k = 1
+    #[serde(with = "json::affines")]
     pub a: Vec<G2>,       // [A]_1 \in G_2^(k+1 \times k), k = 1
 }
 
-#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "G1: JsonAffine, G2: JsonAffine")]
 pub struct LinkEVKey<
     G1: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
     G2: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
-    ScalarField: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
 > {
     pub pp: PP<G1, G2>,
-    pub ek: EK<G1, ScalarField>,
-    pub vk: VK<G2, ScalarField>,
+    pub ek: EK<G1>,
+    pub vk: VK<G2>,
+    /// `SparseMatrix::digest` of the matrix the keys were generated for, if recorded
+    pub matrix_digest: Option<[u8; 32]>,
+}
+
+/// The verification key for the MDDH parameter `k`, stored by columns like `EK`:
+/// `c_b[j]` is column `j` of `C_b`, and `a[j]` is column `j` of `A`.
+#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MddhVK<G2: Clone + Default + CanonicalSerialize + CanonicalDeserialize> {
+    pub c_0: Vec<Vec<G2>>, // [C_0]_2 \in G_2^(nr \times k)
+    pub c_1: Vec<Vec<G2>>, // [C_1]_2 \in G_2^(nr \times k)
+    pub a: Vec<Vec<G2>>,   // [A]_2 \in G_2^((k+1) \times k)
+}
+
+#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MddhEVKey<
+    G1: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
+    G2: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
+> {
+    pub pp: PP<G1, G2>,
+    pub ek: EK<G1>,
+    pub vk: MddhVK<G2>,
+    /// `SparseMatrix::digest` of the matrix the keys were generated for, if recorded
+    pub matrix_digest: Option<[u8; 32]>,
+}
+
+/// The keys of `G2SubspaceSnark`: the statement, `EK` and the proofs are in `G_2`, and `VK`
+/// in `G_1`. `pp` keeps its generators in their own groups.
+#[derive(Clone, Default, PartialEq, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct G2LinkEVKey<
+    G1: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
+    G2: Clone + Default + CanonicalSerialize + CanonicalDeserialize,
+> {
+    pub pp: PP<G1, G2>,
+    pub ek: EK<G2>,
+    pub vk: VK<G1>,
+    /// `SparseMatrix::digest` of the matrix the keys were generated for, if recorded
+    pub matrix_digest: Option<[u8; 32]>,
 }
 
 pub trait SubspaceSnark {
@@ -84,21 +123,158 @@ pub trait SubspaceSnark {
     type Proof;
 
     fn keygen<R: Rng>(rng: &mut R, pp: &Self::PP, m: &Self::KMtx) -> Self::EVK;
-    fn prove(pp: &Self::PP, ek: &Self::EK, x: &[Self::InVec]) -> Self::Proof;
-    fn verify(pp: &Self::PP, vk: &Self::VK, y: &[Self::OutVec], pi: &Self::Proof) -> bool;
+    /// Proves `y = M x` under `label`; the proof only verifies under the same label.
+    fn prove(pp: &Self::PP, ek: &Self::EK, x: &[Self::InVec], label: &[u8]) -> Self::Proof;
+    fn verify(
+        pp: &Self::PP,
+        vk: &Self::VK,
+        y: &[Self::OutVec],
+        pi: &Self::Proof,
+        label: &[u8],
+    ) -> bool;
+}
+
+/// The tag `tau` of the one-time simulation-extractable scheme ([1], Fig. 6), derived from
+/// the label so that a proof simulated or observed under one label is useless under another.
+pub fn label_to_tau<PE: Pairing>(label: &[u8]) -> PE::ScalarField {
+    let mut bytes = b"link-tau".to_vec();
+    bytes.extend_from_slice(label);
+    hash_to_field::<PE>(bytes)
+}
+
+/// `[v]` in the group of the key generator `g`.
+fn vec_to_group<V: CurveGroup>(g: V::Affine, v: &[V::ScalarField]) -> Vec<V::Affine> {
+    let v = v.iter().map(|x| g * x).collect::<Vec<V>>();
+    V::normalize_batch(&v)
+}
+
+/// Key generation for the MDDH parameter `k`, with the matrix `M` and `P_b` in the statement
+/// group `S` and `C_b`, `A` in the key group `V` generated by `g_v`.
+#[allow(clippy::type_complexity)]
+fn keygen_mddh<PE: Pairing, S, V, R: Rng>(
+    rng: &mut R,
+    g_v: V::Affine,
+    nr: usize,
+    nc: usize,
+    m: &SparseMatrix<S::Affine>,
+    k: usize,
+) -> (EK<S::Affine>, MddhVK<V::Affine>)
+where
+    S: CurveGroup<ScalarField = PE::ScalarField>,
+    V: CurveGroup<ScalarField = PE::ScalarField>,
+{
+    let mut rand_vec = |n: usize| (0..n).map(|_| PE::ScalarField::rand(rng)).collect::<Vec<_>>();
+
+    // K_0, K_1 \in Z_p^(nr \times k+1), by columns
+    let k_0: Vec<Vec<PE::ScalarField>> = (0..=k).map(|_| rand_vec(nr)).collect();
+    let k_1: Vec<Vec<PE::ScalarField>> = (0..=k).map(|_| rand_vec(nr)).collect();
+
+    // A \in Z_p^(k+1 \times k), by columns
+    let a: Vec<Vec<PE::ScalarField>> = (0..k).map(|_| rand_vec(k + 1)).collect();
+
+    // M \in Z_p^(nr \times nc)
+    // P_0 = M^T K_0 \in Z_p^(nc \time k+1)
+    // P_1 = M^T K_1 \in Z_p^(nc \time k+1)
+    let p_mult = |k_b: &Vec<Vec<PE::ScalarField>>| {
+        k_b.iter()
+            .map(|col| SparseLinAlgebra::<PE>::sparse_vector_matrix_mult_in::<S>(col, m, nc))
+            .collect::<Vec<_>>()
+    };
+    let p_0 = p_mult(&k_0);
+    let p_1 = p_mult(&k_1);
+
+    // C_0 = K_0 \times A \in V^(nr \times k)
+    // C_1 = K_1 \times A \in V^(nr \times k)
+    let c_mult = |k_b: &Vec<Vec<PE::ScalarField>>| {
+        a.iter()
+            .map(|a_col| {
+                let c_col = (0..nr)
+                    .map(|i| (0..=k).map(|l| k_b[l][i] * a_col[l]).sum())
+                    .collect::<Vec<PE::ScalarField>>();
+                vec_to_group::<V>(g_v, &c_col)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let ek = EK::<S::Affine> { p_0, p_1 };
+    let vk = MddhVK::<V::Affine> {
+        c_0: c_mult(&k_0),
+        c_1: c_mult(&k_1),
+        a: a.iter().map(|a_col| vec_to_group::<V>(g_v, a_col)).collect(),
+    };
+    (ek, vk)
 }
 
-fn vec_to_g2<PE: Pairing>(
-    pp: &PP<PE::G1Affine, PE::G2Affine>,
-    v: &Vec<PE::ScalarField>,
-) -> Vec<PE::G2Affine> {
-    v.iter()
-        .map(|x| pp.g2.mul(*x).into_affine())
-        .collect::<Vec<_>>()
+/// pi = x^T P_0 + tau * x^T P_1 \in S^(k+1), with two MSMs per column.
+/// `tau` is derived from the label of each proof, so `P_0 + tau * P_1` cannot be combined
+/// in `EK` ahead of time as it was while `tau` was fixed at keygen.
+fn prove_mddh<S: CurveGroup>(
+    nc: usize,
+    ek: &EK<S::Affine>,
+    x: &[S::ScalarField],
+    tau: S::ScalarField,
+) -> Vec<S::Affine> {
+    assert_eq!(nc, x.len());
+
+    let pi = ek
+        .p_0
+        .iter()
+        .zip(&ek.p_1)
+        .map(|(p_0_col, p_1_col)| {
+            S::msm(p_0_col, x).unwrap() + S::msm(p_1_col, x).unwrap() * tau
+        })
+        .collect::<Vec<_>>();
+    S::normalize_batch(&pi)
 }
 
+/// Checks `y^T C = pi^T A` column by column, with `C = C_0 + tau * C_1`.
+/// `is_one(left, right)` checks that the product of the pairings of `left[i]` and `right[i]`
+/// is one, in whichever order the groups `S` and `V` are paired.
+#[allow(clippy::too_many_arguments)]
+fn verify_mddh<S: CurveGroup, V: CurveGroup<ScalarField = S::ScalarField>>(
+    nr: usize,
+    tau: S::ScalarField,
+    c_0: &[Vec<V::Affine>],
+    c_1: &[Vec<V::Affine>],
+    a: &[Vec<V::Affine>],
+    y: &[S::Affine],
+    pi: &[S::Affine],
+    is_one: impl Fn(&[S::Affine], &[V::Affine]) -> bool,
+) -> bool {
+    assert_eq!(nr, y.len());
+    if pi.len() != a.len() + 1 {
+        return false;
+    }
+
+    let mut left = y.to_vec();
+    left.extend_from_slice(pi);
+
+    c_0.iter().zip(c_1).zip(a).all(|((c_0_col, c_1_col), a_col)| {
+        let mut right = (0..nr).map(|i| c_0_col[i] + c_1_col[i].mul(tau)).collect::<Vec<V>>();
+        right.extend(a_col.iter().map(|a_l| a_l.into_group().neg()));
+
+        is_one(&left, &V::normalize_batch(&right))
+    })
+}
+
+/// `verify_mddh` for statements in `G_1`, with the keys in `G_2`.
+fn verify_mddh_g1<PE: Pairing>(
+    nr: usize,
+    tau: PE::ScalarField,
+    c_0: &[Vec<PE::G2Affine>],
+    c_1: &[Vec<PE::G2Affine>],
+    a: &[Vec<PE::G2Affine>],
+    y: &[PE::G1Affine],
+    pi: &[PE::G1Affine],
+) -> bool {
+    verify_mddh::<PE::G1, PE::G2>(nr, tau, c_0, c_1, a, y, pi, |left, right| {
+        PE::multi_pairing(left, right).is_zero()
+    })
+}
+
+/// The SXDH (`k = 1`) instance, with the single columns of `C_b` and `A` stored flat in `VK`.
 pub struct PESubspaceSnark<PE: Pairing> {
-    pairing_engine_type: std::marker::PhantomData<PE>,
+    pairing_engine_type: ark_std::marker::PhantomData<PE>,
 }
 
 // NB: Now the system is for y = Mx
@@ -109,138 +285,157 @@ impl<PE: Pairing> SubspaceSnark for PESubspaceSnark<PE> {
 
     type PP = PP<PE::G1Affine, PE::G2Affine>;
 
-    type EK = EK<PE::G1Affine, PE::ScalarField>;
-    type VK = VK<PE::G2Affine, PE::ScalarField>;
-    type EVK = LinkEVKey<PE::G1Affine, PE::G2Affine, PE::ScalarField>;
+    type EK = EK<PE::G1Affine>;
+    type VK = VK<PE::G2Affine>;
+    type EVK = LinkEVKey<PE::G1Affine, PE::G2Affine>;
 
     type Proof = Vec<PE::G1Affine>;
 
     fn keygen<R: Rng>(rng: &mut R, pp: &Self::PP, m: &Self::KMtx) -> Self::EVK {
-        // K_0, K_1 \in Z_p^(nr \times k+1), k = 1
-        let mut k_0_c0: Vec<PE::ScalarField> = Vec::with_capacity(pp.nr);
-        let mut k_0_c1: Vec<PE::ScalarField> = Vec::with_capacity(pp.nr);
-        let mut k_1_c0: Vec<PE::ScalarField> = Vec::with_capacity(pp.nr);
-        let mut k_1_c1: Vec<PE::ScalarField> = Vec::with_capacity(pp.nr);
-        for _ in 0..pp.nr {
-            k_0_c0.push(PE::ScalarField::rand(rng));
-            k_0_c1.push(PE::ScalarField::rand(rng));
-            k_1_c0.push(PE::ScalarField::rand(rng));
-            k_1_c1.push(PE::ScalarField::rand(rng));
-        }
+        let (ek, mut vk) = keygen_mddh::<PE, PE::G1, PE::G2, R>(rng, pp.g2, pp.nr, pp.nc, m, 1);
+        let vk = VK::<PE::G2Affine> {
+            c_0: vk.c_0.remove(0),
+            c_1: vk.c_1.remove(0),
+            a: vk.a.remove(0),
+        };
 
-        // println!("m: {:?}", m);
-
-        // [A]_1 \in G_2^(k+1 \times k), k = 1
-        let mut a: Vec<PE::ScalarField> = Vec::with_capacity(2);
-        a.push(PE::ScalarField::rand(rng));
-        a.push(PE::ScalarField::rand(rng));
-
-        // M \in Z_p^(nr \times nc)
-        // P_0 = M^T K_0 \in Z_p^(nc \time k+1)
-        // P_1 = M^T K_1 \in Z_p^(nc \time k+1)
-        let p_0_c0 = SparseLinAlgebra::<PE>::sparse_vector_matrix_mult(&k_0_c0, &m, pp.nc);
-        let p_0_c1 = SparseLinAlgebra::<PE>::sparse_vector_matrix_mult(&k_0_c1, &m, pp.nc);
-        let p_0: Vec<Vec<PE::G1Affine>> = vec![p_0_c0.clone(), p_0_c1.clone()];
-
-        let p_1_c0 = SparseLinAlgebra::<PE>::sparse_vector_matrix_mult(&k_1_c0, &m, pp.nc);
-        let p_1_c1 = SparseLinAlgebra::<PE>::sparse_vector_matrix_mult(&k_1_c1, &m, pp.nc);
-        let p_1: Vec<Vec<PE::G1Affine>> = vec![p_1_c0.clone(), p_1_c1.clone()];
-
-        // for i in 0..pp.nc {
-        //     assert!(p_0_c0[i] == pp.g1.mul(k_0_c0[0]).into_affine());
-        //     assert!(p_0_c1[i] == pp.g1.mul(k_0_c1[0]).into_affine());
-        //     assert!(p_1_c0[i] == pp.g1.mul(k_1_c0[0]).into_affine());
-        //     assert!(p_1_c1[i] == pp.g1.mul(k_1_c1[0]).into_affine());
-        // }
-        
-        // C_0 = K_0 \times A \in G_1^(nr \times k)
-        // C_1 = K_1 \times A \in G_1^(nr \times k)
-        let mut c_0: Vec<PE::ScalarField> = Vec::with_capacity(pp.nr);
-        let mut c_1: Vec<PE::ScalarField> = Vec::with_capacity(pp.nr);
-        for i in 0..pp.nr {
-            c_0.push(k_0_c0[i].mul(a[0]) + k_0_c1[i].mul(a[1]));
-            c_1.push(k_1_c0[i].mul(a[0]) + k_1_c1[i].mul(a[1]));
+        LinkEVKey::<PE::G1Affine, PE::G2Affine> {
+            pp: pp.clone(),
+            ek,
+            vk,
+            matrix_digest: Some(m.digest()),
         }
+    }
 
-        // tau \in Z_p
-        let tau: <PE as Pairing>::ScalarField = PE::ScalarField::rand(rng);
+    fn prove(pp: &Self::PP, ek: &Self::EK, x: &[Self::InVec], label: &[u8]) -> Self::Proof {
+        prove_mddh::<PE::G1>(pp.nc, ek, x, label_to_tau::<PE>(label))
+    }
 
-        let ek = EK::<PE::G1Affine, PE::ScalarField> { tau, p_0, p_1 };
-        
-        let vk = VK::<PE::G2Affine, PE::ScalarField> {
-            tau,
-            c_0: vec_to_g2::<PE>(pp, &c_0),
-            c_1: vec_to_g2::<PE>(pp, &c_1),
-            a: vec_to_g2::<PE>(pp, &a),
-        };
+    fn verify(
+        pp: &Self::PP,
+        vk: &Self::VK,
+        y: &[Self::OutVec],
+        pi: &Self::Proof,
+        label: &[u8],
+    ) -> bool {
+        verify_mddh_g1::<PE>(
+            pp.nr,
+            label_to_tau::<PE>(label),
+            ark_std::slice::from_ref(&vk.c_0),
+            ark_std::slice::from_ref(&vk.c_1),
+            ark_std::slice::from_ref(&vk.a),
+            y,
+            pi,
+        )
+    }
+}
+
+/// The scheme under the `K`-MDDH assumption: `K = 1` is SXDH and `K = 2` is DLIN.
+/// Proofs have `K + 1` elements of `G_1`, and verification checks `K` pairing products
+/// of `nr + K + 1` pairings each.
+pub struct MddhSubspaceSnark<PE: Pairing, const K: usize> {
+    pairing_engine_type: ark_std::marker::PhantomData<PE>,
+}
+
+impl<PE: Pairing, const K: usize> SubspaceSnark for MddhSubspaceSnark<PE, K> {
+    type KMtx = SparseMatrix<PE::G1Affine>;
+    type InVec = PE::ScalarField;
+    type OutVec = PE::G1Affine;
+
+    type PP = PP<PE::G1Affine, PE::G2Affine>;
+
+    type EK = EK<PE::G1Affine>;
+    type VK = MddhVK<PE::G2Affine>;
+    type EVK = MddhEVKey<PE::G1Affine, PE::G2Affine>;
 
-        let evk = LinkEVKey::<PE::G1Affine, PE::G2Affine, PE::ScalarField> {
+    type Proof = Vec<PE::G1Affine>;
+
+    fn keygen<R: Rng>(rng: &mut R, pp: &Self::PP, m: &Self::KMtx) -> Self::EVK {
+        assert!(K >= 1, "MDDH needs k >= 1");
+        let (ek, vk) = keygen_mddh::<PE, PE::G1, PE::G2, R>(rng, pp.g2, pp.nr, pp.nc, m, K);
+        MddhEVKey::<PE::G1Affine, PE::G2Affine> {
             pp: pp.clone(),
             ek,
             vk,
-        };
-        evk
+            matrix_digest: Some(m.digest()),
+        }
     }
 
-    fn prove(pp: &Self::PP, ek: &Self::EK, x: &[Self::InVec]) -> Self::Proof {
-        assert_eq!(pp.nc, x.len());
-
-        // p_0 \in G_1^(nc \times (k+1)), k = 1
-        // p_1 \in G_1^(nc \times (k+1)), k = 1
-        // p = p_0 + \tau * p_1 \in G_1^(nc \times (k+1)), k = 1
-        let mut p_c0: Vec<PE::G1Affine> = Vec::with_capacity(pp.nc);
-        let mut p_c1: Vec<PE::G1Affine> = Vec::with_capacity(pp.nc);
-        for i in 0..pp.nc {
-            let i0 = ek.p_0[0][i] + ek.p_1[0][i].mul(ek.tau);
-            let i1 = ek.p_0[1][i] + ek.p_1[1][i].mul(ek.tau);
-            p_c0.push(i0.into_affine());
-            p_c1.push(i1.into_affine());
-        };
-        // let p: Vec<Vec<PE::G1Affine>> = vec![p_c0, p_c1];
-
-        // pi = x ^T * (p_0 + \tau * p_1) \in G_1^(k+1), k = 1
-        let mut pi_0 = PE::G1::zero();
-        let mut pi_1 = PE::G1::zero();
-        for j in 0..pp.nc {
-            pi_0 = pi_0 + &p_c0[j].mul(x[j]);
-            pi_1 = pi_1 + &p_c1[j].mul(x[j]);
-        }
-        let pi = vec![pi_0.into_affine(), pi_1.into_affine()];
+    fn prove(pp: &Self::PP, ek: &Self::EK, x: &[Self::InVec], label: &[u8]) -> Self::Proof {
+        prove_mddh::<PE::G1>(pp.nc, ek, x, label_to_tau::<PE>(label))
+    }
 
-        pi
+    fn verify(
+        pp: &Self::PP,
+        vk: &Self::VK,
+        y: &[Self::OutVec],
+        pi: &Self::Proof,
+        label: &[u8],
+    ) -> bool {
+        let tau = label_to_tau::<PE>(label);
+        verify_mddh_g1::<PE>(pp.nr, tau, &vk.c_0, &vk.c_1, &vk.a, y, pi)
     }
+}
 
-    fn verify(pp: &Self::PP, vk: &Self::VK, y: &[Self::OutVec], pi: &Self::Proof) -> bool {
-        assert_eq!(pp.nr, y.len());
+/// The DLIN (`k = 2`) instance.
+pub type DlinSubspaceSnark<PE> = MddhSubspaceSnark<PE, 2>;
 
-        let mut left = y.to_vec();
-        left.push(pi[0]);
-        left.push(pi[1]);
+/// The SXDH scheme for statements in `G_2`: proves that `y \in G_2^nr` is in the span of a
+/// matrix over `G_2`, e.g. to link the `G_2` part of the SMA commitment. The verifier pairs
+/// `VK \in G_1` with `y` and the proof.
+pub struct G2SubspaceSnark<PE: Pairing> {
+    pairing_engine_type: ark_std::marker::PhantomData<PE>,
+}
+
+impl<PE: Pairing> SubspaceSnark for G2SubspaceSnark<PE> {
+    type KMtx = SparseMatrix<PE::G2Affine>;
+    type InVec = PE::ScalarField;
+    type OutVec = PE::G2Affine;
 
-        // C_0 \in G_2^(nr \times k), k = 1
-        // C_1 \in G_2^(nr \times k), k = 1
-        // C = C_0 + \tau * C_1 \in G_2^(nr \times k), k = 1
-        let mut c: Vec<PE::G2Affine> = Vec::with_capacity(pp.nr);
-        for i in 0..pp.nr {
-            c.push(PE::G2Affine::from(vk.c_0[i] + vk.c_1[i].mul(vk.tau)));
+    type PP = PP<PE::G1Affine, PE::G2Affine>;
+
+    type EK = EK<PE::G2Affine>;
+    type VK = VK<PE::G1Affine>;
+    type EVK = G2LinkEVKey<PE::G1Affine, PE::G2Affine>;
+
+    type Proof = Vec<PE::G2Affine>;
+
+    fn keygen<R: Rng>(rng: &mut R, pp: &Self::PP, m: &Self::KMtx) -> Self::EVK {
+        let (ek, mut vk) = keygen_mddh::<PE, PE::G2, PE::G1, R>(rng, pp.g1, pp.nr, pp.nc, m, 1);
+        let vk = VK::<PE::G1Affine> {
+            c_0: vk.c_0.remove(0),
+            c_1: vk.c_1.remove(0),
+            a: vk.a.remove(0),
+        };
+
+        G2LinkEVKey::<PE::G1Affine, PE::G2Affine> {
+            pp: pp.clone(),
+            ek,
+            vk,
+            matrix_digest: Some(m.digest()),
         }
-        let mut right = c.to_vec();
-        right.push(PE::G2Affine::from(vk.a[0].into_group().neg()));
-        right.push(PE::G2Affine::from(vk.a[1].into_group().neg()));
-
-        
-        let result = PE::multi_pairing(
-            cfg_iter!(left)
-                .map(|x| x.clone())
-                .collect::<Vec<_>>()
-                .as_slice(),
-            cfg_iter!(right)
-                .map(|x| x.clone())
-                .collect::<Vec<_>>()
-                .as_slice(),
-        ).is_zero();
-
-        result
     }
-}
 
+    fn prove(pp: &Self::PP, ek: &Self::EK, x: &[Self::InVec], label: &[u8]) -> Self::Proof {
+        prove_mddh::<PE::G2>(pp.nc, ek, x, label_to_tau::<PE>(label))
+    }
+
+    fn verify(
+        pp: &Self::PP,
+        vk: &Self::VK,
+        y: &[Self::OutVec],
+        pi: &Self::Proof,
+        label: &[u8],
+    ) -> bool {
+        verify_mddh::<PE::G2, PE::G1>(
+            pp.nr,
+            label_to_tau::<PE>(label),
+            ark_std::slice::from_ref(&vk.c_0),
+            ark_std::slice::from_ref(&vk.c_1),
+            ark_std::slice::from_ref(&vk.a),
+            y,
+            pi,
+            |left, right| PE::multi_pairing(right, left).is_zero(),
+        )
+    }
+}
@@ -3,10 +3,14 @@ use crate::lrs::utils::*;
 use ark_ff::Field;
 use ark_relations::r1cs::Variable;
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
+use std::path::Path;
 use std::str::FromStr;
 
+/// How many failing constraints `sign` reports when the witness is wrong.
+pub const MAX_REPORTED_CONSTRAINTS: usize = 8;
+
 #[allow(non_snake_case)]
 #[derive(Debug, Clone)]
 pub struct LRSCirc<F>
@@ -16,9 +20,14 @@ where
 {
     pub constraints: Constraints<F>,
     pub sym_data: HashMap<usize, String>,
-    pub witness_data: HashMap<usize, F>,
+    /// The wire of every signal in `sym_data`, by name
+    pub signal_wires: HashMap<String, usize>,
+    /// The value of every wire, `None` if the witness has none
+    pub witness_data: Vec<Option<F>>,
     pub instance: Vec<F>,
     pub commit_witness: Vec<F>,
+    /// The values of `circ_desc.hashed_pub_io` if the instance is hashed, else `instance`
+    pub public_inputs: Vec<F>,
     pub circ_desc: CircDescriptor,
 }
 
@@ -28,141 +37,220 @@ where
 {
     // -> Result<LRSCirc<F>, SynthesisError>
     pub fn construct(circ_desc: &CircDescriptor) -> Result<LRSCirc<F>, SynthesisError> {
-        // public inputs | committed witness | uncommitted witness
-        let ioputs_name = circ_desc.ioputs_name
-            .iter()
-            .map(|name| name.to_string())
-            .collect::<Vec<String>>();
+        let witness_map: HashMap<usize, F> =
+            read_witness_file((circ_desc.path_prefix.clone() + &circ_desc.circuit_name + "_js/witness.wtns.json").as_str())
+                .unwrap_or_else(|_| panic!("Failed to read {}_js/witness.wtns.json", &circ_desc.circuit_name));
+        let mut witness_data = vec![None; witness_map.keys().max().map_or(0, |idx| idx + 1)];
+        for (idx, value) in witness_map {
+            witness_data[idx] = Some(value);
+        }
+        Self::construct_with_wires(circ_desc, witness_data)
+    }
 
-        let constraints = Constraints::<F>::from(
-            Constraints::read_from_file(
-                (circ_desc.path_prefix.clone() + &circ_desc.circuit_name + "_constraints.json").as_str(),
-            )
-            .expect(&format!("Failed to read {}_constraints.json", &circ_desc.circuit_name)),
-        );
+    /// `construct` with the value of every wire given instead of read from `witness.wtns.json`,
+    /// e.g. computed by `WitnessCalculator` for the signer's own inputs.
+    pub fn construct_with_witness(circ_desc: &CircDescriptor, witness: Vec<F>) -> Result<LRSCirc<F>, SynthesisError> {
+        Self::construct_with_wires(circ_desc, witness.into_iter().map(Some).collect())
+    }
 
-        let witness_data: HashMap<usize, F> =
-            read_witness_file((circ_desc.path_prefix.clone() + &circ_desc.circuit_name + "_js/witness.wtns.json").as_str())
-                .expect(&format!(
-                    "Failed to read {}_js/witness.wtns.json",
-                    &circ_desc.circuit_name
-                ));
+    fn construct_with_wires(
+        circ_desc: &CircDescriptor,
+        witness_data: Vec<Option<F>>,
+    ) -> Result<LRSCirc<F>, SynthesisError> {
+        // public inputs | committed witness | uncommitted witness
+        let ioputs_name = &circ_desc.ioputs_name;
+
+        // `_constraints.json` if circom wrote one (`--json`), else the `.r1cs` file; without
+        // either there is no constraint system to build
+        let constraints_json = circ_desc.path_prefix.clone() + &circ_desc.circuit_name + "_constraints.json";
+        let constraints = if Path::new(&constraints_json).exists() {
+            Constraints::read_from_file(&constraints_json).map_err(|_| SynthesisError::MissingCS)?
+        } else {
+            Constraints::read_from_r1cs(&(circ_desc.path_prefix.clone() + &circ_desc.circuit_name + ".r1cs"))
+                .map_err(|_| SynthesisError::MissingCS)?
+        };
 
         let sym_data: HashMap<usize, String> =
             read_sym_file((circ_desc.path_prefix.clone() + &circ_desc.circuit_name + ".sym").as_str())
-                .expect(&format!("Failed to read {}.sym", &circ_desc.circuit_name));
+                .unwrap_or_else(|_| panic!("Failed to read {}.sym", &circ_desc.circuit_name));
+        let signal_wires: HashMap<String, usize> = sym_data.iter().map(|(idx, name)| (name.clone(), *idx)).collect();
+
+        let signal_value = |name: &String| -> Result<F, SynthesisError> {
+            let idx = *signal_wires.get(name).ok_or(SynthesisError::AssignmentMissing)?;
+            witness_data
+                .get(idx)
+                .copied()
+                .flatten()
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
 
         let instance = ioputs_name[0..circ_desc.num_pub_io]
             .iter()
-            .map(|name| {
-                let idx = sym_data
-                    .iter()
-                    .find(|(_, n)| *n == name)
-                    .map(|(index, _)| *index)
-                    .ok_or(SynthesisError::AssignmentMissing)?;
-                let value = witness_data
-                    .get(&idx)
-                    .ok_or(SynthesisError::AssignmentMissing)?;
-                Ok(*value)
-            })
+            .map(signal_value)
             .collect::<Result<Vec<F>, SynthesisError>>()?;
         // println!("instance: {:?}", instance);
 
         let commit_witness = ioputs_name[circ_desc.num_pub_io..(circ_desc.num_pub_io + circ_desc.num_commit_witness)]
             .iter()
-            .map(|name| {
-                let idx = sym_data
-                    .iter()
-                    .find(|(_, n)| *n == name)
-                    .map(|(index, _)| *index)
-                    .ok_or(SynthesisError::AssignmentMissing)?;
-                let value = witness_data
-                    .get(&idx)
-                    .ok_or(SynthesisError::AssignmentMissing)?;
-                Ok(*value)
-            })
+            .map(signal_value)
             .collect::<Result<Vec<F>, SynthesisError>>()?;
         // println!("committed_witness: {:?}", commit_witness);
 
+        let public_inputs = match &circ_desc.hashed_pub_io {
+            Some(hashed_pub_io) => {
+                // A hashed instance is a single public input
+                if circ_desc.num_pub_io != 1 {
+                    return Err(SynthesisError::AssignmentMissing);
+                }
+                hashed_pub_io
+                    .iter()
+                    .map(signal_value)
+                    .collect::<Result<Vec<F>, SynthesisError>>()?
+            }
+            None => instance.clone(),
+        };
+
         // Get input witness data
         Ok(LRSCirc {
             constraints,
             sym_data,
+            signal_wires,
             witness_data,
             instance,
             commit_witness,
+            public_inputs,
             circ_desc: circ_desc.clone(),
         })
     }
 }
 
-impl<F: Field + FromStr> ConstraintSynthesizer<F> for LRSCirc<F>
+impl<F: Field + FromStr> LRSCirc<F>
 where
     <F as FromStr>::Err: Debug,
 {
-    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
-        // 1. Load circuit.sym and find the value of main.phi
-        let sym_data = self.sym_data.clone();
-        let witness_data = self.witness_data.clone();
+    /// The value of wire `idx`, if the witness has one.
+    pub fn wire(&self, idx: usize) -> Option<F> {
+        self.witness_data.get(idx).copied().flatten()
+    }
+
+    fn wire_value(&self, idx: usize) -> Result<F, SynthesisError> {
+        self.wire(idx).ok_or(SynthesisError::AssignmentMissing)
+    }
 
-        let mut variables: HashMap<usize, Variable> = HashMap::new();
+    /// The wires behind the instance and witness variables, in the order
+    /// `generate_constraints` allocates them.
+    fn variable_wires(&self) -> Result<(Vec<usize>, Vec<usize>), SynthesisError> {
+        let num_ioputs = self.circ_desc.num_pub_io + self.circ_desc.num_commit_witness;
+        let ioputs_name = &self.circ_desc.ioputs_name[..num_ioputs];
 
-        // Make sure commit_witness are the first witnesses variable created
-        for i in 0..(self.circ_desc.num_pub_io + self.circ_desc.num_commit_witness) {
-            let var_name = &self.circ_desc.ioputs_name[i];
-            let idx = sym_data
-                .iter()
-                .find(|(_, name)| *name == var_name)
-                .map(|(index, _)| *index)
-                .ok_or(SynthesisError::AssignmentMissing)?;
-
-            let value = witness_data
-                .get(&idx)
-                .ok_or(SynthesisError::AssignmentMissing)?;
-
-            if i < self.circ_desc.num_pub_io {
-                let comm_var = cs.new_input_variable(|| Ok(*value))?;
-                variables.insert(idx, comm_var);
-            } else {
-                // Create commit witness variables
-                let comm_var = cs.new_witness_variable(|| Ok(*value))?;
-                variables.insert(idx, comm_var);
-            }
-            // println!("var_name: {:?}, idx: {:?}, value: {:?}", var_name, idx, value);
+        // 1. Public inputs, then the commit witness as the first witness variables
+        let ioputs = ioputs_name
+            .iter()
+            .map(|var_name| self.signal_wires.get(var_name).copied().ok_or(SynthesisError::AssignmentMissing))
+            .collect::<Result<Vec<_>, _>>()?;
+        let instance_wires = ioputs[..self.circ_desc.num_pub_io].to_vec();
+        let mut witness_wires = ioputs[self.circ_desc.num_pub_io..].to_vec();
+
+        // 2. Other witnesses and public input variables (such as main.out), by wire so that
+        // a `ProverIndex` fits every `LRSCirc` of the circuit
+        let ioputs = ioputs.into_iter().collect::<HashSet<_>>();
+        let mut other_wires = self
+            .sym_data
+            .keys()
+            .filter(|idx| !ioputs.contains(idx))
+            .copied()
+            .collect::<Vec<_>>();
+        other_wires.sort_unstable();
+        witness_wires.extend(other_wires);
+
+        Ok((instance_wires, witness_wires))
+    }
+
+    /// The `.sym` name of wire `idx`; wire 0 is the constant `1`.
+    fn signal_name(&self, idx: usize) -> String {
+        match self.sym_data.get(&idx) {
+            Some(name) => name.clone(),
+            None if idx == 0 => "one".to_string(),
+            None => format!("<wire {}>", idx),
         }
+    }
 
-        // 2. Create other witnesses and public input variables (such as main.out)
-        for entry in self.sym_data.iter() {
-            let idx = *entry.0;
-
-            // skip the commit witness and public input variables
-            let var_name = entry.1;
-
-            let mut skip = false;
-            for i in 0..(self.circ_desc.num_pub_io + self.circ_desc.num_commit_witness) {
-                if var_name == &self.circ_desc.ioputs_name[i] {
-                    // println!(
-                    //     "Skipping commit witness or public input variable: {:?}, idx: {:?}",
-                    //     var_name, idx
-                    // );
-                    skip = true;
-                    break;
-                }
+    /// Evaluates every constraint of `constraints` on `witness_data`, reporting the
+    /// first `max_reported` failing ones with the signals involved.
+    pub fn check_witness(&self, max_reported: usize) -> Result<(), LrsWitnessError<F>> {
+        let value = |idx: usize| -> Result<F, LrsWitnessError<F>> {
+            if idx == 0 {
+                return Ok(F::one());
             }
-            if skip {
+            self.wire(idx).ok_or(LrsWitnessError::MissingWire(idx))
+        };
+        let eval = |terms: &HashMap<usize, F>| -> Result<F, LrsWitnessError<F>> {
+            terms
+                .iter()
+                .try_fold(F::zero(), |acc, (idx, coeff)| Ok(acc + *coeff * value(*idx)?))
+        };
+
+        let mut failing = Vec::new();
+        let mut num_failing = 0;
+        for (index, constraint) in self.constraints.constraints.iter().enumerate() {
+            let (a, b, c) = (eval(&constraint[0])?, eval(&constraint[1])?, eval(&constraint[2])?);
+            if a * b == c {
                 continue;
             }
+            num_failing += 1;
+            if failing.len() < max_reported {
+                let mut wires = constraint
+                    .iter()
+                    .flat_map(|terms| terms.keys().copied())
+                    .collect::<Vec<_>>();
+                wires.sort_unstable();
+                wires.dedup();
+                let signals = wires
+                    .into_iter()
+                    .map(|idx| Ok((self.signal_name(idx), value(idx)?)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                failing.push(UnsatisfiedConstraint { index, a, b, c, signals });
+            }
+        }
 
-            // Create other witness variables
-            let witness_value = witness_data
-                .get(&idx)
-                .ok_or(SynthesisError::AssignmentMissing)?;
+        if num_failing == 0 {
+            Ok(())
+        } else {
+            Err(LrsWitnessError::Unsatisfied(failing, num_failing))
+        }
+    }
 
-          
-            // Create other witness variables
-            let witness_var = cs.new_witness_variable(|| Ok(*witness_value))?;
-            variables.insert(idx, witness_var);
-            
+    /// The assignment `(1, instance, witness)` that `generate_constraints` produces, for
+    /// `cc::create_proof_with_index`.
+    pub fn full_assignment(&self) -> Result<Vec<F>, SynthesisError> {
+        let (instance_wires, witness_wires) = self.variable_wires()?;
+        let mut assignment = vec![F::one()];
+        for idx in instance_wires.into_iter().chain(witness_wires) {
+            assignment.push(self.wire_value(idx)?);
+        }
+        Ok(assignment)
+    }
+}
+
+/// Synthesizes the circuit without cloning its constraints and witness.
+impl<F: Field + FromStr> ConstraintSynthesizer<F> for &LRSCirc<F>
+where
+    <F as FromStr>::Err: Debug,
+{
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let (instance_wires, witness_wires) = self.variable_wires()?;
+
+        let num_wires = instance_wires.iter().chain(&witness_wires).max().map_or(0, |idx| idx + 1);
+        let mut variables: Vec<Option<Variable>> = vec![None; num_wires];
+        for idx in instance_wires {
+            let value = self.wire_value(idx)?;
+            let var = cs.new_input_variable(|| Ok(value))?;
+            variables[idx] = Some(var);
+        }
+        for idx in witness_wires {
+            let value = self.wire_value(idx)?;
+            let var = cs.new_witness_variable(|| Ok(value))?;
+            variables[idx] = Some(var);
         }
 
         // 3. Parse and apply all constraints in circuit.json
@@ -180,3 +268,12 @@ where
         Ok(())
     }
 }
+
+impl<F: Field + FromStr> ConstraintSynthesizer<F> for LRSCirc<F>
+where
+    <F as FromStr>::Err: Debug,
+{
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        (&self).generate_constraints(cs)
+    }
+}
@@ -0,0 +1,288 @@
+//! The Merkle-tree variant of the scheme, over the `lrs_a_<depth>` circuits of `circoms/lrs_a`.
+//!
+//! The ring is the Merkle tree of the members `phi`, and a signature is a single cc proof,
+//! with no committed witness, that the signer's `phi` is a leaf under `root`, that the link
+//! tag is `L = MultiMiMC7((sk, sc), 1)`, and that `(R, s)` is a Schnorr signature of the
+//! message under the signer's key. The instance is `root, sc, L, msg, R[0], R[1], s`, as in
+//! `IOPUTS_NAME_LRS_A_*`; the verifier recomputes `root` from the ring, or keeps it.
+
+use crate::cc::helpers::hash_to_field;
+use crate::cc::{self, CcPVKey, ProverIndex};
+use crate::lrs::keys::{self, Keypair, Point, SK_BITS, SUBGROUP_ORDER};
+use crate::lrs::lrs_circ::LRSCirc;
+use crate::lrs::structures::CircDescriptor;
+use crate::lrs::witness::{WitnessCalculator, WitnessError};
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInteger, One, PrimeField, Zero};
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{CryptoRng, RngCore};
+#[cfg(any(test, feature = "bench"))]
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use num_bigint::BigUint;
+use std::str::FromStr;
+
+/// The proving key of the variant for Merkle trees of `depth`.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerklePVKey {
+    pub depth: usize,
+    pub crs_cc: CcPVKey<Bn254>,
+    pub cc_index: ProverIndex<Fr>,
+}
+
+/// The verifying key of the variant for Merkle trees of `depth`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerkleVKey {
+    pub depth: usize,
+    pub cc_vk: cc::VerifyingKey<Bn254>,
+}
+
+/// A signature; the root of the ring and the message complete its instance.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerkleSignature {
+    pub cc_proof: cc::Proof<Bn254>,
+    /// The scope `sc`
+    pub scope: Fr,
+    /// The link tag `L`, equal for the signatures of one key under one scope
+    pub link_tag: Fr,
+    /// The Schnorr signature `(R, s)` of the message
+    pub r: Point,
+    pub s: Fr,
+}
+
+/// Reasons `setup` and `sign` fail.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MerkleError {
+    /// The ring has more members than the tree has leaves, as (members, leaves).
+    RingTooLarge(usize, usize),
+    /// The key is not a member of the ring.
+    NotInRing,
+    /// The secret key is zero or not below `2^SK_BITS`.
+    InvalidSecretKey,
+    /// The witness calculator of the circuit failed.
+    Witness(WitnessError),
+    /// The circuit cannot be built or proven from the witness, or its files are missing.
+    Synthesis(SynthesisError),
+    /// The cc keys cannot be generated for the circuit.
+    Keys(cc::Error),
+}
+
+/// The Merkle tree of a ring, its leaves padded with zeros up to `2^depth`. Only the
+/// nodes above members are stored; the others are the roots of empty subtrees.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerkleTree {
+    /// The nodes of every level above members, from the leaves up to the root
+    levels: Vec<Vec<Fr>>,
+    /// The root of an empty subtree of every height
+    empty: Vec<Fr>,
+}
+
+/// `MultiMiMC7((left, right), 1)`, the node hash of `MerkleTreeChecker`.
+pub fn hash_node(left: Fr, right: Fr) -> Fr {
+    keys::multi_mimc7(&[left, right], Fr::one())
+}
+
+impl MerkleTree {
+    pub fn new(ring: &[Fr], depth: usize) -> Result<Self, MerkleError> {
+        let num_leaves = 1usize.checked_shl(depth as u32).unwrap_or(usize::MAX);
+        if ring.len() > num_leaves {
+            return Err(MerkleError::RingTooLarge(ring.len(), num_leaves));
+        }
+
+        let mut empty = vec![Fr::zero()];
+        let mut levels = vec![ring.to_vec()];
+        for height in 0..depth {
+            let level = levels[height]
+                .chunks(2)
+                .map(|pair| hash_node(pair[0], pair.get(1).copied().unwrap_or(empty[height])))
+                .collect::<Vec<_>>();
+            levels.push(level);
+            empty.push(hash_node(empty[height], empty[height]));
+        }
+        Ok(MerkleTree { levels, empty })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    pub fn root(&self) -> Fr {
+        self.node(self.depth(), 0)
+    }
+
+    fn node(&self, height: usize, index: usize) -> Fr {
+        self.levels[height].get(index).copied().unwrap_or(self.empty[height])
+    }
+
+    /// The `pathElements` and `pathIndices` of the leaf `index`: the sibling at every
+    /// level, and whether the path node is its right child.
+    pub fn path(&self, index: usize) -> (Vec<Fr>, Vec<Fr>) {
+        (0..self.depth())
+            .map(|height| {
+                let node = index >> height;
+                (self.node(height, node ^ 1), Fr::from((node & 1) as u64))
+            })
+            .unzip()
+    }
+}
+
+/// The circuit input `msg` for `message`.
+pub fn message_field(message: &str) -> Fr {
+    hash_to_field::<Bn254>(message.as_bytes().to_vec())
+}
+
+/// The challenge `e = MultiMiMC7((msg, R, pk), 1)` of `SchnorrVerify`.
+fn challenge(msg: Fr, r: &Point, pk: &Point) -> Fr {
+    keys::multi_mimc7(&[msg, r.0, r.1, pk.0, pk.1], Fr::one())
+}
+
+/// A Schnorr signature `(R, s)` of `msg` that `SchnorrVerify` accepts: `(e * sk) * BASE + R =
+/// s * BASE`, where `e * sk` is reduced modulo the field prime, not the subgroup order, and
+/// must fit in `SK_BITS` bits, so the nonce is resampled until it does. As the reduction
+/// depends on `sk`, only the circuit can check the signature.
+pub fn schnorr_sign<R: RngCore + CryptoRng>(keypair: &Keypair, msg: Fr, rng: &mut R) -> (Point, Fr) {
+    let order = BigUint::from_str(SUBGROUP_ORDER).unwrap();
+    loop {
+        let nonce = Keypair::generate(rng);
+        let sk_e = challenge(msg, &nonce.pk, &keypair.pk) * keypair.sk;
+        if sk_e.into_bigint().num_bits() as usize <= SK_BITS {
+            let s = (BigUint::from(sk_e.into_bigint()) + BigUint::from(nonce.sk.into_bigint())) % &order;
+            return (nonce.pk, Fr::from(s));
+        }
+    }
+}
+
+/// `setup_with_rng` with a fixed seed, for tests and benchmarks only: the
+/// trapdoors of the keys are public.
+#[cfg(any(test, feature = "bench"))]
+pub fn setup(depth: usize) -> Result<MerklePVKey, MerkleError> {
+    setup_with_rng(depth, &mut StdRng::seed_from_u64(0u64))
+}
+
+/// Generates the keys for the `lrs_a_<depth>` circuit in `PATH_PREFIX_LRS_A`; fails with
+/// `MerkleError::Synthesis` if its files are missing, e.g. for a depth `lrs_a.sh` did not build.
+pub fn setup_with_rng<R: RngCore + CryptoRng>(depth: usize, rng: &mut R) -> Result<MerklePVKey, MerkleError> {
+    let circ_desc = CircDescriptor::lrs_a(depth);
+    let circuit = LRSCirc::<Fr>::construct(&circ_desc).map_err(MerkleError::Synthesis)?;
+
+    let crs_cc: CcPVKey<Bn254> =
+        cc::generate_random_parameters(&circuit, circ_desc.num_commit_witness, rng).map_err(MerkleError::Keys)?;
+    let cc_index = cc::ProverIndex::new(&circuit).map_err(MerkleError::Synthesis)?;
+
+    Ok(MerklePVKey { depth, crs_cc, cc_index })
+}
+
+impl MerklePVKey {
+    /// The keys a verifier needs, to be shipped without the proving keys.
+    pub fn vkey(&self) -> MerkleVKey {
+        MerkleVKey {
+            depth: self.depth,
+            cc_vk: self.crs_cc.vk.clone(),
+        }
+    }
+}
+
+/// `sign_with_rng` with a fixed seed, for tests and benchmarks only: the
+/// Schnorr nonces of a key repeat, which reveals it.
+#[cfg(any(test, feature = "bench"))]
+pub fn sign(
+    pvkey: &MerklePVKey,
+    sk: Fr,
+    ring: &[Fr],
+    message: &str,
+    scope: Fr,
+) -> Result<MerkleSignature, MerkleError> {
+    sign_with_rng(pvkey, sk, ring, message, scope, &mut StdRng::seed_from_u64(0u64))
+}
+
+/// Signs `message` under `scope` for `ring`, in which the signer is the (first) member
+/// equal to the `phi` of `sk`.
+pub fn sign_with_rng<R: RngCore + CryptoRng>(
+    pvkey: &MerklePVKey,
+    sk: Fr,
+    ring: &[Fr],
+    message: &str,
+    scope: Fr,
+    rng: &mut R,
+) -> Result<MerkleSignature, MerkleError> {
+    if sk.is_zero() || sk.into_bigint().num_bits() as usize > SK_BITS {
+        return Err(MerkleError::InvalidSecretKey);
+    }
+    let keypair = Keypair::from_sk(sk);
+    let tree = MerkleTree::new(ring, pvkey.depth)?;
+    let signer_index = ring
+        .iter()
+        .position(|member| *member == keypair.phi())
+        .ok_or(MerkleError::NotInRing)?;
+    let (path_elements, path_indices) = tree.path(signer_index);
+
+    let msg = message_field(message);
+    let link_tag = keypair.link_tag(scope);
+    let (r, s) = schnorr_sign(&keypair, msg, rng);
+
+    let inputs = vec![
+        ("msg".to_string(), vec![msg]),
+        ("sk".to_string(), vec![sk]),
+        ("phi".to_string(), vec![keypair.phi()]),
+        ("sc".to_string(), vec![scope]),
+        ("L".to_string(), vec![link_tag]),
+        ("root".to_string(), vec![tree.root()]),
+        ("pathElements".to_string(), path_elements),
+        ("pathIndices".to_string(), path_indices),
+        ("R".to_string(), vec![r.0, r.1]),
+        ("s".to_string(), vec![s]),
+    ];
+    let circ_desc = CircDescriptor::lrs_a(pvkey.depth);
+    let wasm = format!(
+        "{}{}_js/{}.wasm",
+        circ_desc.path_prefix, circ_desc.circuit_name, circ_desc.circuit_name
+    );
+    let witness = WitnessCalculator::from_file(&wasm)
+        .and_then(|mut calculator| calculator.calculate(&inputs))
+        .map_err(MerkleError::Witness)?;
+    let circuit = LRSCirc::construct_with_witness(&circ_desc, witness).map_err(MerkleError::Synthesis)?;
+
+    let (cc_proof, _, _) = cc::create_proof_with_index(
+        &pvkey.cc_index,
+        &pvkey.crs_cc.pk,
+        &circuit.full_assignment().map_err(MerkleError::Synthesis)?,
+        rng,
+    )
+    .map_err(MerkleError::Synthesis)?;
+
+    Ok(MerkleSignature {
+        cc_proof,
+        scope,
+        link_tag,
+        r,
+        s,
+    })
+}
+
+/// The cc instance of `signature` for the ring with root `root` and `message`.
+pub fn instance(root: Fr, message: &str, signature: &MerkleSignature) -> Vec<Fr> {
+    vec![
+        root,
+        signature.scope,
+        signature.link_tag,
+        message_field(message),
+        signature.r.0,
+        signature.r.1,
+        signature.s,
+    ]
+}
+
+/// Verifies `signature` of `message` by a member of the ring with root `root`.
+pub fn verify(vkey: &MerkleVKey, root: Fr, message: &str, signature: &MerkleSignature) -> bool {
+    let instance = instance(root, message, signature)
+        .iter()
+        .map(|value| value.into_bigint())
+        .collect::<Vec<_>>();
+    let pvk = cc::prepare_verifying_key(&vkey.cc_vk);
+    cc::verify_proof_with_prepared_vk(&pvk, &signature.cc_proof, &instance).unwrap_or(false)
+}
+
+/// Whether two signatures under the same scope come from the same key.
+pub fn linked(a: &MerkleSignature, b: &MerkleSignature) -> bool {
+    a.scope == b.scope && a.link_tag == b.link_tag
+}
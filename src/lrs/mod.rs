@@ -1,11 +1,20 @@
+#[cfg(feature = "std")]
 pub mod lrs_circ;
 pub mod setup;
+#[cfg(feature = "std")]
 pub mod sign;
+pub mod keys;
+#[cfg(feature = "circom")]
+pub mod merkle_variant;
 pub mod structures;
+#[cfg(feature = "std")]
 pub mod utils;
 pub mod verify;
+#[cfg(feature = "circom")]
+pub mod witness;
 
 pub use structures::*;
+#[cfg(feature = "std")]
 pub use utils::*;
 
 #[cfg(test)]
@@ -16,6 +25,21 @@ mod tests {
     use crate::lrs::SignTime;
     use crate::lrs::VerifyTime;
     use crate::constants::*;
+    use ark_serialize::CanonicalSerialize;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    /// Checks that the JSON of `value` decodes to the same canonical bytes, and re-encodes
+    /// to the same JSON.
+    fn assert_json_round_trip<T: CanonicalSerialize + Serialize + DeserializeOwned>(value: &T) {
+        let json = serde_json::to_string(value).unwrap();
+        let decoded: T = serde_json::from_str(&json).unwrap();
+        let (mut bytes, mut decoded_bytes) = (Vec::new(), Vec::new());
+        value.serialize_compressed(&mut bytes).unwrap();
+        decoded.serialize_compressed(&mut decoded_bytes).unwrap();
+        assert_eq!(decoded_bytes, bytes);
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+    }
 
     #[test]
     fn test_read_witness_file() {
@@ -34,6 +58,30 @@ mod tests {
         );
         let constraints = Constraints::<ScalarField>::read_from_file(filename).unwrap();
         println!("{:?}", constraints.constraints[0]);
+
+        // The .r1cs file holds the same constraints, over BN254 only
+        let constraints = Constraints::<ark_bn254::Fr>::read_from_file(filename).unwrap();
+        let filename = &format!("{}/{}.r1cs", PATH_PREFIX_LRS_SE, CIRCUIT_NAME_LRS_SE);
+        let from_r1cs = Constraints::<ark_bn254::Fr>::read_from_r1cs(filename).unwrap();
+        assert_eq!(from_r1cs.constraints, constraints.constraints);
+        assert_eq!(
+            Constraints::<ScalarField>::read_from_r1cs(filename).err(),
+            Some(crate::lrs::CircLayoutError::FieldMismatch(filename.clone()))
+        );
+
+        // A header claiming more constraints than the file holds is rejected
+        let mut data = std::fs::read(filename).unwrap();
+        let header = crate::lrs::utils::r1cs_section(&data, 1).unwrap();
+        let num_constraints_at = header.as_ptr() as usize - data.as_ptr() as usize + 4 + 32 + 24;
+        data[num_constraints_at..num_constraints_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let truncated = std::env::temp_dir().join(format!("lrs-r1cs-{}.r1cs", std::process::id()));
+        std::fs::write(&truncated, &data).unwrap();
+        let truncated = truncated.to_str().unwrap().to_string();
+        assert_eq!(
+            Constraints::<ark_bn254::Fr>::read_from_r1cs(&truncated).err(),
+            Some(crate::lrs::CircLayoutError::R1cs(truncated.clone()))
+        );
+        std::fs::remove_file(&truncated).unwrap();
     }
 
     use crate::lrs::read_sym_file;
@@ -58,10 +106,100 @@ mod tests {
             ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
             path_prefix: PATH_PREFIX_LRS_SE.to_string(),
             circuit_name: CIRCUIT_NAME_LRS_SE.to_string(),
+            hashed_pub_io: None,
         };
         
         let lrs_circ = LRSCirc::<ScalarField>::construct(&circ_desc).unwrap();
         println!("{:?}", lrs_circ);
+        assert_eq!(lrs_circ.public_inputs, lrs_circ.instance);
+
+        // The assignment handed to `cc::create_proof_with_index` matches synthesis
+        use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+        let cs = ConstraintSystem::<ScalarField>::new_ref();
+        (&lrs_circ).generate_constraints(cs.clone()).unwrap();
+        let cs = cs.borrow().unwrap();
+        let expected = [cs.instance_assignment.as_slice(), cs.witness_assignment.as_slice()].concat();
+        assert_eq!(lrs_circ.full_assignment().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_circ_manifest() {
+        let circ_desc = CircDescriptor {
+            num_pub_io: NUM_PUB_IO_LRS_SE,
+            num_commit_witness: NUM_COMMIT_WITNESS_LRS_SE,
+            ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
+            path_prefix: PATH_PREFIX_LRS_SE.to_string(),
+            circuit_name: CIRCUIT_NAME_LRS_SE.to_string(),
+            hashed_pub_io: None,
+        };
+        let manifest = format!("{}{}.manifest.json", PATH_PREFIX_LRS_SE, CIRCUIT_NAME_LRS_SE);
+        assert_eq!(CircDescriptor::from_manifest(&manifest).unwrap(), circ_desc);
+
+        let dir = std::env::temp_dir().join(format!("lrs_manifest_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let read = |json: &str| {
+            let path = dir.join("circuit.manifest.json");
+            std::fs::write(&path, json).unwrap();
+            CircDescriptor::from_manifest(path.to_str().unwrap())
+        };
+        let circ_desc = read(
+            r#"{"circuit": "c", "path": "../c", "public": ["main.h"], "hashed_public": ["main.a", "main.b"]}"#,
+        )
+        .unwrap();
+        assert_eq!(circ_desc.path_prefix, format!("{}/../c/", dir.display()));
+        assert_eq!((circ_desc.num_pub_io, circ_desc.num_commit_witness), (1, 0));
+        assert_eq!(circ_desc.hashed_pub_io, Some(vec!["main.a".to_string(), "main.b".to_string()]));
+        assert!(read(r#"{"circuit": "c", "public": ["main.a", "main.b"], "hashed_public": []}"#).is_err());
+        assert!(read(r#"{"circuit": "c", "public": ["main.a"], "committed": ["main.a"]}"#).is_err());
+        assert!(read(r#"{"circuit": "c", "public": ["main.a"], "commited": ["main.b"]}"#).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // The generated lrs_a signals match the per-depth constants
+        assert_eq!(ioputs_name_lrs_a(3), IOPUTS_NAME_LRS_A_3.to_vec());
+        assert_eq!(ioputs_name_lrs_a(20), IOPUTS_NAME_LRS_A_20.to_vec());
+        assert_eq!(circuit_name_lrs_a(20), CIRCUIT_NAME_LRS_A_20);
+        assert_eq!(CircDescriptor::lrs_a(7).ioputs_name.len(), 11 + 2 * 7);
+    }
+
+    #[test]
+    fn test_circ_infer() {
+        use crate::lrs::CircLayoutError;
+        use ark_bn254::Fr as ScalarField;
+
+        // The Merkle circuit outputs its root, and the leaf can be committed
+        let prefix = "./circoms/lrs_a/merkle/merkle_circoms/";
+        let circ_desc = CircDescriptor::infer(prefix, "merkle_3", &["main.leaf"]).unwrap();
+        assert_eq!((circ_desc.num_pub_io, circ_desc.num_commit_witness), (1, 1));
+        assert_eq!(circ_desc.ioputs_name[..3], ["main.root", "main.leaf", "main.pathElements[0]"]);
+        assert_eq!(circ_desc.ioputs_name.len(), 8);
+        let lrs_circ = LRSCirc::<ScalarField>::construct(&circ_desc).unwrap();
+        assert_eq!(lrs_circ.check_witness(1), Ok(()));
+
+        let infer = |committed: &[&str]| CircDescriptor::infer(prefix, "merkle_3", committed);
+        assert_eq!(infer(&["main.root"]), Err(CircLayoutError::PublicSignal("main.root".to_string())));
+        assert_eq!(infer(&["main.nope"]), Err(CircLayoutError::UnknownSignal("main.nope".to_string())));
+        assert_eq!(
+            infer(&["main.hashers[0].k"]),
+            Err(CircLayoutError::RemovedSignal("main.hashers[0].k".to_string()))
+        );
+        assert_eq!(
+            infer(&["main.leaf", "main.leaf"]),
+            Err(CircLayoutError::DuplicateSignal("main.leaf".to_string()))
+        );
+        assert!(matches!(
+            CircDescriptor::infer(prefix, "merkle_0", &[]),
+            Err(CircLayoutError::Io(path, _)) if path.ends_with("merkle_0.r1cs")
+        ));
+
+        let sym = format!("{}merkle_3.sym", prefix);
+        assert_eq!(crate::lrs::read_r1cs_header(&sym), Err(CircLayoutError::R1cs(sym.clone())));
+
+        // The LRS circuit declares no public signals, so they are chosen in `CircDescriptor`
+        let header = crate::lrs::read_r1cs_header("./circoms/lrs/lrs.r1cs").unwrap();
+        assert_eq!((header.num_pub_out, header.num_pub_in, header.num_prv_in), (0, 0, 4));
+        let circ_desc = CircDescriptor::infer(PATH_PREFIX_LRS_SE, CIRCUIT_NAME_LRS_SE, &["main.phi"]).unwrap();
+        assert_eq!((circ_desc.num_pub_io, circ_desc.num_commit_witness), (0, 1));
+        assert_eq!(circ_desc.ioputs_name, ["main.phi", "main.sk", "main.sc", "main.L"]);
     }
 
     #[test]
@@ -81,9 +219,10 @@ mod tests {
             ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
             path_prefix: PATH_PREFIX_LRS_SE.to_string(),
             circuit_name: CIRCUIT_NAME_LRS_SE.to_string(),
+            hashed_pub_io: None,
         };
 
-        let (lrs_pvkey, circuit) = setup::setup::<ark_bn254::Bn254>(security_par, ring_size_max, &circ_desc);
+        let (lrs_pvkey, circuit) = setup::setup::<ark_bn254::Bn254>(security_par, ring_size_max, &circ_desc).unwrap();
 
         let signer_idx = 1usize;
         let rng = &mut ark_std::test_rng();
@@ -91,12 +230,397 @@ mod tests {
         ring[signer_idx] = circuit.commit_witness[0];
 
         let mut sign_time = SignTime::new();
-        let signature = sign::sign::<ark_bn254::Bn254>(&lrs_pvkey, &circuit, &ring, msg, &mut sign_time);
+        let signature = sign::sign::<ark_bn254::Bn254>(&lrs_pvkey, &circuit, &ring, msg, &mut sign_time).unwrap();
 
         let mut verify_time = VerifyTime::new();
         let result =
             verify::verify::<ark_bn254::Bn254>(&lrs_pvkey, &ring, msg, &signature, &mut verify_time);
 
         assert!(result, "Signature verification failed");
+
+        // Invalid signatures are rejected without panicking
+        assert!(!verify::verify(&lrs_pvkey, &ring, "another message", &signature, &mut verify_time));
+        let mut truncated = signature.clone();
+        truncated.cc_proof.d.clear();
+        assert!(!verify::verify(&lrs_pvkey, &ring, msg, &truncated, &mut verify_time));
+        assert!(!verify::verify(&lrs_pvkey, &vec![], msg, &signature, &mut verify_time));
+
+        // A decoded signature verifies
+        use crate::lrs::Signature;
+        use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+        let mut bytes = Vec::new();
+        signature.serialize_compressed(&mut bytes).unwrap();
+        let decoded = Signature::<ark_bn254::Bn254>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(decoded, signature);
+        assert!(verify::verify(&lrs_pvkey, &ring, msg, &decoded, &mut verify_time));
+
+        // A rerandomized cc proof keeps the link proof valid
+        let mut rerandomized = signature.clone();
+        rerandomized.cc_proof = crate::cc::rerandomize(&lrs_pvkey.crs_cc.vk, &signature.cc_proof, rng);
+        assert_ne!(rerandomized.cc_proof, signature.cc_proof);
+        assert!(verify::verify(&lrs_pvkey, &ring, msg, &rerandomized, &mut verify_time));
+
+        // So does the signature under a decoded verifier key, which finds the link tag
+        use crate::lrs::LrsVKey;
+        let mut bytes = Vec::new();
+        lrs_pvkey.vkey().serialize_compressed(&mut bytes).unwrap();
+        let lrs_vkey = LrsVKey::<ark_bn254::Bn254>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(lrs_vkey.validate(), Ok(()));
+        assert!(verify::verify_with_vkey(&lrs_vkey, &ring, msg, &signature, &mut verify_time));
+        assert_eq!(circ_desc.link_tag_index(), Some(1));
+        assert_eq!(lrs_vkey.link_tag(&signature), Some(signature.instance[1]));
+
+        // The signature, the verifier key and the ring round-trip through JSON
+        assert_json_round_trip(&signature);
+        assert_json_round_trip(&lrs_vkey);
+        assert_json_round_trip(&lrs_pvkey);
+        let mut pvkey_json = serde_json::to_value(&lrs_pvkey).unwrap();
+        pvkey_json["cc_index"]["domain_size"] = serde_json::json!(2);
+        assert!(serde_json::from_value::<crate::lrs::LrsPVKey<ark_bn254::Bn254>>(pvkey_json).is_err());
+        let mut ring_json = Vec::new();
+        crate::json::fields::serialize(&ring, &mut serde_json::Serializer::new(&mut ring_json)).unwrap();
+        let decoded_ring: Vec<ark_bn254::Fr> =
+            crate::json::fields::deserialize(&mut serde_json::Deserializer::from_slice(&ring_json)).unwrap();
+        assert_eq!(decoded_ring, ring);
+        let decoded: Signature<ark_bn254::Bn254> = serde_json::from_str(&serde_json::to_string(&signature).unwrap()).unwrap();
+        assert!(verify::verify_with_vkey(&lrs_vkey, &decoded_ring, msg, &decoded, &mut verify_time));
+
+        // The link proof does not carry over to a signature on another message
+        use crate::link::{PESubspaceSnark, SubspaceSnark};
+        use ark_ec::CurveGroup;
+        let commitments = vec![signature.sma_comm.c_g1.into_affine(), signature.cc_proof.d[0]];
+        let label_for = |msg: &str| {
+            verify::link_label(
+                msg,
+                &signature.sma_comm,
+                &signature.sma_proof,
+                &signature.cc_proof.d[0],
+                &signature.instance,
+            )
+        };
+        let link = &lrs_pvkey.crs_link;
+        let link_proof = &signature.link_proof;
+        assert!(PESubspaceSnark::<ark_bn254::Bn254>::verify(
+            &link.pp, &link.vk, &commitments, link_proof, &label_for(msg)
+        ));
+        assert!(!PESubspaceSnark::<ark_bn254::Bn254>::verify(
+            &link.pp, &link.vk, &commitments, link_proof, &label_for("another message")
+        ));
+    }
+
+    #[test]
+    fn test_json_encoding() {
+        use crate::cc::Proof;
+        use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+        use ark_ec::AffineRepr;
+        use ark_ff::PrimeField;
+        use serde_json::json;
+
+        // Points are [x, y, 1] as in snarkjs, with the coefficients of G2 coordinates in arrays
+        let proof = Proof::<Bn254> {
+            a: G1Affine::generator(),
+            b: G2Affine::generator(),
+            c: G1Affine::zero(),
+            d: vec![],
+            delta_prime: G2Affine::zero(),
+        };
+        let value = serde_json::to_value(&proof).unwrap();
+        assert_eq!(value["a"], json!(["1", "2", "1"]));
+        assert_eq!(value["b"][2], json!(["1", "0"]));
+        assert_eq!(value["c"], json!(["0", "1", "0"]));
+        assert_eq!(value["delta_prime"], json!([["0", "0"], ["1", "0"], ["0", "0"]]));
+        assert_json_round_trip(&proof);
+
+        // Anything but the canonical encoding is rejected
+        let decode = |a: serde_json::Value| {
+            let mut value = value.clone();
+            value["a"] = a;
+            serde_json::from_value::<Proof<Bn254>>(value).is_ok()
+        };
+        assert!(decode(json!(["1", "2", "1"])));
+        assert!(!decode(json!(["01", "2", "1"])));
+        assert!(!decode(json!(["+1", "2", "1"])));
+        assert!(!decode(json!([1, 2, 1])));
+        assert!(!decode(json!(["1", "3", "1"])));
+        assert!(!decode(json!(["1", "2", "2"])));
+        assert!(!decode(json!(["1", "2"])));
+        assert!(!decode(json!(["0", "0", "0"])));
+
+        let fields = |values: serde_json::Value| crate::json::fields::deserialize::<Fr, _>(values).is_ok();
+        assert!(fields(json!(["0", "12"])));
+        assert!(!fields(json!([Fr::MODULUS.to_string()])));
+        assert!(!fields(json!(["00"])));
+        assert!(!fields(json!([""])));
+    }
+
+    #[test]
+    fn test_lrs_check_witness() {
+        use crate::lrs::LrsWitnessError;
+        use ark_bn254::Fr as ScalarField;
+
+        let circ_desc = CircDescriptor {
+            num_pub_io: NUM_PUB_IO_LRS_SE,
+            num_commit_witness: NUM_COMMIT_WITNESS_LRS_SE,
+            ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
+            path_prefix: PATH_PREFIX_LRS_SE.to_string(),
+            circuit_name: CIRCUIT_NAME_LRS_SE.to_string(),
+            hashed_pub_io: None,
+        };
+        let lrs_circ = LRSCirc::<ScalarField>::construct(&circ_desc).unwrap();
+        assert_eq!(lrs_circ.check_witness(4), Ok(()));
+
+        // Tamper with the committed witness: the failures name it
+        let name = &circ_desc.ioputs_name[circ_desc.num_pub_io];
+        let idx = lrs_circ.signal_wires[name];
+        let mut wrong_circ = lrs_circ.clone();
+        *wrong_circ.witness_data[idx].as_mut().unwrap() += ScalarField::from(1u64);
+        match wrong_circ.check_witness(1) {
+            Err(LrsWitnessError::Unsatisfied(failing, num_failing)) => {
+                assert_eq!(failing.len(), 1);
+                assert!(num_failing >= 1);
+                assert_ne!(failing[0].a * failing[0].b, failing[0].c);
+                assert!(failing[0].signals.iter().any(|(n, _)| n == name));
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+
+        let mut missing_circ = lrs_circ.clone();
+        missing_circ.witness_data[idx] = None;
+        assert_eq!(missing_circ.check_witness(4), Err(LrsWitnessError::MissingWire(idx)));
+    }
+
+    #[test]
+    fn test_lrs_pvkey_validate() {
+        use crate::lrs::setup;
+        use crate::lrs::LrsKeyError;
+        use crate::sma::SmaCrsError;
+
+        let circ_desc = CircDescriptor {
+            num_pub_io: NUM_PUB_IO_LRS_SE,
+            num_commit_witness: NUM_COMMIT_WITNESS_LRS_SE,
+            ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
+            path_prefix: PATH_PREFIX_LRS_SE.to_string(),
+            circuit_name: CIRCUIT_NAME_LRS_SE.to_string(),
+            hashed_pub_io: None,
+        };
+
+        let (lrs_pvkey, _) = setup::setup::<ark_bn254::Bn254>("128".to_string(), 1 << 4, &circ_desc).unwrap();
+        assert_eq!(lrs_pvkey.validate(), Ok(()));
+        assert_eq!(lrs_pvkey.vkey().validate(), Ok(()));
+
+        let mut bad = lrs_pvkey.vkey();
+        bad.link_vk.c_1.pop();
+        assert_eq!(bad.validate(), Err(LrsKeyError::LinkKeyShape));
+
+        let mut bad = lrs_pvkey.clone();
+        bad.crs_link.vk.c_1.swap(0, 1);
+        assert_eq!(bad.validate(), Err(LrsKeyError::LinkColumnMismatch(1, 0)));
+
+        let mut bad = lrs_pvkey.clone();
+        bad.crs_link.ek.p_0[0][2] = bad.crs_link.ek.p_0[1][2];
+        assert_eq!(bad.validate(), Err(LrsKeyError::LinkColumnMismatch(0, 2)));
+
+        let mut bad = lrs_pvkey.clone();
+        bad.crs_cc.pk.commit_slots[0].eta_gamma_inv_g1 = bad.crs_cc.pk.delta_g1;
+        assert_eq!(bad.validate(), Err(LrsKeyError::LinkMatrixDigest));
+        bad.crs_link.matrix_digest = None;
+        assert_eq!(bad.validate(), Err(LrsKeyError::LinkColumnMismatch(0, 2)));
+
+        let mut bad = lrs_pvkey.clone();
+        bad.crs_sma.crs_g2s[2] = bad.crs_sma.crs_g2s[1];
+        assert_eq!(bad.validate(), Err(LrsKeyError::Sma(SmaCrsError::G2PowerMismatch(2))));
+    }
+
+    #[test]
+    fn test_keys() {
+        use crate::lrs::keys::*;
+        use ark_bn254::Fr;
+        use ark_ff::PrimeField;
+        use ark_std::str::FromStr;
+
+        // The inputs of circoms/lrs/input_backend.json
+        let keypair = Keypair::from_sk(Fr::from(20001027u64));
+        let phi = "16522763539274936672550010747276183294750403510407374019801689147715102349978";
+        let tag = "17804474404831372771318062141310527328296588970581814948270177988694117180422";
+        assert!(is_on_curve(&keypair.pk));
+        assert_eq!(keypair.phi(), Fr::from_str(phi).unwrap());
+        assert_eq!(keypair.link_tag(Fr::from(20000928u64)), Fr::from_str(tag).unwrap());
+
+        let keypair = Keypair::generate(&mut ark_std::test_rng());
+        assert!(keypair.sk.into_bigint() < Fr::from_str(SUBGROUP_ORDER).unwrap().into_bigint());
+        assert_eq!(mul(Fr::from_str(SUBGROUP_ORDER).unwrap(), &keypair.pk), (Fr::from(0u64), Fr::from(1u64)));
+    }
+
+    #[cfg(feature = "circom")]
+    #[test]
+    fn test_witness_calculator() {
+        use crate::lrs::keys::Keypair;
+        use crate::lrs::witness::{WitnessCalculator, WitnessError};
+        use ark_bn254::Fr;
+
+        let path = format!("{}/{}_js/{}.wasm", PATH_PREFIX_LRS_SE, CIRCUIT_NAME_LRS_SE, CIRCUIT_NAME_LRS_SE);
+        let mut calculator = WitnessCalculator::from_file(&path).unwrap();
+        let keypair = Keypair::from_sk(Fr::from(20001027u64));
+        let sc = Fr::from(20000928u64);
+        let mut inputs = vec![
+            ("sk".to_string(), vec![keypair.sk]),
+            ("phi".to_string(), vec![keypair.phi()]),
+            ("sc".to_string(), vec![sc]),
+            ("L".to_string(), vec![keypair.link_tag(sc)]),
+        ];
+
+        // The same witness as generate_witness.js
+        let witness = calculator.calculate(&inputs).unwrap();
+        let filename = format!("{}/{}_js/witness.wtns.json", PATH_PREFIX_LRS_SE, CIRCUIT_NAME_LRS_SE);
+        let expected = read_witness_file::<Fr>(&filename).unwrap();
+        assert_eq!(witness.len(), expected.len());
+        assert!(witness.iter().enumerate().all(|(i, value)| expected[&i] == *value));
+
+        inputs[3].1[0] += Fr::from(1u64);
+        assert_eq!(calculator.calculate(&inputs), Err(WitnessError::Exception(4)));
+        inputs[3].0 = "tag".to_string();
+        assert_eq!(calculator.calculate(&inputs), Err(WitnessError::UnknownInput("tag".to_string())));
+    }
+
+    #[cfg(feature = "circom")]
+    #[test]
+    fn test_lrs_sign_with_fresh_key() {
+        use crate::lrs::keys::Keypair;
+        use crate::lrs::witness::WitnessCalculator;
+        use crate::lrs::{setup, sign, verify, LrsPVKey, LrsWitnessError};
+        use crate::sma::ring_gen;
+        use ark_bn254::{Bn254, Fr};
+        use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+        let circ_desc = CircDescriptor {
+            num_pub_io: NUM_PUB_IO_LRS_SE,
+            num_commit_witness: NUM_COMMIT_WITNESS_LRS_SE,
+            ioputs_name: IOPUTS_NAME_LRS_SE.iter().map(|s| s.to_string()).collect(),
+            path_prefix: PATH_PREFIX_LRS_SE.to_string(),
+            circuit_name: CIRCUIT_NAME_LRS_SE.to_string(),
+            hashed_pub_io: None,
+        };
+        let rng = &mut ark_std::test_rng();
+        let (lrs_pvkey, _) = setup::setup_with_rng::<Bn254, _>("128".to_string(), 1 << 4, &circ_desc, rng).unwrap();
+
+        // A decoded prover key signs
+        let mut bytes = Vec::new();
+        lrs_pvkey.serialize_compressed(&mut bytes).unwrap();
+        let lrs_pvkey = LrsPVKey::<Bn254>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(lrs_pvkey.validate(), Ok(()));
+
+        let keypair = Keypair::generate(rng);
+        let sc = Fr::from(7u64);
+        let inputs = vec![
+            ("sk".to_string(), vec![keypair.sk]),
+            ("phi".to_string(), vec![keypair.phi()]),
+            ("sc".to_string(), vec![sc]),
+            ("L".to_string(), vec![keypair.link_tag(sc)]),
+        ];
+        let path = format!("{}/{}_js/{}.wasm", PATH_PREFIX_LRS_SE, CIRCUIT_NAME_LRS_SE, CIRCUIT_NAME_LRS_SE);
+        let witness = WitnessCalculator::from_file(&path).unwrap().calculate(&inputs).unwrap();
+        let circuit = LRSCirc::construct_with_witness(&circ_desc, witness).unwrap();
+
+        let mut ring = ring_gen::<_, Bn254>(1 << 4, 3, rng);
+        let mut sign_time = SignTime::new();
+        assert_eq!(
+            sign::sign_with_rng(&lrs_pvkey, &circuit, &ring, "msg", &mut sign_time, rng).err(),
+            Some(LrsWitnessError::NotInRing)
+        );
+
+        // The signer need not be the first member
+        ring[3] = keypair.phi();
+        let signature = sign::sign_with_rng(&lrs_pvkey, &circuit, &ring, "msg", &mut sign_time, rng).unwrap();
+        assert!(verify::verify(&lrs_pvkey, &ring, "msg", &signature, &mut VerifyTime::new()));
+        assert_eq!(lrs_pvkey.vkey().link_tag(&signature), Some(keypair.link_tag(sc)));
+    }
+
+    #[test]
+    #[cfg(feature = "circom")]
+    fn test_merkle_variant() {
+        use crate::lrs::keys::{self, Keypair};
+        use crate::lrs::merkle_variant::*;
+        use ark_bn254::Fr;
+        use ark_ff::Zero;
+        use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+        use ark_std::rand::{rngs::StdRng, SeedableRng};
+        use std::str::FromStr;
+
+        let fr = |s: &str| Fr::from_str(s).unwrap();
+        let fold = |leaf: Fr, (elements, indices): &(Vec<Fr>, Vec<Fr>)| {
+            elements.iter().zip(indices).fold(leaf, |node, (sibling, index)| {
+                if index.is_zero() { hash_node(node, *sibling) } else { hash_node(*sibling, node) }
+            })
+        };
+
+        // The sample input of lrs_a_3: its root, and its Schnorr signature
+        let sample_phi = fr("16522763539274936672550010747276183294750403510407374019801689147715102349978");
+        let sample_path = (vec![fr("1"), fr("2"), fr("3")], vec![fr("1"), fr("0"), fr("1")]);
+        assert_eq!(
+            fold(sample_phi, &sample_path),
+            fr("4033257068176571581019461532540626551678213060258794414183121152269717455683")
+        );
+        let sample_key = Keypair::from_sk(fr("20001027"));
+        assert_eq!(sample_key.phi(), sample_phi);
+        let schnorr_holds = |keypair: &Keypair, msg: Fr, r: &keys::Point, s: Fr| {
+            let e = keys::multi_mimc7(&[msg, r.0, r.1, keypair.pk.0, keypair.pk.1], Fr::from(1u64));
+            keys::add(&keys::public_key(e * keypair.sk), r) == keys::public_key(s)
+        };
+        let sample_r = (
+            fr("10918642783053875021381551257981477660228172875824202511341991363438302410352"),
+            fr("7976072897088337598522169879450513734342614653470297207233748225578910562791"),
+        );
+        let sample_s = fr("10906470673929214899118628593245015559688844497719200689591735429009794817349");
+        assert!(schnorr_holds(&sample_key, fr("12345"), &sample_r, sample_s));
+
+        // Every member's path leads to the root, the empty leaves included
+        let rng = &mut StdRng::seed_from_u64(0u64);
+        let keypairs = (0..3).map(|_| Keypair::generate(rng)).collect::<Vec<_>>();
+        let ring = keypairs.iter().map(|keypair| keypair.phi()).collect::<Vec<_>>();
+        let tree = MerkleTree::new(&ring, 3).unwrap();
+        for (index, leaf) in ring.iter().chain([Fr::zero()].iter()).enumerate() {
+            assert_eq!(fold(*leaf, &tree.path(index)), tree.root());
+        }
+        assert_eq!(MerkleTree::new(&[Fr::zero(); 9], 3).err(), Some(MerkleError::RingTooLarge(9, 8)));
+
+        let (r, s) = schnorr_sign(&keypairs[1], fr("12345"), rng);
+        assert!(schnorr_holds(&keypairs[1], fr("12345"), &r, s));
+
+        // Depth 8 has no `.r1cs` file in the tree
+        assert_eq!(
+            setup(8).err(),
+            Some(MerkleError::Synthesis(ark_relations::r1cs::SynthesisError::MissingCS))
+        );
+        let pvkey = setup(3).unwrap();
+        let vkey = pvkey.vkey();
+        let (sc, root) = (Fr::from(7u64), tree.root());
+        let signature = sign(&pvkey, keypairs[1].sk, &ring, "msg", sc).unwrap();
+        assert!(verify(&vkey, root, "msg", &signature));
+        assert!(!verify(&vkey, root, "other msg", &signature));
+        assert!(!verify(&vkey, MerkleTree::new(&ring[..2], 3).unwrap().root(), "msg", &signature));
+        let mut forged = signature.clone();
+        forged.link_tag = keypairs[0].link_tag(sc);
+        assert!(!verify(&vkey, root, "msg", &forged));
+
+        // A decoded signature verifies under a decoded verifier key
+        let mut bytes = Vec::new();
+        signature.serialize_compressed(&mut bytes).unwrap();
+        let decoded = MerkleSignature::deserialize_compressed(&bytes[..]).unwrap();
+        let mut bytes = Vec::new();
+        vkey.serialize_compressed(&mut bytes).unwrap();
+        let decoded_vkey = MerkleVKey::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(verify(&decoded_vkey, root, "msg", &decoded));
+
+        // Signatures by one key under one scope link, whatever the message
+        let other = sign_with_rng(&pvkey, keypairs[1].sk, &ring, "other msg", sc, rng).unwrap();
+        assert!(verify(&vkey, root, "other msg", &other));
+        assert!(linked(&signature, &other));
+        assert_eq!(signature.link_tag, keypairs[1].link_tag(sc));
+
+        assert_eq!(
+            sign(&pvkey, Keypair::generate(rng).sk, &ring, "msg", sc).err(),
+            Some(MerkleError::NotInRing)
+        );
+        assert_eq!(sign(&pvkey, Fr::zero(), &ring, "msg", sc).err(), Some(MerkleError::InvalidSecretKey));
     }
 }
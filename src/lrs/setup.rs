@@ -1,39 +1,63 @@
-use crate::cc;
 use crate::cc::CcPVKey;
-use crate::link;
-use crate::link::snark::SubspaceSnark;
 use crate::link::SparseMatrix;
-use crate::lrs::lrs_circ::LRSCirc;
-use crate::lrs::structures::LrsPVKey;
-use crate::sma;
+use crate::lrs::structures::{LrsKeyError, LrsPVKey, LrsVKey};
+use crate::sma::SmaCRS;
 use ark_ec::pairing::Pairing;
-use ark_ec::CurveGroup;
-use ark_ec::Group;
+use ark_ec::AffineRepr;
+use ark_ff::Zero;
 use ark_relations::r1cs::Field;
 use ark_std::fmt::Debug;
 use ark_std::str::FromStr;
+use ark_std::vec::Vec;
 
-use super::CircDescriptor;
+// Setup reads the circuit from its files
+#[cfg(feature = "std")]
+use {
+    super::CircDescriptor,
+    crate::cc,
+    crate::link,
+    crate::link::snark::SubspaceSnark,
+    crate::lrs::lrs_circ::LRSCirc,
+    crate::sma,
+    ark_ec::{CurveGroup, Group},
+    ark_std::rand::RngCore,
+};
 
+/// `setup_with_rng` with the fixed `ark_std::test_rng`, for tests and benchmarks only: the
+/// trapdoors of the keys are public.
+#[cfg(all(feature = "std", any(test, feature = "bench")))]
 pub fn setup<E>(
     security_par: String,
     ring_size_max: usize,
     circ_desc: &CircDescriptor,
-) -> (LrsPVKey<E>, LRSCirc<E::ScalarField>)
+) -> crate::Result<(LrsPVKey<E>, LRSCirc<E::ScalarField>)>
 where
     E: Pairing,
     <E as Pairing>::ScalarField: Field + FromStr,
     <E::ScalarField as FromStr>::Err: Debug,
 {
-    let rng = &mut ark_std::test_rng();
+    setup_with_rng(security_par, ring_size_max, circ_desc, &mut ark_std::test_rng())
+}
 
-    let lrs_circ = LRSCirc::<E::ScalarField>::construct(circ_desc).unwrap();
+/// Generates the keys for the circuit of `circ_desc`; fails if the circuit files do not match it.
+#[cfg(feature = "std")]
+pub fn setup_with_rng<E, R: RngCore>(
+    security_par: String,
+    ring_size_max: usize,
+    circ_desc: &CircDescriptor,
+    rng: &mut R,
+) -> crate::Result<(LrsPVKey<E>, LRSCirc<E::ScalarField>)>
+where
+    E: Pairing,
+    <E as Pairing>::ScalarField: Field + FromStr,
+    <E::ScalarField as FromStr>::Err: Debug,
+{
+    let lrs_circ = LRSCirc::<E::ScalarField>::construct(circ_desc)?;
 
     let crs_sma = sma::crs_key_gen(security_par, ring_size_max, rng);
 
     let crs_cc: CcPVKey<E> =
-        cc::generate_random_parameters(lrs_circ.clone(), circ_desc.num_commit_witness, rng)
-            .unwrap();
+        cc::generate_random_parameters(&lrs_circ, circ_desc.num_commit_witness, rng)?;
 
     let link_rows = 2;
     let link_cols = circ_desc.num_commit_witness + 2;
@@ -44,25 +68,148 @@ where
         g2: E::G2::generator().into_affine(),
     };
 
-    let mut link_m = SparseMatrix::<E::G1Affine>::new(link_rows, link_cols);
-    link_m.insert_row_slice(0, 0, vec![crs_sma.crs_g1s[1], crs_sma.crs_g1s[0]]);
-    link_m.insert_row_slice(1, 0, crs_cc.pk.comm_wit_abc_query.clone());
-    assert!(crs_cc.pk.comm_wit_abc_query.len() == circ_desc.num_commit_witness);
-    link_m.insert_row_slice(
-        1,
-        circ_desc.num_commit_witness + 1,
-        vec![crs_cc.pk.eta_gamma_inv_g1],
-    );
+    let num_commit_query = crs_cc.pk.commit_slots[0].comm_wit_abc_query.len();
+    if num_commit_query != circ_desc.num_commit_witness {
+        return Err(cc::Error::LengthMismatch {
+            expected: circ_desc.num_commit_witness,
+            got: num_commit_query,
+        });
+    }
+    let link_m = link_matrix(&crs_sma, &crs_cc);
 
     let crs_link = link::PESubspaceSnark::<E>::keygen(rng, &link_pp, &link_m);
 
+    let cc_index = cc::ProverIndex::new(&lrs_circ)?;
+
     // Return the generated CRS
-    (
+    Ok((
         LrsPVKey {
             crs_link,
             crs_cc,
             crs_sma,
+            cc_index,
+            hashed_instance: circ_desc.hashed_pub_io.is_some(),
+            link_tag_index: circ_desc.link_tag_index(),
         },
         lrs_circ,
-    )
+    ))
+}
+
+/// The matrix whose column span the link SNARK proves membership in:
+/// row 0 opens the SMA commitment `c_g1`, row 1 opens the (single) cc commitment `D`.
+pub fn link_matrix<E: Pairing>(crs_sma: &SmaCRS<E>, crs_cc: &CcPVKey<E>) -> SparseMatrix<E::G1Affine> {
+    let slot = &crs_cc.pk.commit_slots[0];
+    let num_commit_witness = slot.comm_wit_abc_query.len();
+    let mut link_m = SparseMatrix::<E::G1Affine>::builder(2, num_commit_witness + 2);
+    link_m.insert_row_slice(0, 0, &[crs_sma.crs_g1s[1], crs_sma.crs_g1s[0]]).unwrap();
+    link_m.insert_row_slice(1, 0, &slot.comm_wit_abc_query).unwrap();
+    link_m.insert_row_slice(1, num_commit_witness + 1, &[slot.eta_gamma_inv_g1]).unwrap();
+    link_m.build()
+}
+
+impl<E> LrsPVKey<E>
+where
+    E: Pairing,
+    <E as Pairing>::ScalarField: Field + FromStr,
+    <E::ScalarField as FromStr>::Err: Debug,
+{
+    /// Checks the SMA CRS, and that the link keys were generated for the matrix `setup`
+    /// derives from `crs_sma` and `crs_cc`, i.e. `P_b * A = M^T * C_b` for `b = 0, 1`.
+    pub fn validate(&self) -> Result<(), LrsKeyError> {
+        self.crs_sma.validate().map_err(LrsKeyError::Sma)?;
+
+        let pk = &self.crs_cc.pk;
+        let vk = &self.crs_cc.vk;
+        if pk.commit_slots.len() != 1 || vk.gamma_g2_neg_pc.len() != 1 {
+            return Err(LrsKeyError::CommitSlotCount(pk.commit_slots.len()));
+        }
+        let slot = &pk.commit_slots[0];
+        if vk.commit_witness_indices != [slot.indices.clone()] {
+            return Err(LrsKeyError::CommitWitnessMismatch(
+                pk.commit_witness_num(),
+                vk.commit_witness_indices.iter().map(|indices| indices.len()).sum(),
+            ));
+        }
+        if slot.comm_wit_abc_query.len() != slot.len() {
+            return Err(LrsKeyError::CommitQueryLength(
+                slot.comm_wit_abc_query.len(),
+                slot.len(),
+            ));
+        }
+
+        let link_m = link_matrix(&self.crs_sma, &self.crs_cc);
+        let pp = &self.crs_link.pp;
+        if (pp.nr, pp.nc) != (link_m.nr, link_m.nc) {
+            return Err(LrsKeyError::LinkShape((pp.nr, pp.nc), (link_m.nr, link_m.nc)));
+        }
+        if self.crs_link.matrix_digest.is_some_and(|digest| digest != link_m.digest()) {
+            return Err(LrsKeyError::LinkMatrixDigest);
+        }
+
+        let ek = &self.crs_link.ek;
+        let vk = &self.crs_link.vk;
+        let k = vk.a.len();
+        if k == 0
+            || ek.p_0.len() != k
+            || ek.p_1.len() != k
+            || ek.p_0.iter().chain(ek.p_1.iter()).any(|col| col.len() != pp.nc)
+            || vk.c_0.len() != pp.nr
+            || vk.c_1.len() != pp.nr
+        {
+            return Err(LrsKeyError::LinkKeyShape);
+        }
+
+        for (part, (p, c)) in [(&ek.p_0, &vk.c_0), (&ek.p_1, &vk.c_1)].into_iter().enumerate() {
+            for j in 0..pp.nc {
+                let mut g1s = p.iter().map(|p_col| p_col[j].into_group()).collect::<Vec<_>>();
+                let mut g2s = vk.a.clone();
+                for coeff_pos in link_m.get_col(j) {
+                    g1s.push(-coeff_pos.val.into_group());
+                    g2s.push(c[coeff_pos.pos]);
+                }
+                if !E::multi_pairing(g1s, g2s).is_zero() {
+                    return Err(LrsKeyError::LinkColumnMismatch(part, j));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The keys a verifier needs, to be shipped without the proving keys.
+    pub fn vkey(&self) -> LrsVKey<E> {
+        LrsVKey {
+            link_pp: self.crs_link.pp.clone(),
+            link_vk: self.crs_link.vk.clone(),
+            cc_vk: self.crs_cc.vk.clone(),
+            crs_sma: self.crs_sma.clone(),
+            hashed_instance: self.hashed_instance,
+            link_tag_index: self.link_tag_index,
+        }
+    }
+}
+
+impl<E: Pairing> LrsVKey<E> {
+    /// The checks of `LrsPVKey::validate` that need no proving key: the SMA CRS, and the
+    /// shapes of the cc and link verifying keys. Whether the link keys match the cc keys
+    /// can only be checked with `LrsPVKey::validate`.
+    pub fn validate(&self) -> Result<(), LrsKeyError> {
+        self.crs_sma.validate().map_err(LrsKeyError::Sma)?;
+
+        let vk = &self.cc_vk;
+        if vk.gamma_g2_neg_pc.len() != 1 || vk.commit_witness_indices.len() != 1 {
+            return Err(LrsKeyError::CommitSlotCount(vk.gamma_g2_neg_pc.len()));
+        }
+        let link_cols = vk.commit_witness_indices[0].len() + 2;
+        let pp = &self.link_pp;
+        if (pp.nr, pp.nc) != (2, link_cols) {
+            return Err(LrsKeyError::LinkShape((pp.nr, pp.nc), (2, link_cols)));
+        }
+
+        let vk = &self.link_vk;
+        if vk.a.is_empty() || vk.c_0.len() != pp.nr || vk.c_1.len() != pp.nr {
+            return Err(LrsKeyError::LinkKeyShape);
+        }
+        Ok(())
+    }
 }
@@ -1,23 +1,46 @@
-use super::lrs_circ::LRSCirc;
+use super::lrs_circ::{LRSCirc, MAX_REPORTED_CONSTRAINTS};
 use super::structures::SignTime;
 use crate::cc;
 use crate::link::PESubspaceSnark;
 use crate::link::SubspaceSnark;
 use crate::lrs::structures::LrsPVKey;
-use crate::lrs::Signature;
+use crate::lrs::verify::link_label;
+use crate::lrs::{LrsWitnessError, Signature};
 use crate::sma;
 use ark_ec::pairing::Pairing;
 use ark_ff::Field;
+use ark_std::rand::RngCore;
 use ark_std::fmt::Debug;
 use ark_std::str::FromStr;
 
+/// `sign_with_rng` with the fixed `ark_std::test_rng`, for tests and benchmarks only: the
+/// signatures of a key are all blinded alike.
+#[cfg(any(test, feature = "bench"))]
 pub fn sign<E>(
     lrs_pvkey: &LrsPVKey<E>,
     circuit: &LRSCirc<E::ScalarField>,
     ring: &Vec<E::ScalarField>,
     message: &str,
     sign_time: &mut SignTime,
-) -> Signature<E>
+) -> Result<Signature<E>, LrsWitnessError<E::ScalarField>>
+where
+    E: Pairing,
+    <E as Pairing>::ScalarField: Field + FromStr,
+    <E::ScalarField as FromStr>::Err: Debug,
+{
+    sign_with_rng(lrs_pvkey, circuit, ring, message, sign_time, &mut ark_std::test_rng())
+}
+
+/// Signs `message` for `ring`, in which the signer is the (first) member equal to the
+/// committed witness of `circuit`.
+pub fn sign_with_rng<E, R: RngCore>(
+    lrs_pvkey: &LrsPVKey<E>,
+    circuit: &LRSCirc<E::ScalarField>,
+    ring: &Vec<E::ScalarField>,
+    message: &str,
+    sign_time: &mut SignTime,
+    rng: &mut R,
+) -> Result<Signature<E>, LrsWitnessError<E::ScalarField>>
 where
     E: Pairing,
     <E as Pairing>::ScalarField: Field + FromStr,
@@ -25,34 +48,50 @@ where
 {
     let start = std::time::Instant::now();
 
-    let rng = &mut ark_std::test_rng();
+    // Reject a wrong witness here rather than producing a proof that does not verify
+    circuit.check_witness(MAX_REPORTED_CONSTRAINTS)?;
+    let signer_index = ring
+        .iter()
+        .skip(1)
+        .position(|member| *member == circuit.commit_witness[0])
+        .ok_or(LrsWitnessError::NotInRing)?
+        + 1;
     let cc_start = std::time::Instant::now();
-    let (cc_proof, mut comm_witness, v) =
-        cc::create_random_proof(circuit.clone(), &lrs_pvkey.crs_cc.pk, rng).unwrap();
+    let (cc_proof, comm_witnesses, vs) = cc::create_proof_with_index(
+        &lrs_pvkey.cc_index,
+        &lrs_pvkey.crs_cc.pk,
+        &circuit.full_assignment().map_err(LrsWitnessError::Synthesis)?,
+        rng,
+    )
+    .map_err(LrsWitnessError::Synthesis)?;
+    let (mut comm_witness, v) = (comm_witnesses[0].clone(), vs[0]);
     sign_time.cc = cc_start.elapsed();
 
     let sma_start = std::time::Instant::now();
-    let comm = sma::commit::<_, E>(&ring, &lrs_pvkey.crs_sma, 1, rng);
-    let sma_proof = sma::set_member_proof_opt(message, &lrs_pvkey.crs_sma, &comm, ring, 1, rng);
+    let comm = sma::commit::<_, E>(ring, &lrs_pvkey.crs_sma, signer_index, rng);
+    let sma_proof =
+        sma::set_member_proof_opt(message, &lrs_pvkey.crs_sma, &comm, ring, signer_index, rng);
     sign_time.sma = sma_start.elapsed();
 
     let link_start = std::time::Instant::now();
     comm_witness.push(comm.r);
     comm_witness.push(v);
 
+    let label = link_label(message, &comm, &sma_proof, &cc_proof.d[0], &circuit.public_inputs);
     let link_proof = PESubspaceSnark::<E>::prove(
         &lrs_pvkey.crs_link.pp,
         &lrs_pvkey.crs_link.ek,
         &comm_witness,
+        &label,
     );
     sign_time.link = link_start.elapsed();
 
     sign_time.sign = start.elapsed();
-    Signature {
+    Ok(Signature {
         sma_comm: comm,
         sma_proof,
         cc_proof,
         link_proof,
-        instance: circuit.instance.clone(),
-    }
+        instance: circuit.public_inputs.clone(),
+    })
 }
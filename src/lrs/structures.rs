@@ -1,34 +1,52 @@
 use crate::cc::Proof;
+use crate::constants::{
+    circuit_name_lrs_a, ioputs_name_lrs_a, LINK_TAG_SIGNAL, NUM_COMMIT_WITNESS_LRS_A, NUM_PUB_IO_LRS_A,
+    PATH_PREFIX_LRS_A,
+};
 use crate::sma::SmaComm;
 use crate::sma::SmaProof;
+use crate::json::{self, JsonPairing};
 use crate::{cc, link, sma};
 use ark_ec::pairing::Pairing;
+use ark_relations::r1cs::SynthesisError;
 use ark_ff::Field;
+#[cfg(feature = "std")]
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "std")]
 use ark_std::fs;
-#[allow(non_snake_case)]
 use serde::Deserialize;
-use serde_json;
+#[cfg(feature = "std")]
 use serde_json::Result as JsonResult;
+#[cfg(feature = "std")]
+use crate::lrs::utils::{parse_r1cs_header, r1cs_section, read_r1cs_header};
+#[cfg(feature = "std")]
+use num_bigint::BigUint;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::fmt::Debug;
-use std::str::FromStr;
+use ark_std::fmt::Debug;
+use ark_std::str::FromStr;
 use ark_std::ops::Div;
+use ark_std::string::{String, ToString};
+use ark_std::vec::Vec;
+use core::time::Duration;
+use serde::Serialize;
 
 #[derive(Debug, Clone)] 
 pub struct SignTime {
-    pub sign: std::time::Duration,
-    pub sma: std::time::Duration,
-    pub cc: std::time::Duration,
-    pub link: std::time::Duration,
+    pub sign: Duration,
+    pub sma: Duration,
+    pub cc: Duration,
+    pub link: Duration,
 }
 
 impl SignTime {
     pub fn new() -> Self {
         SignTime {
-            sign: std::time::Duration::new(0, 0),
-            sma: std::time::Duration::new(0, 0),
-            cc: std::time::Duration::new(0, 0),
-            link: std::time::Duration::new(0, 0),
+            sign: Duration::new(0, 0),
+            sma: Duration::new(0, 0),
+            cc: Duration::new(0, 0),
+            link: Duration::new(0, 0),
         }
     }
     
@@ -51,19 +69,19 @@ impl Div<u32> for SignTime {
 
 #[derive(Debug, Clone)] 
 pub struct VerifyTime {
-    pub verify: std::time::Duration,
-    pub sma: std::time::Duration,
-    pub cc: std::time::Duration,
-    pub link: std::time::Duration,
+    pub verify: Duration,
+    pub sma: Duration,
+    pub cc: Duration,
+    pub link: Duration,
 }
 
 impl VerifyTime {
     pub fn new() -> Self {
         VerifyTime {
-            verify: std::time::Duration::new(0, 0),
-            sma: std::time::Duration::new(0, 0),
-            cc: std::time::Duration::new(0, 0),
-            link: std::time::Duration::new(0, 0),
+            verify: Duration::new(0, 0),
+            sma: Duration::new(0, 0),
+            cc: Duration::new(0, 0),
+            link: Duration::new(0, 0),
         }
     }
 }
@@ -85,40 +103,283 @@ impl Div<u32> for VerifyTime {
 }
 
 
-#[derive(Debug, Clone)] 
+#[derive(Debug, Clone, PartialEq)] 
 pub struct CircDescriptor {
     pub num_pub_io: usize,
     pub num_commit_witness: usize,
     pub ioputs_name: Vec<String>,
     pub path_prefix: String,
     pub circuit_name: String,
+    /// The signals whose `cc::helpers::hash_instance` is the single public input
+    /// `ioputs_name[0]`, in hash order; `None` if the public inputs are not compressed.
+    pub hashed_pub_io: Option<Vec<String>>,
 }
 
-#[derive(Clone)]
+impl CircDescriptor {
+    /// The position of the link tag `LINK_TAG_SIGNAL` among the public inputs a signature
+    /// carries, or `None` if the circuit does not output one.
+    pub fn link_tag_index(&self) -> Option<usize> {
+        let public_inputs = match &self.hashed_pub_io {
+            Some(hashed_pub_io) => &hashed_pub_io[..],
+            None => &self.ioputs_name[..self.num_pub_io],
+        };
+        public_inputs.iter().position(|name| name == LINK_TAG_SIGNAL)
+    }
+
+    /// The descriptor of the lrs_a circuit for a Merkle tree of `depth`, as built by
+    /// `circoms/lrs_a/lrs_a.sh` in `PATH_PREFIX_LRS_A`.
+    pub fn lrs_a(depth: usize) -> CircDescriptor {
+        CircDescriptor {
+            num_pub_io: NUM_PUB_IO_LRS_A,
+            num_commit_witness: NUM_COMMIT_WITNESS_LRS_A,
+            ioputs_name: ioputs_name_lrs_a(depth),
+            path_prefix: PATH_PREFIX_LRS_A.to_string(),
+            circuit_name: circuit_name_lrs_a(depth),
+            hashed_pub_io: None,
+        }
+    }
+
+    /// The descriptor of the circuit `<path_prefix><circuit_name>.r1cs`: its public signals are
+    /// the public outputs then the public inputs, named as in `<circuit_name>.sym`, followed by
+    /// `committed`, which must be private signals, and by the other private inputs.
+    #[cfg(feature = "std")]
+    pub fn infer(path_prefix: &str, circuit_name: &str, committed: &[&str]) -> Result<CircDescriptor, CircLayoutError> {
+        let header = read_r1cs_header(&format!("{}{}.r1cs", path_prefix, circuit_name))?;
+        let num_pub_io = (header.num_pub_out + header.num_pub_in) as usize;
+        let num_inputs = num_pub_io + header.num_prv_in as usize;
+
+        // The wire of every signal, -1 if removed, and the `main.*` name of every input wire
+        let sym_path = format!("{}{}.sym", path_prefix, circuit_name);
+        let sym = fs::read_to_string(&sym_path).map_err(|err| CircLayoutError::Io(sym_path.clone(), err.to_string()))?;
+        let mut wires = HashMap::<&str, isize>::new();
+        let mut input_names = vec![None; num_inputs + 1];
+        for (i, line) in sym.lines().enumerate() {
+            let parts: Vec<&str> = line.split(',').collect();
+            let wire = match parts[..] {
+                [_, wire, _, _] => wire.parse::<isize>().map_err(|_| CircLayoutError::Sym(sym_path.clone(), i + 1))?,
+                _ => return Err(CircLayoutError::Sym(sym_path.clone(), i + 1)),
+            };
+            let name = parts[3];
+            wires.entry(name).or_insert(wire);
+            let is_main_signal = name.strip_prefix("main.").is_some_and(|signal| !signal.contains('.'));
+            if is_main_signal && (1..=num_inputs as isize).contains(&wire) {
+                input_names[wire as usize].get_or_insert(name);
+            }
+        }
+        let input_names = (1..=num_inputs)
+            .map(|wire| input_names[wire].ok_or(CircLayoutError::UnnamedWire(wire)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (i, name) in committed.iter().enumerate() {
+            match wires.get(name) {
+                None => return Err(CircLayoutError::UnknownSignal(name.to_string())),
+                Some(-1) => return Err(CircLayoutError::RemovedSignal(name.to_string())),
+                Some(wire) if *wire >= 1 && *wire as usize <= num_pub_io => {
+                    return Err(CircLayoutError::PublicSignal(name.to_string()))
+                }
+                _ if committed[..i].contains(name) => return Err(CircLayoutError::DuplicateSignal(name.to_string())),
+                _ => {}
+            }
+        }
+
+        let private_inputs = input_names[num_pub_io..].iter().filter(|name| !committed.contains(name));
+        let ioputs_name = input_names[..num_pub_io]
+            .iter()
+            .chain(committed)
+            .chain(private_inputs)
+            .map(|name| name.to_string())
+            .collect();
+        Ok(CircDescriptor {
+            num_pub_io,
+            num_commit_witness: committed.len(),
+            ioputs_name,
+            path_prefix: path_prefix.to_string(),
+            circuit_name: circuit_name.to_string(),
+            hashed_pub_io: None,
+        })
+    }
+
+    /// Reads the descriptor of a circuit from its manifest, e.g. `circoms/lrs/lrs.manifest.json`.
+    /// The circuit files are looked up in the directory of the manifest unless it names another.
+    #[cfg(feature = "std")]
+    pub fn from_manifest(path: &str) -> JsonResult<CircDescriptor> {
+        use serde::de::Error;
+
+        let json_str = fs::read_to_string(path).map_err(serde_json::Error::io)?;
+        let manifest: CircManifest = serde_json::from_str(&json_str)?;
+
+        let mut dir = std::path::Path::new(path).parent().unwrap_or(std::path::Path::new("")).to_path_buf();
+        if let Some(circuit_dir) = &manifest.path {
+            dir.push(circuit_dir);
+        }
+        let dir = dir.to_string_lossy();
+        let path_prefix = if dir.is_empty() { "./".to_string() } else { format!("{}/", dir.trim_end_matches('/')) };
+
+        if manifest.hashed_public.is_some() && manifest.public.len() != 1 {
+            return Err(serde_json::Error::custom("a circuit with `hashed_public` has a single public signal"));
+        }
+        let num_pub_io = manifest.public.len();
+        let num_commit_witness = manifest.committed.len();
+        let ioputs_name = [manifest.public, manifest.committed, manifest.private].concat();
+        if let Some(name) = ioputs_name.iter().enumerate().find_map(|(i, name)| ioputs_name[..i].contains(name).then_some(name)) {
+            return Err(serde_json::Error::custom(format!("the signal {} is listed twice", name)));
+        }
+
+        Ok(CircDescriptor {
+            num_pub_io,
+            num_commit_witness,
+            ioputs_name,
+            path_prefix,
+            circuit_name: manifest.circuit,
+            hashed_pub_io: manifest.hashed_public,
+        })
+    }
+}
+
+/// The manifest of a circom circuit, a JSON file read by `CircDescriptor::from_manifest`.
+/// Signals are named as in the `.sym` file, e.g. `main.sc`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircManifest {
+    /// The name of `<circuit>.sym`, `<circuit>_constraints.json` and `<circuit>_js/`
+    pub circuit: String,
+    /// The directory of the circuit files relative to the manifest, if not the manifest's own
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The public inputs and outputs, in instance order
+    pub public: Vec<String>,
+    /// The witness signals committed to, in commitment order
+    #[serde(default)]
+    pub committed: Vec<String>,
+    /// The other input signals, for reference only
+    #[serde(default)]
+    pub private: Vec<String>,
+    /// See `CircDescriptor::hashed_pub_io`
+    #[serde(default)]
+    pub hashed_public: Option<Vec<String>>,
+}
+
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
 pub struct LrsPVKey<E: Pairing>
 where
     <E as Pairing>::ScalarField: Field + FromStr,
     <E::ScalarField as FromStr>::Err: Debug,
 {
-    pub crs_link: link::LinkEVKey<E::G1Affine, E::G2Affine, E::ScalarField>,
+    pub crs_link: link::LinkEVKey<E::G1Affine, E::G2Affine>,
     pub crs_cc: cc::CcPVKey<E>,
     pub crs_sma: sma::SmaCRS<E>,
+    /// The synthesized circuit, so that signing skips constraint synthesis
+    pub cc_index: cc::ProverIndex<E::ScalarField>,
+    /// Whether the cc instance is the hash of the signature's public inputs
+    pub hashed_instance: bool,
+    /// The position of the link tag in `Signature::instance`, see `CircDescriptor::link_tag_index`
+    pub link_tag_index: Option<usize>,
+}
+
+/// The part of `LrsPVKey` that verifying a signature needs, see `LrsPVKey::vkey`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
+pub struct LrsVKey<E: Pairing> {
+    pub link_pp: link::PP<E::G1Affine, E::G2Affine>,
+    pub link_vk: link::VK<E::G2Affine>,
+    pub cc_vk: cc::VerifyingKey<E>,
+    pub crs_sma: sma::SmaCRS<E>,
+    pub hashed_instance: bool,
+    pub link_tag_index: Option<usize>,
+}
+
+/// Reasons a `LrsPVKey` fails `LrsPVKey::validate`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LrsKeyError {
+    /// The SMA CRS is malformed.
+    Sma(sma::SmaCrsError),
+    /// The cc keys have this many commitment slots instead of one.
+    CommitSlotCount(usize),
+    /// The cc proving and verifying keys commit to (pk, vk) witnesses.
+    CommitWitnessMismatch(usize, usize),
+    /// `comm_wit_abc_query` has (found, expected) elements.
+    CommitQueryLength(usize, usize),
+    /// The link parameters are (rows, cols) but the link matrix is (rows, cols).
+    LinkShape((usize, usize), (usize, usize)),
+    /// The link keys record the digest of another link matrix.
+    LinkMatrixDigest,
+    /// The link `EK`/`VK` vectors do not match the dimensions in the link parameters.
+    LinkKeyShape,
+    /// Column `j` of `P_b` is inconsistent with the link matrix and `C_b`, as (b, j).
+    LinkColumnMismatch(usize, usize),
+}
+
+/// A constraint `A * B = C` of the circom circuit that the witness violates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnsatisfiedConstraint<F> {
+    /// The index of the constraint in `_constraints.json`.
+    pub index: usize,
+    pub a: F,
+    pub b: F,
+    pub c: F,
+    /// The `.sym` names and values of the signals in the constraint, by wire index.
+    pub signals: Vec<(String, F)>,
 }
 
+/// Reasons `LRSCirc::check_witness` rejects the witness.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LrsWitnessError<F> {
+    /// The witness has no value for this wire of a constraint.
+    MissingWire(usize),
+    /// The first failing constraints, and how many constraints fail in total.
+    Unsatisfied(Vec<UnsatisfiedConstraint<F>>, usize),
+    /// The committed witness is not a member of the ring.
+    NotInRing,
+    /// The cc prover failed, e.g. as the proving key is for another circuit.
+    Synthesis(SynthesisError),
+}
+
+/// Reasons `CircDescriptor::infer` fails.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CircLayoutError {
+    /// The file cannot be read, as (path, error).
+    Io(String, String),
+    /// The file is not a circom `.r1cs` file with a header and a constraints section.
+    R1cs(String),
+    /// The `.r1cs` file is over another prime field than the one it is read into.
+    FieldMismatch(String),
+    /// This line of the `.sym` file is not `signal,wire,component,name`, as (path, line number).
+    Sym(String, usize),
+    /// The `.sym` file has no `main.*` signal on this public wire.
+    UnnamedWire(usize),
+    /// The committed signal is not in the `.sym` file.
+    UnknownSignal(String),
+    /// The committed signal has no wire, as circom optimized it away.
+    RemovedSignal(String),
+    /// The committed signal is public.
+    PublicSignal(String),
+    /// The committed signal is listed twice.
+    DuplicateSignal(String),
+}
+
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
 pub struct Signature<E: Pairing> {
     pub sma_comm: SmaComm<E>,
     pub sma_proof: SmaProof<E>,
     pub cc_proof: Proof<E>,
+    #[serde(with = "json::affines")]
     pub link_proof: Vec<E::G1Affine>,
+    /// The public inputs; hashed into the cc instance if `LrsPVKey::hashed_instance`
+    #[serde(with = "json::fields")]
     pub instance: Vec<E::ScalarField>,
 }
 
+#[cfg(feature = "std")]
 #[derive(Debug, Clone, Deserialize)]
 pub struct ConstraintsRaw {
     constraints: Vec<Vec<HashMap<usize, String>>>,
 }
 
 // 顶层结构体（泛型）
+#[cfg(feature = "std")]
 #[derive(Debug, Clone)]
 pub struct Constraints<F>
 where
@@ -128,6 +389,7 @@ where
     pub constraints: Vec<Vec<HashMap<usize, F>>>,
 }
 
+#[cfg(feature = "std")]
 impl<F> Constraints<F>
 where
     F: Field + FromStr,
@@ -137,24 +399,72 @@ where
         let json_str = fs::read_to_string(path).map_err(serde_json::Error::io)?;
         let constraints_raw: ConstraintsRaw = serde_json::from_str(&json_str)?;
 
+        // Coefficients repeat across constraints (e.g. -1), so each one is parsed once
+        let mut coeffs: HashMap<&str, F> = HashMap::new();
         let mut constraints = Vec::new();
         for constraint in &constraints_raw.constraints {
-            let mut a: HashMap<usize, F> = HashMap::new();
-            for term in &constraint[0] {
-                a.insert(term.0.clone(), F::from_str(term.1.as_str()).unwrap());
+            let mut abc = Vec::with_capacity(3);
+            for terms in &constraint[..3] {
+                let mut lc: HashMap<usize, F> = HashMap::with_capacity(terms.len());
+                for (idx, coeff) in terms {
+                    let coeff = *coeffs.entry(coeff).or_insert_with(|| F::from_str(coeff).unwrap());
+                    lc.insert(*idx, coeff);
+                }
+                abc.push(lc);
             }
+            constraints.push(abc);
+        }
 
-            let mut b: HashMap<usize, F> = HashMap::new();
-            for term in &constraint[1] {
-                b.insert(term.0.clone(), F::from_str(term.1.as_str()).unwrap());
-            }
+        Ok(Constraints { constraints })
+    }
 
-            let mut c: HashMap<usize, F> = HashMap::new();
-            for term in &constraint[2] {
-                c.insert(term.0.clone(), F::from_str(term.1.as_str()).unwrap());
-            }
+    /// Reads the constraints from the circom `.r1cs` file at `path`, for circuits compiled
+    /// without `--json`; they are the same as in `_constraints.json`. The file must be over
+    /// the field of `F`.
+    pub fn read_from_r1cs(path: &str) -> Result<Constraints<F>, CircLayoutError> {
+        let data = fs::read(path).map_err(|err| CircLayoutError::Io(path.to_string(), err.to_string()))?;
+        let malformed = || CircLayoutError::R1cs(path.to_string());
+        let header = parse_r1cs_header(&data).ok_or_else(malformed)?;
+        if F::extension_degree() != 1
+            || BigUint::from_bytes_le(&header.prime) != BigUint::from_bytes_le(&F::BasePrimeField::MODULUS.to_bytes_le())
+        {
+            return Err(CircLayoutError::FieldMismatch(path.to_string()));
+        }
+        let section = r1cs_section(&data, 2).ok_or_else(malformed)?;
+        let field_size = header.prime.len();
+        // The counts in the header are not trusted to size allocations: a constraint takes
+        // at least 12 bytes of the section, and a term `4 + field_size`
+        let max_constraints = section.len() / 12;
+        let max_terms = section.len() / (4 + field_size);
+        let bytes_at = |pos: usize, len: usize| section.get(pos..pos + len).ok_or_else(malformed);
+        let u32_at = |pos: usize| Ok::<_, CircLayoutError>(u32::from_le_bytes(bytes_at(pos, 4)?.try_into().unwrap()));
 
-            constraints.push(vec![a, b, c]);
+        // Each constraint is three linear combinations of (wire, little-endian coefficient) terms
+        let mut coeffs: HashMap<&[u8], F> = HashMap::new();
+        let mut constraints = Vec::with_capacity((header.num_constraints as usize).min(max_constraints));
+        let mut pos = 0;
+        for _ in 0..header.num_constraints {
+            let mut abc = Vec::with_capacity(3);
+            for _ in 0..3 {
+                let num_terms = u32_at(pos)? as usize;
+                pos += 4;
+                let mut lc: HashMap<usize, F> = HashMap::with_capacity(num_terms.min(max_terms));
+                for _ in 0..num_terms {
+                    let idx = u32_at(pos)? as usize;
+                    let bytes = bytes_at(pos + 4, field_size)?;
+                    let coeff = match coeffs.get(bytes) {
+                        Some(coeff) => *coeff,
+                        None => {
+                            let coeff = F::from_str(&BigUint::from_bytes_le(bytes).to_string()).map_err(|_| malformed())?;
+                            *coeffs.entry(bytes).or_insert(coeff)
+                        }
+                    };
+                    lc.insert(idx, coeff);
+                    pos += 4 + field_size;
+                }
+                abc.push(lc);
+            }
+            constraints.push(abc);
         }
 
         Ok(Constraints { constraints })
@@ -10,6 +10,7 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::io::BufRead;
 use std::str::FromStr;
+use crate::lrs::structures::CircLayoutError;
 
 pub fn read_witness_file<F>(filename: &str) -> JsonResult<HashMap<usize, F>>
 where
@@ -51,9 +52,10 @@ pub fn read_sym_file(filename: &str) -> JsonResult<HashMap<usize, String>> {
     Ok(sym_data)
 }
 
+/// The linear combination `terms` of wires, where `variables[idx]` is the variable of wire `idx`.
 pub fn parse_linear_comb<F>(
     terms: &HashMap<usize, F>,
-    variables: &HashMap<usize, Variable>,
+    variables: &[Option<Variable>],
 ) -> Result<LinearCombination<F>, SynthesisError>
 where
     F: Field + FromStr,
@@ -66,11 +68,69 @@ where
             ark_relations::r1cs::Variable::Instance(0)
         } else {
             variables
-                .get(var_name)
+                .get(*var_name)
+                .copied()
+                .flatten()
                 .ok_or(SynthesisError::AssignmentMissing)?
-                .clone()
         };
         lc = lc + (coeff.clone(), var);
     }
     Ok(lc)
 }
+
+/// The header section of a circom `.r1cs` file. Wire 0 is the constant `1`, followed by the
+/// public outputs, the public inputs and the private inputs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct R1csHeader {
+    /// The prime of the field, little-endian
+    pub prime: Vec<u8>,
+    pub num_wires: u32,
+    pub num_pub_out: u32,
+    pub num_pub_in: u32,
+    pub num_prv_in: u32,
+    pub num_labels: u64,
+    pub num_constraints: u32,
+}
+
+pub fn read_r1cs_header(filename: &str) -> Result<R1csHeader, CircLayoutError> {
+    let data = std::fs::read(filename).map_err(|err| CircLayoutError::Io(filename.to_string(), err.to_string()))?;
+    parse_r1cs_header(&data).ok_or(CircLayoutError::R1cs(filename.to_string()))
+}
+
+/// The content of the first section of `section_type` in the circom `.r1cs` file `data`.
+pub(crate) fn r1cs_section(data: &[u8], section_type: u32) -> Option<&[u8]> {
+    let u32_at = |pos: usize| Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?));
+    let u64_at = |pos: usize| Some(u64::from_le_bytes(data.get(pos..pos + 8)?.try_into().ok()?));
+    if data.get(..4)? != b"r1cs" {
+        return None;
+    }
+
+    // The sections are (type, size, content), in any order
+    let mut pos = 12;
+    for _ in 0..u32_at(8)? {
+        let size = usize::try_from(u64_at(pos + 4)?).ok()?;
+        if u32_at(pos)? == section_type {
+            return data.get(pos + 12..(pos + 12).checked_add(size)?);
+        }
+        pos = (pos + 12).checked_add(size)?;
+    }
+    None
+}
+
+pub(crate) fn parse_r1cs_header(data: &[u8]) -> Option<R1csHeader> {
+    let header = r1cs_section(data, 1)?;
+    let u32_at = |pos: usize| Some(u32::from_le_bytes(header.get(pos..pos + 4)?.try_into().ok()?));
+    let u64_at = |pos: usize| Some(u64::from_le_bytes(header.get(pos..pos + 8)?.try_into().ok()?));
+    let field_size = u32_at(0)? as usize;
+    let prime = header.get(4..4 + field_size)?.to_vec();
+    let pos = 4 + field_size;
+    Some(R1csHeader {
+        prime,
+        num_wires: u32_at(pos)?,
+        num_pub_out: u32_at(pos + 4)?,
+        num_pub_in: u32_at(pos + 8)?,
+        num_prv_in: u32_at(pos + 12)?,
+        num_labels: u64_at(pos + 16)?,
+        num_constraints: u32_at(pos + 24)?,
+    })
+}
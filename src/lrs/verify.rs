@@ -1,17 +1,41 @@
 use crate::cc;
 use crate::link::PESubspaceSnark;
 use crate::link::SubspaceSnark;
-use crate::lrs::structures::LrsPVKey;
+use crate::link;
+use crate::lrs::structures::{LrsPVKey, LrsVKey};
 use crate::lrs::structures::VerifyTime;
 use crate::lrs::Signature;
 use crate::sma::verify_set_member_proof_opt;
+use crate::sma::{SmaCRS, SmaComm, SmaProof};
 use ark_ec::pairing::Pairing;
 use ark_ec::CurveGroup;
 use ark_ff::Field;
 use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
 use ark_std::fmt::Debug;
 use ark_std::str::FromStr;
-use ark_std::time::Instant;
+use ark_std::vec::Vec;
+use crate::time::Instant;
+
+/// The label the link proof of a signature is bound to: the message, the public instance,
+/// the SMA commitment and proof, and the cc commitment `D` it links, so that it cannot be
+/// replayed in another signature. The rest of the cc proof is left out, so that the proof
+/// can be rerandomized with `cc::rerandomize`, as are the secret SMA openings `r` and `r_b`.
+pub fn link_label<E: Pairing>(
+    message: &str,
+    sma_comm: &SmaComm<E>,
+    sma_proof: &SmaProof<E>,
+    d: &E::G1Affine,
+    instance: &[E::ScalarField],
+) -> Vec<u8> {
+    let mut label = message.as_bytes().to_vec();
+    sma_comm.c_g1.serialize_compressed(&mut label).unwrap();
+    sma_comm.c_b_g2.serialize_compressed(&mut label).unwrap();
+    sma_proof.serialize_compressed(&mut label).unwrap();
+    d.serialize_compressed(&mut label).unwrap();
+    instance.serialize_compressed(&mut label).unwrap();
+    label
+}
 
 pub fn verify<E>(
     lrs_pvkey: &LrsPVKey<E>,
@@ -24,43 +48,121 @@ where
     E: Pairing,
     <E as Pairing>::ScalarField: Field + FromStr,
     <E::ScalarField as FromStr>::Err: Debug,
+{
+    verify_parts(
+        &lrs_pvkey.crs_link.pp,
+        &lrs_pvkey.crs_link.vk,
+        &lrs_pvkey.crs_cc.vk,
+        &lrs_pvkey.crs_sma,
+        lrs_pvkey.hashed_instance,
+        ring,
+        message,
+        signature,
+        verify_time,
+    )
+}
+
+/// `verify` with only the verifier's part of the keys, see `LrsPVKey::vkey`.
+pub fn verify_with_vkey<E>(
+    lrs_vkey: &LrsVKey<E>,
+    ring: &Vec<E::ScalarField>,
+    message: &str,
+    signature: &Signature<E>,
+    verify_time: &mut VerifyTime,
+) -> bool
+where
+    E: Pairing,
+    <E::ScalarField as FromStr>::Err: Debug,
+{
+    verify_parts(
+        &lrs_vkey.link_pp,
+        &lrs_vkey.link_vk,
+        &lrs_vkey.cc_vk,
+        &lrs_vkey.crs_sma,
+        lrs_vkey.hashed_instance,
+        ring,
+        message,
+        signature,
+        verify_time,
+    )
+}
+
+impl<E: Pairing> LrsVKey<E> {
+    /// The link tag of the signature: two signatures by the same signer under the same
+    /// scope carry the same tag. `None` if the circuit has no link tag.
+    pub fn link_tag(&self, signature: &Signature<E>) -> Option<E::ScalarField> {
+        self.link_tag_index.and_then(|i| signature.instance.get(i).copied())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn verify_parts<E>(
+    link_pp: &link::PP<E::G1Affine, E::G2Affine>,
+    link_vk: &link::VK<E::G2Affine>,
+    cc_vk: &cc::VerifyingKey<E>,
+    crs_sma: &SmaCRS<E>,
+    hashed_instance: bool,
+    ring: &Vec<E::ScalarField>,
+    message: &str,
+    signature: &Signature<E>,
+    verify_time: &mut VerifyTime,
+) -> bool
+where
+    E: Pairing,
+    <E::ScalarField as FromStr>::Err: Debug,
 {
     let verify_start = Instant::now();
 
-    let instance = signature
-        .instance
-        .iter()
-        .map(|x| x.into_bigint())
-        .collect::<Vec<_>>();
+    let instance = if hashed_instance {
+        vec![cc::helpers::hash_instance::<E>(&signature.instance).into_bigint()]
+    } else {
+        signature
+            .instance
+            .iter()
+            .map(|x| x.into_bigint())
+            .collect::<Vec<_>>()
+    };
 
     let cc_start = Instant::now();
-    let mut result =
-        cc::verify_proof(&lrs_pvkey.crs_cc.vk, &signature.cc_proof, &instance).unwrap();
+    let pvk = cc::prepare_verifying_key(cc_vk);
+    let mut result = cc::verify_proof_with_prepared_vk(&pvk, &signature.cc_proof, &instance).unwrap_or(false);
     verify_time.cc = cc_start.elapsed();
 
     let sma_start = Instant::now();
-    verify_set_member_proof_opt(
+    result = result
+        && verify_set_member_proof_opt(
+            message,
+            crs_sma,
+            &signature.sma_comm,
+            ring,
+            &signature.sma_proof,
+        );
+    verify_time.sma = sma_start.elapsed();
+
+    // The link proof is over the single cc commitment
+    if signature.cc_proof.d.len() != 1 {
+        return false;
+    }
+    let link_start = Instant::now();
+    let commitments = vec![signature.sma_comm.c_g1.into_affine(), signature.cc_proof.d[0]];
+    let label = link_label(
         message,
-        &lrs_pvkey.crs_sma,
         &signature.sma_comm,
-        &ring,
         &signature.sma_proof,
+        &signature.cc_proof.d[0],
+        &signature.instance,
     );
-    verify_time.sma = sma_start.elapsed();
-
-    let link_start = Instant::now();
-    let commitments = vec![signature.sma_comm.c_g1.into_affine(), signature.cc_proof.d];
     result = result
         && PESubspaceSnark::<E>::verify(
-            &lrs_pvkey.crs_link.pp,
-            &lrs_pvkey.crs_link.vk,
+            link_pp,
+            link_vk,
             &commitments,
             &signature.link_proof,
+            &label,
         );
     verify_time.link = link_start.elapsed();
 
     verify_time.verify = verify_start.elapsed();
 
-    assert!(result, "Verification failed");
     result
 }
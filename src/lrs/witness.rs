@@ -0,0 +1,163 @@
+//! Computes the witness of a circom circuit by running the `<name>_js/<name>.wasm` that
+//! `circom --wasm` writes, in place of `generate_witness.js`.
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::fmt;
+use fnv::FnvHasher;
+use std::hash::Hasher;
+use wasmtime::{Engine, Error, Instance, Linker, Module, Store, TypedFunc, WasmParams, WasmResults};
+
+/// Reasons `WitnessCalculator` fails.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WitnessError {
+    /// The wasm file cannot be read, compiled or instantiated, or traps, with the error message.
+    Wasm(String),
+    /// The circuit is over a field with a modulus other than `F::MODULUS`.
+    FieldMismatch,
+    /// The circuit has no input signal with this name.
+    UnknownInput(String),
+    /// The input signal takes (expected) values but (found) are given, as (name, expected, found).
+    InputLength(String, usize, usize),
+    /// The circuit raised this circom runtime exception, e.g. 4 for a failed `===` or `assert`.
+    Exception(i32),
+}
+
+/// A circom runtime exception, carried out of the wasm through a trap.
+#[derive(Debug)]
+struct Exception(i32);
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "circom exception {}", self.0)
+    }
+}
+
+impl std::error::Error for Exception {}
+
+impl From<Error> for WitnessError {
+    fn from(err: Error) -> Self {
+        match err.downcast_ref::<Exception>() {
+            Some(Exception(code)) => WitnessError::Exception(*code),
+            None => WitnessError::Wasm(err.to_string()),
+        }
+    }
+}
+
+/// An instance of a circom 2 witness calculator.
+pub struct WitnessCalculator {
+    store: Store<()>,
+    instance: Instance,
+    /// The number of 32-bit limbs of a field element
+    n32: u32,
+}
+
+impl WitnessCalculator {
+    pub fn from_file(path: &str) -> Result<Self, WitnessError> {
+        let wasm = std::fs::read(path).map_err(|err| WitnessError::Wasm(format!("{}: {}", path, err)))?;
+        Self::new(&wasm)
+    }
+
+    pub fn new(wasm: &[u8]) -> Result<Self, WitnessError> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, wasm)?;
+        let mut store = Store::new(&engine, ());
+        let mut linker = Linker::<()>::new(&engine);
+        linker
+            .func_wrap("runtime", "exceptionHandler", |code: i32| -> Result<(), Error> {
+                Err(Error::new(Exception(code)))
+            })?
+            .func_wrap("runtime", "printErrorMessage", || {})?
+            .func_wrap("runtime", "writeBufferMessage", || {})?
+            .func_wrap("runtime", "showSharedRWMemory", || {})?;
+        let instance = linker.instantiate(&mut store, &module)?;
+
+        let mut calculator = WitnessCalculator { store, instance, n32: 0 };
+        calculator.n32 = calculator.call::<(), i32>("getFieldNumLen32", ())? as u32;
+        Ok(calculator)
+    }
+
+    fn func<Params, Results>(&mut self, name: &str) -> Result<TypedFunc<Params, Results>, WitnessError>
+    where
+        Params: WasmParams,
+        Results: WasmResults,
+    {
+        Ok(self.instance.get_typed_func(&mut self.store, name)?)
+    }
+
+    fn call<Params, Results>(&mut self, name: &str, params: Params) -> Result<Results, WitnessError>
+    where
+        Params: WasmParams,
+        Results: WasmResults,
+    {
+        Ok(self.func(name)?.call(&mut self.store, params)?)
+    }
+
+    /// The field element in the shared memory, as little-endian 32-bit limbs.
+    fn read_bytes(&mut self) -> Result<Vec<u8>, WitnessError> {
+        let read = self.func::<i32, i32>("readSharedRWMemory")?;
+        let mut bytes = Vec::with_capacity(4 * self.n32 as usize);
+        for i in 0..self.n32 {
+            bytes.extend_from_slice(&(read.call(&mut self.store, i as i32)? as u32).to_le_bytes());
+        }
+        Ok(bytes)
+    }
+
+    fn write_field<F: PrimeField>(&mut self, value: F) -> Result<(), WitnessError> {
+        let write = self.func::<(i32, i32), ()>("writeSharedRWMemory")?;
+        let mut bytes = value.into_bigint().to_bytes_le();
+        bytes.resize(4 * self.n32 as usize, 0);
+        for (i, limb) in bytes.chunks(4).enumerate() {
+            let limb = u32::from_le_bytes(limb.try_into().unwrap());
+            write.call(&mut self.store, (i as i32, limb as i32))?;
+        }
+        Ok(())
+    }
+
+    /// The witness of the circuit for `inputs`, the values of its input signals by name
+    /// (without the `main.` prefix), indexed by wire as in `witness.wtns.json`.
+    pub fn calculate<F: PrimeField>(&mut self, inputs: &[(String, Vec<F>)]) -> Result<Vec<F>, WitnessError> {
+        self.call::<(), ()>("getRawPrime", ())?;
+        let mut modulus = F::MODULUS.to_bytes_le();
+        modulus.resize(4 * self.n32 as usize, 0);
+        if self.read_bytes()? != modulus {
+            return Err(WitnessError::FieldMismatch);
+        }
+
+        self.call::<i32, ()>("init", 1)?;
+        let input_size = self.func::<(i32, i32), i32>("getInputSignalSize")?;
+        let set_input = self.func::<(i32, i32, i32), ()>("setInputSignal")?;
+        for (name, values) in inputs {
+            let (msb, lsb) = fnv(name);
+            let size = input_size
+                .call(&mut self.store, (msb, lsb))
+                .map_err(|_| WitnessError::UnknownInput(name.clone()))?;
+            if size < 0 {
+                return Err(WitnessError::UnknownInput(name.clone()));
+            }
+            if size as usize != values.len() {
+                return Err(WitnessError::InputLength(name.clone(), size as usize, values.len()));
+            }
+            for (i, value) in values.iter().enumerate() {
+                self.write_field(*value)?;
+                set_input.call(&mut self.store, (msb, lsb, i as i32))?;
+            }
+        }
+
+        let witness_size = self.call::<(), i32>("getWitnessSize", ())?;
+        let get_witness = self.func::<i32, ()>("getWitness")?;
+        (0..witness_size)
+            .map(|i| {
+                get_witness.call(&mut self.store, i)?;
+                Ok(F::from_le_bytes_mod_order(&self.read_bytes()?))
+            })
+            .collect()
+    }
+}
+
+/// The halves of the 64-bit FNV-1a hash circom identifies an input signal by.
+fn fnv(name: &str) -> (i32, i32) {
+    let mut hasher = FnvHasher::default();
+    hasher.write(name.as_bytes());
+    let hash = hasher.finish();
+    ((hash >> 32) as i32, hash as i32)
+}
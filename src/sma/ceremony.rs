@@ -0,0 +1,166 @@
+use crate::cc::helpers::hash_to_field;
+use crate::sma::generator::crs_key_gen_with_alpha;
+use crate::sma::SmaCRS;
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{One, Zero};
+use ark_serialize::*;
+use ark_std::rand::RngCore;
+use ark_std::{cfg_iter, UniformRand};
+use ark_std::vec::Vec;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// A public record of one update `alpha -> x * alpha` of the SMA CRS.
+///
+/// `x_g1`/`x_g2` commit to the secret factor, `alpha_g1` is `crs_g1s[1]` after the update,
+/// and `(pok_r, pok_z)` is a Schnorr proof of knowledge of `x` bound to the previous `crs_g1s[1]`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SmaContribution<E: Pairing> {
+    pub alpha_g1: E::G1Affine,
+    pub x_g1: E::G1Affine,
+    pub x_g2: E::G2Affine,
+    pub pok_r: E::G1Affine,
+    pub pok_z: E::ScalarField,
+}
+
+/// The CRS every ceremony starts from, i.e. the one for `alpha = 1`.
+pub fn initial_crs<E: Pairing>(ring_size_max: usize) -> SmaCRS<E> {
+    crs_key_gen_with_alpha(ring_size_max, E::ScalarField::one())
+}
+
+fn pok_challenge<E: Pairing>(
+    prev_alpha_g1: &E::G1Affine,
+    alpha_g1: &E::G1Affine,
+    x_g1: &E::G1Affine,
+    pok_r: &E::G1Affine,
+) -> E::ScalarField {
+    let mut bytes = Vec::new();
+    prev_alpha_g1.serialize_compressed(&mut bytes).unwrap();
+    alpha_g1.serialize_compressed(&mut bytes).unwrap();
+    x_g1.serialize_compressed(&mut bytes).unwrap();
+    pok_r.serialize_compressed(&mut bytes).unwrap();
+    hash_to_field::<E>(bytes)
+}
+
+/// Rerandomizes `crs` by a fresh secret factor `x`, so that the new trapdoor is `x * alpha`.
+/// The factor is dropped on return; the contribution lets anyone check the update.
+pub fn contribute<R: RngCore, E: Pairing>(
+    crs: &SmaCRS<E>,
+    rng: &mut R,
+) -> (SmaCRS<E>, SmaContribution<E>) {
+    let mut x = E::ScalarField::rand(rng);
+    while x.is_zero() {
+        x = E::ScalarField::rand(rng);
+    }
+
+    let x_pows = (0..crs.crs_g1s.len())
+        .scan(E::ScalarField::one(), |state, _| {
+            let current = *state;
+            *state *= x;
+            Some(current)
+        })
+        .collect::<Vec<_>>();
+
+    let crs_g1s = cfg_iter!(crs.crs_g1s)
+        .zip(cfg_iter!(x_pows))
+        .map(|(g, x_i)| *g * x_i)
+        .collect::<Vec<_>>();
+    let crs_g2s = cfg_iter!(crs.crs_g2s)
+        .zip(cfg_iter!(x_pows))
+        .map(|(g, x_i)| *g * x_i)
+        .collect::<Vec<_>>();
+
+    let next = SmaCRS {
+        g1_generator: crs.g1_generator,
+        g2_generator: crs.g2_generator,
+        crs_g1s: E::G1::normalize_batch(&crs_g1s),
+        crs_g2s: E::G2::normalize_batch(&crs_g2s),
+        ring_size_max: crs.ring_size_max,
+    };
+
+    let x_g1 = (crs.g1_generator * x).into_affine();
+    let x_g2 = (crs.g2_generator * x).into_affine();
+    let k = E::ScalarField::rand(rng);
+    let pok_r = (crs.g1_generator * k).into_affine();
+    let c = pok_challenge::<E>(&crs.crs_g1s[1], &next.crs_g1s[1], &x_g1, &pok_r);
+
+    let contribution = SmaContribution {
+        alpha_g1: next.crs_g1s[1],
+        x_g1,
+        x_g2,
+        pok_r,
+        pok_z: k + c * x,
+    };
+
+    (next, contribution)
+}
+
+/// Checks a single contribution on top of the CRS whose `crs_g1s[1]` is `prev_alpha_g1`.
+pub fn verify_contribution<E: Pairing>(
+    g1_generator: E::G1,
+    g2_generator: E::G2,
+    prev_alpha_g1: &E::G1Affine,
+    contribution: &SmaContribution<E>,
+) -> bool {
+    if contribution.x_g1.is_zero() || contribution.alpha_g1.is_zero() {
+        return false;
+    }
+
+    // Proof of knowledge of x
+    let c = pok_challenge::<E>(
+        prev_alpha_g1,
+        &contribution.alpha_g1,
+        &contribution.x_g1,
+        &contribution.pok_r,
+    );
+    if g1_generator * contribution.pok_z != contribution.pok_r + contribution.x_g1 * c {
+        return false;
+    }
+
+    // x_g1 and x_g2 share the same x, and alpha_g1 = x * prev_alpha_g1
+    let same_x = E::multi_pairing(
+        [contribution.x_g1.into_group(), -g1_generator],
+        [g2_generator, contribution.x_g2.into_group()],
+    );
+    let updated = E::multi_pairing(
+        [contribution.alpha_g1.into_group(), -prev_alpha_g1.into_group()],
+        [g2_generator, contribution.x_g2.into_group()],
+    );
+
+    same_x.is_zero() && updated.is_zero()
+}
+
+/// Verifies that `crs` results from applying `contributions` in order to `initial`,
+/// and that both CRSs are well-formed powers of their trapdoor.
+pub fn verify_contributions<E: Pairing>(
+    initial: &SmaCRS<E>,
+    contributions: &[SmaContribution<E>],
+    crs: &SmaCRS<E>,
+) -> bool {
+    if initial.ring_size_max != crs.ring_size_max
+        || initial.g1_generator != crs.g1_generator
+        || initial.g2_generator != crs.g2_generator
+    {
+        return false;
+    }
+    if initial.validate().is_err() || crs.validate().is_err() {
+        return false;
+    }
+
+    let mut prev_alpha_g1 = initial.crs_g1s[1];
+    for contribution in contributions {
+        if !verify_contribution(
+            crs.g1_generator,
+            crs.g2_generator,
+            &prev_alpha_g1,
+            contribution,
+        ) {
+            return false;
+        }
+        prev_alpha_g1 = contribution.alpha_g1;
+    }
+
+    prev_alpha_g1 == crs.crs_g1s[1]
+}
@@ -0,0 +1,33 @@
+/// Reasons a `SmaCRS` fails `SmaCRS::validate`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SmaCrsError {
+    /// `ring_size_max` is zero.
+    EmptyCrs,
+    /// `crs_g1s` has (found, expected) elements.
+    G1LengthMismatch(usize, usize),
+    /// `crs_g2s` has (found, expected) elements.
+    G2LengthMismatch(usize, usize),
+    /// `crs_g1s[0]` is not `g1_generator`.
+    G1GeneratorMismatch,
+    /// `crs_g2s[0]` is not `g2_generator`.
+    G2GeneratorMismatch,
+    /// The element at this index, `ring_size_max + 1`, should be removed but is not the identity.
+    GapNotZero(usize),
+    /// `crs_g1s[1]` is the identity, i.e. `alpha = 0`.
+    ZeroTrapdoor,
+    /// `crs_g1s[1]` and `crs_g2s[1]` encode different alphas.
+    TrapdoorMismatch,
+    /// `crs_g1s[i]` is not the expected power of alpha.
+    G1PowerMismatch(usize),
+    /// `crs_g2s[i]` is not the expected power of alpha.
+    G2PowerMismatch(usize),
+}
+
+/// Reasons `set_non_member_proof` fails.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SmaError {
+    /// The set has (found) elements but the CRS allows at most (max).
+    SetTooLarge(usize, usize),
+    /// The committed value is in the set.
+    ValueInSet,
+}
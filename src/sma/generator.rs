@@ -1,12 +1,18 @@
-use ark_ec::{pairing::Pairing, Group};
-use ark_ec::{scalar_mul::fixed_base::FixedBase, AffineRepr, CurveGroup};
+use crate::cc::helpers::hash_to_field;
+use crate::sma::SmaCrsError;
+use ark_ec::pairing::Pairing;
+use ark_ec::Group;
+use ark_ec::{scalar_mul::fixed_base::FixedBase, AffineRepr, CurveGroup, VariableBaseMSM};
 use ark_ff::Field;
 use ark_ff::PrimeField;
-use ark_ff::Zero;
+use ark_ff::{One, Zero};
+use ark_serialize::CanonicalSerialize;
 use ark_std::rand::RngCore;
 use ark_std::UniformRand;
 
 use super::SmaCRS;
+use ark_std::string::String;
+use ark_std::vec::Vec;
 
 #[allow(non_snake_case)]
 pub fn crs_key_gen<R: RngCore, E: Pairing>(
@@ -14,10 +20,15 @@ pub fn crs_key_gen<R: RngCore, E: Pairing>(
     ring_size_max: usize,
     rng: &mut R,
 ) -> SmaCRS<E> {
+    let alpha = E::ScalarField::rand(rng);
+    crs_key_gen_with_alpha(ring_size_max, alpha)
+}
+
+/// Create the SMA CRS for a given trapdoor `alpha`.
+pub fn crs_key_gen_with_alpha<E: Pairing>(ring_size_max: usize, alpha: E::ScalarField) -> SmaCRS<E> {
     let mut crs_g1s = Vec::with_capacity(2 * ring_size_max + 1);
     let mut crs_g2s = Vec::with_capacity(ring_size_max + 1);
 
-    let alpha = E::ScalarField::rand(rng);
     let alpha_exps = (0..(ring_size_max * 2 + 1))
         .map(|i| alpha.pow(&[i as u64]))
         .collect::<Vec<_>>();
@@ -71,3 +82,113 @@ pub fn crs_key_gen<R: RngCore, E: Pairing>(
         ring_size_max,
     }
 }
+
+impl<E: Pairing> SmaCRS<E> {
+    /// Checks that `crs_g1s` and `crs_g2s` are consecutive powers of the same non-zero alpha,
+    /// with `crs_g1s[ring_size_max + 1]` zeroed.
+    ///
+    /// The power relations are first checked with one random linear combination per group;
+    /// on failure the offending index is located with individual pairings.
+    pub fn validate(&self) -> Result<(), SmaCrsError> {
+        let n = self.ring_size_max;
+        if n == 0 {
+            return Err(SmaCrsError::EmptyCrs);
+        }
+        if self.crs_g1s.len() != 2 * n + 1 {
+            return Err(SmaCrsError::G1LengthMismatch(self.crs_g1s.len(), 2 * n + 1));
+        }
+        if self.crs_g2s.len() != n + 1 {
+            return Err(SmaCrsError::G2LengthMismatch(self.crs_g2s.len(), n + 1));
+        }
+        if self.crs_g1s[0] != self.g1_generator.into_affine() {
+            return Err(SmaCrsError::G1GeneratorMismatch);
+        }
+        if self.crs_g2s[0] != self.g2_generator.into_affine() {
+            return Err(SmaCrsError::G2GeneratorMismatch);
+        }
+        if !self.crs_g1s[n + 1].is_zero() {
+            return Err(SmaCrsError::GapNotZero(n + 1));
+        }
+        if self.crs_g1s[1].is_zero() {
+            return Err(SmaCrsError::ZeroTrapdoor);
+        }
+
+        // crs_g1s[1] and crs_g2s[1] encode the same alpha
+        if !E::multi_pairing(
+            [self.crs_g1s[1].into_group(), -self.crs_g1s[0].into_group()],
+            [self.crs_g2s[0], self.crs_g2s[1]],
+        )
+        .is_zero()
+        {
+            return Err(SmaCrsError::TrapdoorMismatch);
+        }
+
+        let mut bytes = Vec::new();
+        self.serialize_compressed(&mut bytes).unwrap();
+        let rho = hash_to_field::<E>(bytes);
+        let rhos = (0..2 * n)
+            .scan(E::ScalarField::one(), |state, _| {
+                let current = *state;
+                *state *= rho;
+                Some(current)
+            })
+            .collect::<Vec<_>>();
+
+        // crs_g2s[i] = alpha * crs_g2s[i - 1]
+        let g2_step = |lower: E::G2, upper: E::G2| {
+            E::multi_pairing(
+                [self.crs_g1s[0].into_group(), -self.crs_g1s[1].into_group()],
+                [upper, lower],
+            )
+            .is_zero()
+        };
+        if !g2_step(
+            E::G2::msm(&self.crs_g2s[..n], &rhos[..n]).unwrap(),
+            E::G2::msm(&self.crs_g2s[1..], &rhos[..n]).unwrap(),
+        ) {
+            let i = (1..=n)
+                .find(|i| !g2_step(self.crs_g2s[*i - 1].into(), self.crs_g2s[*i].into()))
+                .unwrap();
+            return Err(SmaCrsError::G2PowerMismatch(i));
+        }
+
+        // crs_g1s[i] = alpha * crs_g1s[i - 1], except crs_g1s[n + 2] = alpha^2 * crs_g1s[n]
+        // which bridges the gap
+        let g1_steps = (1..=2 * n).filter(|i| *i != n + 1).collect::<Vec<_>>();
+        let rhos = &rhos[..g1_steps.len()];
+        let (bridged, plain): (Vec<_>, Vec<_>) = g1_steps
+            .iter()
+            .zip(rhos)
+            .partition(|(i, _)| **i == n + 2);
+        let g1_check = |steps: &[(&usize, &E::ScalarField)], predecessor: usize| {
+            let scalars = steps.iter().map(|(_, rho)| **rho).collect::<Vec<_>>();
+            let upper = steps.iter().map(|(i, _)| self.crs_g1s[**i]).collect::<Vec<_>>();
+            let lower = steps.iter().map(|(i, _)| self.crs_g1s[**i - predecessor]).collect::<Vec<_>>();
+            (
+                E::G1::msm(&upper, &scalars).unwrap(),
+                E::G1::msm(&lower, &scalars).unwrap(),
+            )
+        };
+        let (upper_1, lower_1) = g1_check(&plain, 1);
+        let (upper_2, lower_2) = g1_check(&bridged, 2);
+        let g1_step = |i: usize| {
+            let (lower, g2) = if i == n + 2 { (n, 2) } else { (i - 1, 1) };
+            E::multi_pairing(
+                [self.crs_g1s[i].into_group(), -self.crs_g1s[lower].into_group()],
+                [self.crs_g2s[0], self.crs_g2s[g2]],
+            )
+            .is_zero()
+        };
+        if !E::multi_pairing(
+            [upper_1 + upper_2, -lower_1, -lower_2],
+            [self.crs_g2s[0], self.crs_g2s[1], self.crs_g2s.get(2).copied().unwrap_or_default()],
+        )
+        .is_zero()
+        {
+            let i = g1_steps.into_iter().find(|i| !g1_step(*i)).unwrap();
+            return Err(SmaCrsError::G1PowerMismatch(i));
+        }
+
+        Ok(())
+    }
+}
@@ -1,14 +1,22 @@
+pub mod error;
 pub mod structures;
 pub mod generator;
 pub mod prover;
 pub mod verifier;
 pub mod utils;
+pub mod ceremony;
+#[cfg(feature = "std")]
+pub mod ptau;
 
+pub use error::*;
 pub use structures::*;
 pub use generator::*;
 pub use prover::*;
 pub use verifier::*;
 pub use utils::*;
+pub use ceremony::*;
+#[cfg(feature = "std")]
+pub use ptau::*;
 
 #[test]
 fn test_set_member_proof_opt() {
@@ -43,13 +51,13 @@ fn test_set_member_proof_opt() {
     );
 
     let t1 = Instant::now();
-    verify_set_member_proof_opt::< Bn254>(
+    assert!(verify_set_member_proof_opt::< Bn254>(
         msg,
         &sma_crs,
         &sma_comm,
         &ring,
         &sma_proof,
-    );
+    ));
     let t2 = Instant::now();
     records.insert(
         "Verify proof (More than all)",
@@ -65,3 +73,223 @@ fn test_set_member_proof_opt() {
         println!("{:<30}: {}", key, value);
     }
 }
+
+#[cfg(test)]
+fn non_member_prove_and_verify(set_size: usize) {
+    use ark_bn254::Bn254;
+    use ark_std::UniformRand;
+
+    let rng = &mut ark_std::test_rng();
+    let ring_size_max = 1 << 4;
+    let msg = "test message";
+
+    let sma_crs = crs_key_gen::<_, Bn254>("128".to_string(), ring_size_max, rng);
+    let ring = ring_gen::<_, Bn254>(ring_size_max, 1, rng);
+    let comm = commit::<_, Bn254>(&ring, &sma_crs, 1, rng);
+    let phi = ring[1];
+
+    let set = (0..set_size)
+        .map(|_| ark_bn254::Fr::rand(rng))
+        .collect::<Vec<_>>();
+
+    let proof = set_non_member_proof(msg, &sma_crs, &comm, phi, &set, rng).unwrap();
+    assert!(verify_set_non_member_proof(msg, &sma_crs, &comm, &set, &proof));
+    assert!(!verify_set_non_member_proof("other message", &sma_crs, &comm, &set, &proof));
+
+    // The proof decodes from JSON and re-encodes to the same JSON
+    let json = serde_json::to_string(&proof).unwrap();
+    let decoded: SmaNonMemProof<Bn254> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, proof);
+    assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+
+    // A proof for one revocation list must not verify against another
+    let mut other_set = set.clone();
+    other_set.push(ark_bn254::Fr::rand(rng));
+    other_set.truncate(ring_size_max);
+    if other_set != set {
+        assert!(!verify_set_non_member_proof(msg, &sma_crs, &comm, &other_set, &proof));
+    }
+}
+
+#[test]
+fn test_set_non_member_proof_empty_set() {
+    non_member_prove_and_verify(0);
+}
+
+#[test]
+fn test_set_non_member_proof_single_element() {
+    non_member_prove_and_verify(1);
+}
+
+#[test]
+fn test_set_non_member_proof_full_set() {
+    non_member_prove_and_verify(1 << 4);
+}
+
+#[test]
+fn test_set_non_member_proof_rejects_member() {
+    use ark_bn254::Bn254;
+
+    let rng = &mut ark_std::test_rng();
+    let ring_size_max = 1 << 4;
+
+    let sma_crs = crs_key_gen::<_, Bn254>("128".to_string(), ring_size_max, rng);
+    let ring = ring_gen::<_, Bn254>(ring_size_max, 1, rng);
+    let comm = commit::<_, Bn254>(&ring, &sma_crs, 1, rng);
+
+    // The revocation list contains the signer's phi
+    let set = ring[1..].to_vec();
+    let result = set_non_member_proof("test message", &sma_crs, &comm, ring[1], &set, rng);
+    assert_eq!(result, Err(SmaError::ValueInSet));
+
+    let too_large = vec![ring[2]; ring_size_max + 1];
+    let result = set_non_member_proof("test message", &sma_crs, &comm, ring[1], &too_large, rng);
+    assert_eq!(result, Err(SmaError::SetTooLarge(ring_size_max + 1, ring_size_max)));
+}
+
+#[test]
+fn test_crs_ceremony() {
+    use ark_bn254::Bn254;
+
+    let rng = &mut ark_std::test_rng();
+    let ring_size_max = 1 << 4;
+
+    let initial = initial_crs::<Bn254>(ring_size_max);
+    let mut crs = initial.clone();
+    let mut contributions = Vec::new();
+    for _ in 0..3 {
+        let (next, contribution) = contribute(&crs, rng);
+        crs = next;
+        contributions.push(contribution);
+    }
+    assert!(verify_contributions(&initial, &contributions, &crs));
+
+    // The ceremony output is a usable SMA CRS
+    let ring = ring_gen::<_, Bn254>(ring_size_max, 1, rng);
+    let comm = commit::<_, Bn254>(&ring, &crs, 1, rng);
+    let proof = set_member_proof_opt("test message", &crs, &comm, &ring, 1, rng);
+    assert!(verify_set_member_proof_opt("test message", &crs, &comm, &ring, &proof));
+    assert!(!verify_set_member_proof_opt("another message", &crs, &comm, &ring, &proof));
+    assert!(!verify_set_member_proof_opt("test message", &crs, &comm, &vec![], &proof));
+
+    // Dropping or reordering contributions breaks the chain
+    assert!(!verify_contributions(&initial, &contributions[..2], &crs));
+    contributions.swap(0, 1);
+    assert!(!verify_contributions(&initial, &contributions, &crs));
+    contributions.swap(0, 1);
+
+    // A contribution without knowledge of its factor is rejected
+    let mut forged = contributions.clone();
+    forged[1].pok_z += ark_bn254::Fr::from(1u64);
+    assert!(!verify_contributions(&initial, &forged, &crs));
+
+    // A CRS that is not a power sequence is rejected
+    let mut broken = crs.clone();
+    broken.crs_g1s[3] = broken.crs_g1s[2];
+    assert!(!verify_contributions(&initial, &contributions, &broken));
+}
+
+#[cfg(test)]
+fn write_test_ptau(path: &std::path::Path, power: u32, tau: ark_bn254::Fr) {
+    use ark_bn254::{Fq, G1Projective, G2Projective};
+    use ark_ec::{AffineRepr, CurveGroup, Group};
+    use ark_ff::{BigInteger, PrimeField};
+    use std::io::Write;
+
+    // snarkjs stores base field elements in Montgomery form, little endian
+    let lem = |f: &Fq| f.0.to_bytes_le();
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&32u32.to_le_bytes());
+    header.extend_from_slice(&Fq::MODULUS.to_bytes_le());
+    header.extend_from_slice(&power.to_le_bytes());
+    header.extend_from_slice(&power.to_le_bytes());
+
+    let mut tau_g1 = Vec::new();
+    let mut tau_i = ark_bn254::Fr::from(1u64);
+    for _ in 0..(1usize << (power + 1)) - 1 {
+        let p = (G1Projective::generator() * tau_i).into_affine();
+        tau_g1.extend(lem(p.x().unwrap()));
+        tau_g1.extend(lem(p.y().unwrap()));
+        tau_i *= tau;
+    }
+
+    let mut tau_g2 = Vec::new();
+    let mut tau_i = ark_bn254::Fr::from(1u64);
+    for _ in 0..(1usize << power) {
+        let p = (G2Projective::generator() * tau_i).into_affine();
+        for c in [&p.x().unwrap().c0, &p.x().unwrap().c1, &p.y().unwrap().c0, &p.y().unwrap().c1] {
+            tau_g2.extend(lem(c));
+        }
+        tau_i *= tau;
+    }
+
+    let mut file = std::fs::File::create(path).unwrap();
+    file.write_all(b"ptau").unwrap();
+    file.write_all(&1u32.to_le_bytes()).unwrap();
+    file.write_all(&3u32.to_le_bytes()).unwrap();
+    for (id, section) in [(1u32, header), (2, tau_g1), (3, tau_g2)] {
+        file.write_all(&id.to_le_bytes()).unwrap();
+        file.write_all(&(section.len() as u64).to_le_bytes()).unwrap();
+        file.write_all(&section).unwrap();
+    }
+}
+
+#[test]
+fn test_crs_from_ptau() {
+    use ark_bn254::Bn254;
+    use ark_std::UniformRand;
+
+    let rng = &mut ark_std::test_rng();
+    let tau = ark_bn254::Fr::rand(rng);
+    let path = std::env::temp_dir().join("lrs_v2_test_crs_from_ptau.ptau");
+    write_test_ptau(&path, 3, tau);
+
+    let crs = crs_from_ptau::<Bn254>(path.to_str().unwrap(), 7).unwrap();
+    assert_eq!(crs, crs_key_gen_with_alpha::<Bn254>(7, tau));
+    assert_eq!(crs.validate(), Ok(()));
+
+    // A power-3 transcript holds 15 powers in G1, enough for rings of at most 7
+    assert!(crs_from_ptau::<Bn254>(path.to_str().unwrap(), 8).is_err());
+    assert!(crs_from_ptau::<ark_bls12_381::Bls12_381>(path.to_str().unwrap(), 4).is_err());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_sma_crs_validate() {
+    use ark_bn254::Bn254;
+    use ark_ec::{AffineRepr, CurveGroup};
+
+    let rng = &mut ark_std::test_rng();
+    let ring_size_max = 1 << 4;
+    let crs = crs_key_gen::<_, Bn254>("128".to_string(), ring_size_max, rng);
+    assert_eq!(crs.validate(), Ok(()));
+
+    let mut bad = crs.clone();
+    bad.crs_g1s[5] = (bad.crs_g1s[5] + bad.crs_g1s[0]).into_affine();
+    assert_eq!(bad.validate(), Err(SmaCrsError::G1PowerMismatch(5)));
+
+    let mut bad = crs.clone();
+    bad.crs_g1s[ring_size_max + 2] = bad.crs_g1s[ring_size_max + 3];
+    assert_eq!(bad.validate(), Err(SmaCrsError::G1PowerMismatch(ring_size_max + 2)));
+
+    let mut bad = crs.clone();
+    bad.crs_g2s[3] = bad.crs_g2s[2];
+    assert_eq!(bad.validate(), Err(SmaCrsError::G2PowerMismatch(3)));
+
+    let mut bad = crs.clone();
+    bad.crs_g1s[ring_size_max + 1] = bad.crs_g1s[0];
+    assert_eq!(bad.validate(), Err(SmaCrsError::GapNotZero(ring_size_max + 1)));
+
+    let mut bad = crs.clone();
+    bad.crs_g2s.pop();
+    assert_eq!(
+        bad.validate(),
+        Err(SmaCrsError::G2LengthMismatch(ring_size_max, ring_size_max + 1))
+    );
+
+    let mut bad = crs.clone();
+    bad.crs_g1s[0] = <Bn254 as ark_ec::pairing::Pairing>::G1Affine::zero();
+    assert_eq!(bad.validate(), Err(SmaCrsError::G1GeneratorMismatch));
+}
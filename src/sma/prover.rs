@@ -1,19 +1,19 @@
 use crate::cc::helpers::hash_to_field;
-use crate::sma::utils::kzg_evaluate;
-use crate::sma::{SmaCRS, SmaProof};
+use crate::sma::SmaError;
+use crate::sma::utils::{kzg_evaluate, non_member_challenge, vanishing_poly};
+use crate::sma::{SmaCRS, SmaNonMemProof, SmaProof};
 use ark_ec::VariableBaseMSM;
 use ark_ec::{pairing::Pairing, Group};
-use ark_ff::PrimeField;
+use ark_ff::{Field, PrimeField};
 use ark_ff::{One, Zero};
 use ark_serialize::CanonicalSerialize;
 use ark_std::cfg_iter;
 use ark_std::fmt::Debug;
 use ark_std::rand::RngCore;
 use ark_std::str::FromStr;
-use ark_std::time::Instant;
 use ark_std::UniformRand;
-use rayon::iter::IntoParallelRefIterator;
-use std::{collections::HashMap, ops::Mul};
+use ark_std::{collections::BTreeMap, ops::Mul, vec::Vec};
+use crate::time::Instant;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
@@ -35,7 +35,7 @@ where
     let ring_size_real = ring.len() - 1; // omit the 0-th index
     let ring_size_max = sma_crs.ring_size_max; // max size of the ring
 
-    let mut records = HashMap::new();
+    let mut records = BTreeMap::new();
 
     let start_randomness = Instant::now();
     let r_s = E::ScalarField::rand(rng);
@@ -402,3 +402,92 @@ where
         pi_kzg: pi_kzg.into(),
     }
 }
+
+/// Proves that the value `phi` committed in `comm.c_g1 = r * g + phi * g_1` is not in `set`.
+///
+/// With `Z_S(X) = q(X) (X - phi) + d` and `d != 0`, the prover publishes the blinded quotient
+/// `W = [q(alpha) + t]_1` together with commitments `C_u = [u]_{g_1} + [r_u]_g` (`u = t * phi`)
+/// and `C_d = [d]_{g_1} + [r_d]_g`, and runs a Fiat-Shamir sigma protocol showing
+///   1. `c_g1 = r * g + phi * g_1`,
+///   2. `e([Z_S(alpha)]_1, h) - e(W, [alpha]_2) = -phi * e(W, h) - t * e(g_1, h) + (u + d) * e(g, h)`,
+///   3. `C_u = u * g_1 + r_u * g = t * c_g1 + r_tc * g`,
+///   4. `C_d = d * g_1 + r_d * g` and `g_1 = d^{-1} * C_d + s * g`, so that `d != 0`.
+#[allow(non_snake_case)]
+pub fn set_non_member_proof<R: RngCore, E: Pairing>(
+    message: &str,
+    sma_crs: &SmaCRS<E>,
+    comm: &SmaComm<E>,
+    phi: E::ScalarField,
+    set: &[E::ScalarField],
+    rng: &mut R,
+) -> Result<SmaNonMemProof<E>, SmaError> {
+    if set.len() > sma_crs.ring_size_max {
+        return Err(SmaError::SetTooLarge(set.len(), sma_crs.ring_size_max));
+    }
+
+    let g = sma_crs.g1_generator;
+    let g_1 = E::G1::from(sma_crs.crs_g1s[1]);
+
+    let z_s = vanishing_poly::<E>(set);
+    let d = kzg_evaluate::<E>(&z_s, phi);
+    let d_inv = d.inverse().ok_or(SmaError::ValueInSet)?;
+
+    // q(X) = (Z_S(X) - d) / (X - phi), by synthetic division from the leading coefficient
+    let mut q = vec![E::ScalarField::zero(); set.len()];
+    let mut carry = E::ScalarField::zero();
+    for i in (0..set.len()).rev() {
+        carry = z_s[i + 1] + carry * phi;
+        q[i] = carry;
+    }
+
+    let acc = E::G1::msm(&sma_crs.crs_g1s[..z_s.len()], &z_s).unwrap();
+    let w = E::G1::msm(&sma_crs.crs_g1s[..q.len()], &q).unwrap();
+
+    let t = E::ScalarField::rand(rng);
+    let r_u = E::ScalarField::rand(rng);
+    let r_d = E::ScalarField::rand(rng);
+    let u = t * phi;
+    let r_tc = r_u - t * comm.r;
+    let s = -d_inv * r_d;
+
+    let w_g1 = w + g * t;
+    let c_u_g1 = g_1 * u + g * r_u;
+    let c_d_g1 = g_1 * d + g * r_d;
+
+    let gt = E::pairing(g, sma_crs.g2_generator);
+    let b_w = E::pairing(w_g1, sma_crs.g2_generator);
+    let b_g_1 = E::pairing(g_1, sma_crs.g2_generator);
+
+    let [k_phi, k_r, k_t, k_u, k_r_u, k_r_tc, k_d, k_r_d, k_d_inv, k_s] =
+        [(); 10].map(|_| E::ScalarField::rand(rng));
+
+    let r_1 = g * k_r + g_1 * k_phi;
+    let r_2 = gt * (k_u + k_d) - b_w * k_phi - b_g_1 * k_t;
+    let r_3 = g_1 * k_u + g * k_r_u;
+    let r_4 = comm.c_g1 * k_t + g * k_r_tc;
+    let r_5 = g_1 * k_d + g * k_r_d;
+    let r_6 = c_d_g1 * k_d_inv + g * k_s;
+
+    let challenge = non_member_challenge::<E>(
+        message,
+        &[comm.c_g1, acc, w_g1, c_u_g1, c_d_g1, r_1, r_3, r_4, r_5, r_6],
+        &r_2,
+    );
+
+    Ok(SmaNonMemProof {
+        w_g1: w_g1.into(),
+        c_u_g1: c_u_g1.into(),
+        c_d_g1: c_d_g1.into(),
+        challenge,
+        z_phi: k_phi + challenge * phi,
+        z_r: k_r + challenge * comm.r,
+        z_t: k_t + challenge * t,
+        z_u: k_u + challenge * u,
+        z_r_u: k_r_u + challenge * r_u,
+        z_r_tc: k_r_tc + challenge * r_tc,
+        z_d: k_d + challenge * d,
+        z_r_d: k_r_d + challenge * r_d,
+        z_d_inv: k_d_inv + challenge * d_inv,
+        z_s: k_s + challenge * s,
+    })
+}
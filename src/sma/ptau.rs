@@ -0,0 +1,175 @@
+use crate::sma::SmaCRS;
+use ark_ec::pairing::Pairing;
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_std::collections::HashMap;
+use ark_std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::fs::File;
+
+/// snarkjs `.ptau` section ids
+const PTAU_SECTION_HEADER: u32 = 1;
+const PTAU_SECTION_TAU_G1: u32 = 2;
+const PTAU_SECTION_TAU_G2: u32 = 3;
+
+/// Pairings whose points can be decoded from a snarkjs `.ptau` transcript.
+pub trait PtauPairing: Pairing {
+    fn g1_from_lem(bytes: &[u8]) -> Option<Self::G1Affine>;
+    fn g2_from_lem(bytes: &[u8]) -> Option<Self::G2Affine>;
+    fn base_modulus_le() -> Vec<u8>;
+}
+
+impl PtauPairing for ark_bn254::Bn254 {
+    fn g1_from_lem(bytes: &[u8]) -> Option<Self::G1Affine> {
+        affine_from_lem::<ark_bn254::g1::Config>(bytes)
+    }
+    fn g2_from_lem(bytes: &[u8]) -> Option<Self::G2Affine> {
+        affine_from_lem::<ark_bn254::g2::Config>(bytes)
+    }
+    fn base_modulus_le() -> Vec<u8> {
+        ark_bn254::Fq::MODULUS.to_bytes_le()
+    }
+}
+
+impl PtauPairing for ark_bls12_381::Bls12_381 {
+    fn g1_from_lem(bytes: &[u8]) -> Option<Self::G1Affine> {
+        affine_from_lem::<ark_bls12_381::g1::Config>(bytes)
+    }
+    fn g2_from_lem(bytes: &[u8]) -> Option<Self::G2Affine> {
+        affine_from_lem::<ark_bls12_381::g2::Config>(bytes)
+    }
+    fn base_modulus_le() -> Vec<u8> {
+        ark_bls12_381::Fq::MODULUS.to_bytes_le()
+    }
+}
+
+/// Decodes a point stored as snarkjs does: uncompressed `x || y`, every base prime field
+/// element little endian in Montgomery form, and the point at infinity as all zeros.
+fn affine_from_lem<P: SWCurveConfig>(bytes: &[u8]) -> Option<Affine<P>> {
+    type Fq<P> = <<P as ark_ec::CurveConfig>::BaseField as Field>::BasePrimeField;
+
+    if bytes.iter().all(|b| *b == 0) {
+        return Some(Affine::<P>::zero());
+    }
+
+    let degree = P::BaseField::extension_degree() as usize;
+    let n8 = bytes.len() / (2 * degree);
+    let r_inv = Fq::<P>::from(2u64).pow([8 * n8 as u64]).inverse()?;
+    let coords = bytes
+        .chunks(n8)
+        .map(|chunk| Fq::<P>::from_le_bytes_mod_order(chunk) * r_inv)
+        .collect::<Vec<_>>();
+
+    let x = P::BaseField::from_base_prime_field_elems(&coords[..degree])?;
+    let y = P::BaseField::from_base_prime_field_elems(&coords[degree..])?;
+    let point = Affine::<P>::new_unchecked(x, y);
+    if point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve() {
+        Some(point)
+    } else {
+        None
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_points<R: Read + Seek, T>(
+    reader: &mut R,
+    offset: u64,
+    count: usize,
+    point_size: usize,
+    decode: impl Fn(&[u8]) -> Option<T>,
+) -> io::Result<Vec<T>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0u8; point_size];
+    (0..count)
+        .map(|i| {
+            reader.read_exact(&mut buf)?;
+            decode(&buf).ok_or_else(|| invalid_data(format!("invalid curve point #{}", i)))
+        })
+        .collect()
+}
+
+/// Builds the SMA CRS from the `tauG1`/`tauG2` sections of a snarkjs powers-of-tau file,
+/// with `tau` as the SMA trapdoor and `crs_g1s[ring_size_max + 1]` removed.
+///
+/// The transcript must hold at least `2 * ring_size_max + 1` powers in G1, i.e. a `.ptau`
+/// of power `log2(ring_size_max) + 1` or more.
+pub fn crs_from_ptau<E: PtauPairing>(path: &str, ring_size_max: usize) -> io::Result<SmaCRS<E>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != b"ptau" {
+        return Err(invalid_data(format!("{} is not a .ptau file", path)));
+    }
+    let _version = read_u32(&mut reader)?;
+    let num_sections = read_u32(&mut reader)?;
+
+    let mut sections = HashMap::new();
+    for _ in 0..num_sections {
+        let section_type = read_u32(&mut reader)?;
+        let section_size = read_u64(&mut reader)?;
+        let offset = reader.stream_position()?;
+        sections.insert(section_type, (offset, section_size));
+        reader.seek(SeekFrom::Current(section_size as i64))?;
+    }
+    let section = |id: u32| {
+        sections
+            .get(&id)
+            .copied()
+            .ok_or_else(|| invalid_data(format!("missing .ptau section {}", id)))
+    };
+
+    let (header_offset, _) = section(PTAU_SECTION_HEADER)?;
+    reader.seek(SeekFrom::Start(header_offset))?;
+    let n8 = read_u32(&mut reader)? as usize;
+    let mut q = vec![0u8; n8];
+    reader.read_exact(&mut q)?;
+    let power = read_u32(&mut reader)?;
+
+    let mut modulus = E::base_modulus_le();
+    modulus.resize(n8, 0);
+    if q != modulus {
+        return Err(invalid_data("the .ptau curve does not match the pairing".to_string()));
+    }
+
+    let num_g1 = (1usize << (power + 1)) - 1;
+    let num_g2 = 1usize << power;
+    if 2 * ring_size_max + 1 > num_g1 || ring_size_max + 1 > num_g2 {
+        return Err(invalid_data(format!(
+            "a .ptau of power {} supports rings up to {}, requested {}",
+            power,
+            (num_g1 - 1) / 2,
+            ring_size_max
+        )));
+    }
+
+    let (g1_offset, _) = section(PTAU_SECTION_TAU_G1)?;
+    let mut crs_g1s = read_points(&mut reader, g1_offset, 2 * ring_size_max + 1, 2 * n8, E::g1_from_lem)?;
+    let (g2_offset, _) = section(PTAU_SECTION_TAU_G2)?;
+    let crs_g2s = read_points(&mut reader, g2_offset, ring_size_max + 1, 4 * n8, E::g2_from_lem)?;
+
+    crs_g1s[ring_size_max + 1] = E::G1Affine::zero();
+
+    Ok(SmaCRS {
+        g1_generator: crs_g1s[0].into_group(),
+        g2_generator: crs_g2s[0].into_group(),
+        crs_g1s,
+        crs_g2s,
+        ring_size_max,
+    })
+}
@@ -1,15 +1,22 @@
+use crate::json::{self, JsonPairing};
 use ark_ec::pairing::Pairing;
 use ark_serialize::*;
 use ark_std::vec::Vec;
 use ark_std::Zero;
+use serde::{Deserialize, Serialize};
 
 /// Modifying Paper `Simulation Extractable Versions of Groth’s zk-SNARK Revisited` to the commit-carry version
 
-#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
 pub struct SmaCRS<E: Pairing> {
+    #[serde(with = "json::projective")]
     pub g1_generator: E::G1,
+    #[serde(with = "json::projective")]
     pub g2_generator: E::G2,
+    #[serde(with = "json::affines")]
     pub crs_g1s: Vec<E::G1Affine>,
+    #[serde(with = "json::affines")]
     pub crs_g2s: Vec<E::G2Affine>,
     pub ring_size_max: usize,
 }
@@ -27,11 +34,16 @@ impl <E: Pairing> Default for SmaCRS<E> {
     
 }
 
-#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
 pub struct SmaComm<E: Pairing> {
+    #[serde(with = "json::projective")]
     pub c_g1:       E::G1,
+    #[serde(with = "json::projective")]
     pub c_b_g2:     E::G2,
+    #[serde(with = "json::field")]
     pub r:          E::ScalarField,
+    #[serde(with = "json::field")]
     pub r_b:        E::ScalarField,
 }
 
@@ -47,14 +59,22 @@ impl <E: Pairing> Default for SmaComm<E> {
 }
 
 /// A proof in the Groth16 SNARK.
-#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
 pub struct SmaProof<E: Pairing> {
+    #[serde(with = "json::affine")]
     pub c_b_g2: E::G2Affine,
+    #[serde(with = "json::affine")]
     pub c_s_g1: E::G1Affine,
+    #[serde(with = "json::affine")]
     pub pi:     E::G1Affine,
+    #[serde(with = "json::affine")]
     pub c_h_g1: E::G1Affine,
+    #[serde(with = "json::affine")]
     pub c_u_g2: E::G2Affine,
+    #[serde(with = "json::affine")]
     pub c_t_g2: E::G2Affine,
+    #[serde(with = "json::affine")]
     pub pi_kzg: E::G1Affine,
 }
 
@@ -71,4 +91,62 @@ impl <E: Pairing> Default for SmaProof<E> {
         }
     }
     
-}
\ No newline at end of file
+}
+
+/// A proof that the value committed in `SmaComm::c_g1` is not a member of a public set.
+///
+/// `w_g1` is the blinded KZG quotient of the set's vanishing polynomial, `c_u_g1` and
+/// `c_d_g1` commit to the blinding product and to the (non-zero) remainder respectively.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize)]
+#[serde(bound = "E: JsonPairing")]
+pub struct SmaNonMemProof<E: Pairing> {
+    #[serde(with = "json::affine")]
+    pub w_g1:       E::G1Affine,
+    #[serde(with = "json::affine")]
+    pub c_u_g1:     E::G1Affine,
+    #[serde(with = "json::affine")]
+    pub c_d_g1:     E::G1Affine,
+    #[serde(with = "json::field")]
+    pub challenge:  E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_phi:      E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_r:        E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_t:        E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_u:        E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_r_u:      E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_r_tc:     E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_d:        E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_r_d:      E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_d_inv:    E::ScalarField,
+    #[serde(with = "json::field")]
+    pub z_s:        E::ScalarField,
+}
+
+impl <E: Pairing> Default for SmaNonMemProof<E> {
+    fn default() -> Self {
+        Self {
+            w_g1:       E::G1Affine::default(),
+            c_u_g1:     E::G1Affine::default(),
+            c_d_g1:     E::G1Affine::default(),
+            challenge:  E::ScalarField::zero(),
+            z_phi:      E::ScalarField::zero(),
+            z_r:        E::ScalarField::zero(),
+            z_t:        E::ScalarField::zero(),
+            z_u:        E::ScalarField::zero(),
+            z_r_u:      E::ScalarField::zero(),
+            z_r_tc:     E::ScalarField::zero(),
+            z_d:        E::ScalarField::zero(),
+            z_r_d:      E::ScalarField::zero(),
+            z_d_inv:    E::ScalarField::zero(),
+            z_s:        E::ScalarField::zero(),
+        }
+    }
+}
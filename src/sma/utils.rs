@@ -1,12 +1,18 @@
+use crate::cc::helpers::hash_to_field;
 use crate::sma::{SmaCRS, SmaComm};
 use ark_ec::AffineRepr;
-use ark_ec::pairing::Pairing;
-use ark_ff::{One, Zero};
+use ark_ec::VariableBaseMSM;
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ff::{FftField, One, Zero};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::DenseUVPolynomial;
+use ark_serialize::CanonicalSerialize;
 use ark_std::fmt::Debug;
 use ark_std::rand::RngCore;
 use ark_std::str::FromStr;
 use ark_std::UniformRand;
-use std::ops::Mul;
+use ark_std::ops::Mul;
+use ark_std::vec::Vec;
 
 #[allow(unused_variables)]
 pub fn ring_gen<R: RngCore, E: Pairing>(
@@ -58,3 +64,45 @@ pub fn kzg_evaluate<E: Pairing>(poly: &Vec<E::ScalarField>, x: E::ScalarField) -
     }
     eval
 }
+
+/// Coefficients (lowest degree first) of `Z_S(X) = prod_{s in set} (X - s)`.
+/// The set carries no placeholder at index 0, unlike the rings built by `ring_gen`.
+pub fn vanishing_poly<E: Pairing>(set: &[E::ScalarField]) -> Vec<E::ScalarField> {
+    fn product<F: FftField>(set: &[F]) -> DensePolynomial<F> {
+        match set.len() {
+            0 => DensePolynomial::from_coefficients_vec(vec![F::one()]),
+            1 => DensePolynomial::from_coefficients_vec(vec![-set[0], F::one()]),
+            len => {
+                let (left, right) = set.split_at(len / 2);
+                &product(left) * &product(right)
+            }
+        }
+    }
+
+    let mut coeffs = product(set).coeffs;
+    coeffs.resize(set.len() + 1, E::ScalarField::zero());
+    coeffs
+}
+
+/// Commits to the vanishing polynomial of `set` under the SMA powers of alpha,
+/// i.e. returns `[Z_S(alpha)]_1`. Requires `set.len() <= ring_size_max`.
+pub fn set_accumulator<E: Pairing>(sma_crs: &SmaCRS<E>, set: &[E::ScalarField]) -> E::G1 {
+    let coeffs = vanishing_poly::<E>(set);
+    E::G1::msm(&sma_crs.crs_g1s[..coeffs.len()], &coeffs).unwrap()
+}
+
+/// Fiat-Shamir challenge of the set non-membership argument, binding the message,
+/// the statement, the proof elements and the sigma-protocol first messages.
+pub(crate) fn non_member_challenge<E: Pairing>(
+    message: &str,
+    g1_elems: &[E::G1],
+    gt_elem: &PairingOutput<E>,
+) -> E::ScalarField {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(message.as_bytes());
+    for p in g1_elems {
+        p.serialize_compressed(&mut bytes).unwrap();
+    }
+    gt_elem.serialize_compressed(&mut bytes).unwrap();
+    hash_to_field::<E>(bytes)
+}
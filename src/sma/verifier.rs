@@ -1,6 +1,6 @@
 use crate::cc::helpers::hash_to_field;
-use crate::sma::utils::kzg_evaluate;
-use crate::sma::SmaProof;
+use crate::sma::utils::{kzg_evaluate, non_member_challenge, set_accumulator};
+use crate::sma::{SmaNonMemProof, SmaProof};
 
 use ark_ec::{pairing::Pairing, Group};
 
@@ -8,12 +8,15 @@ use ark_ff::{One, Zero};
 use ark_serialize::CanonicalSerialize;
 use ark_std::fmt::Debug;
 use ark_std::str::FromStr;
-use ark_std::time::Instant;
+use ark_std::vec::Vec;
+use crate::time::Instant;
 
-use std::collections::HashMap;
+use ark_std::collections::BTreeMap;
 use crate::sma::SmaCRS;
 use crate::sma::SmaComm;
 
+/// Verifies a `SmaProof` that the value committed in `comm.c_g1` is a member of `ring`,
+/// whose 0-th entry is not a member.
 #[allow(non_snake_case)]
 pub fn verify_set_member_proof_opt<E: Pairing>(
     message: &str,
@@ -21,13 +24,18 @@ pub fn verify_set_member_proof_opt<E: Pairing>(
     comm: &SmaComm<E>,
     ring: &Vec<E::ScalarField>,
     sma_proof: &SmaProof<E>,
-) where
+) -> bool
+where
     <E::ScalarField as FromStr>::Err: Debug,
 {
-    let ring_size_real = ring.len() - 1; // omit the 0-th index
     let ring_size_max = sma_crs.ring_size_max; // max size of the ring
+    let ring_size_real = match ring.len().checked_sub(1) {
+        // omit the 0-th index
+        Some(ring_size_real) if ring_size_real <= ring_size_max => ring_size_real,
+        _ => return false,
+    };
 
-    let mut records = HashMap::new();
+    let mut records = BTreeMap::new();
 
     let start_randomness = Instant::now();
 
@@ -204,13 +212,13 @@ pub fn verify_set_member_proof_opt<E: Pairing>(
 
     let start_verify_3_outsource_verify = Instant::now();
     // Perform pairing checks
-    assert_eq!(
-        E::pairing(E::G1::from(sma_proof.c_h_g1) - E::G1::generator() * eval_1, E::G2::generator() * delta_h) + 
+    if E::pairing(E::G1::from(sma_proof.c_h_g1) - E::G1::generator() * eval_1, E::G2::generator() * delta_h) + 
             E::pairing(E::G1::generator() * delta_u, E::G2::from(sma_proof.c_u_g2) - E::G2::generator() * eval_2) +
-            E::pairing(E::G1::generator() * delta_t, E::G2::from(sma_proof.c_t_g2) - E::G2::generator() * eval_3),
-
-        E::pairing(sma_proof.pi_kzg, E::G2::from(sma_crs.crs_g2s[1]) - E::G2::generator() * z)
-    );
+            E::pairing(E::G1::generator() * delta_t, E::G2::from(sma_proof.c_t_g2) - E::G2::generator() * eval_3)
+        != E::pairing(sma_proof.pi_kzg, E::G2::from(sma_crs.crs_g2s[1]) - E::G2::generator() * z)
+    {
+        return false;
+    }
 
     let duration_verify_3_outsource_verify = start_verify_3_outsource_verify.elapsed();
     records.insert(
@@ -226,13 +234,11 @@ pub fn verify_set_member_proof_opt<E: Pairing>(
     let e12_left_up_g1 = sma_proof.c_s_g1 * delta_b + sma_proof.c_h_g1;
     let e12_left_down1_g2 = E::G2::from(sma_crs.crs_g2s[ring_size_max]) * delta_phi + sma_proof.c_u_g2 - E::G2::from(sma_crs.crs_g2s[ring_size_max-1]) * delta_d2;
     let e12_left_down2_g2 = sma_proof.c_t_g2;
-    assert!(
-        E::pairing(e12_left_up_g1, sma_proof.c_b_g2) +
+    let result = E::pairing(e12_left_up_g1, sma_proof.c_b_g2) +
             - (E::pairing(comm.c_g1, e12_left_down1_g2)
                 + E::pairing(sma_proof.c_s_g1, e12_left_down2_g2)
                 + E::pairing(sma_crs.crs_g1s[ring_size_max] * delta_o, sma_crs.crs_g2s[1]))
-            == E::pairing(sma_proof.pi, E::G2::generator())
-    );
+            == E::pairing(sma_proof.pi, E::G2::generator());
     let duration_verify_4_pi = start_verify_4_pi.elapsed();
     records.insert(
         "Verify 4 pi",
@@ -260,4 +266,47 @@ pub fn verify_set_member_proof_opt<E: Pairing>(
     // for (key, value) in records.iter() {
     //     println!("{:<30}: {}", key, value);
     // }
+    result
+}
+
+/// Verifies a `SmaNonMemProof` that the value committed in `comm.c_g1` is not in `set`.
+/// See `set_non_member_proof` for the relations checked.
+#[allow(non_snake_case)]
+pub fn verify_set_non_member_proof<E: Pairing>(
+    message: &str,
+    sma_crs: &SmaCRS<E>,
+    comm: &SmaComm<E>,
+    set: &[E::ScalarField],
+    proof: &SmaNonMemProof<E>,
+) -> bool {
+    if set.len() > sma_crs.ring_size_max {
+        return false;
+    }
+
+    let g = sma_crs.g1_generator;
+    let g_1 = E::G1::from(sma_crs.crs_g1s[1]);
+    let w_g1 = E::G1::from(proof.w_g1);
+    let c_u_g1 = E::G1::from(proof.c_u_g1);
+    let c_d_g1 = E::G1::from(proof.c_d_g1);
+    let ch = proof.challenge;
+
+    let acc = set_accumulator(sma_crs, set);
+
+    let gt = E::pairing(g, sma_crs.g2_generator);
+    let b_w = E::pairing(w_g1, sma_crs.g2_generator);
+    let b_g_1 = E::pairing(g_1, sma_crs.g2_generator);
+    let y = E::pairing(acc, sma_crs.g2_generator) - E::pairing(w_g1, sma_crs.crs_g2s[1]);
+
+    let r_1 = g * proof.z_r + g_1 * proof.z_phi - comm.c_g1 * ch;
+    let r_2 = gt * (proof.z_u + proof.z_d) - b_w * proof.z_phi - b_g_1 * proof.z_t - y * ch;
+    let r_3 = g_1 * proof.z_u + g * proof.z_r_u - c_u_g1 * ch;
+    let r_4 = comm.c_g1 * proof.z_t + g * proof.z_r_tc - c_u_g1 * ch;
+    let r_5 = g_1 * proof.z_d + g * proof.z_r_d - c_d_g1 * ch;
+    let r_6 = c_d_g1 * proof.z_d_inv + g * proof.z_s - g_1 * ch;
+
+    ch == non_member_challenge::<E>(
+        message,
+        &[comm.c_g1, acc, w_g1, c_u_g1, c_d_g1, r_1, r_3, r_4, r_5, r_6],
+        &r_2,
+    )
 }
@@ -0,0 +1,27 @@
+//! Wall-clock timing for the `SignTime`/`VerifyTime` and SMA records. Without the `std`
+//! feature there is no clock, e.g. on `wasm32-unknown-unknown`, and every duration is zero.
+
+use core::time::Duration;
+
+/// A drop-in for `std::time::Instant` that also builds without `std`.
+#[derive(Clone, Copy, Debug)]
+pub struct Instant {
+    #[cfg(feature = "std")]
+    inner: std::time::Instant,
+}
+
+impl Instant {
+    pub fn now() -> Self {
+        Instant {
+            #[cfg(feature = "std")]
+            inner: std::time::Instant::now(),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        #[cfg(feature = "std")]
+        return self.inner.elapsed();
+        #[cfg(not(feature = "std"))]
+        return Duration::ZERO;
+    }
+}
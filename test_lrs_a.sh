@@ -6,14 +6,7 @@ echo "1"
 echo "   cd /circoms/lrs_a"
 echo "   run 'bash lrs_a.sh'(you may need to adjust var 'tree_height_low' and var 'tree_height_high' in lrs_a.sh to the desired range)\n"
 echo "2"
-echo "   adjust /src/constants.rs to add the macro defines that the desired range needed\n"
-echo "   add 'define_input_and_circuit_names_lrs_a!(...)'"
-echo "3"
-echo "   adjust /src/bin/lrs_a.rs to the desired range:"
-echo "   modify variable 'ioputs_names'"
-echo "   modify variable 'circuit_names'"
-echo "   modify variable 'low'"
-echo "   modify variable 'high'"
+echo "   adjust 'tree_height_low' and 'tree_height_high' in this script to the same range\n"
 echo "Then"
 echo "    You must run this code in the root directory of current project"
 echo "    You are in $(pwd)"
@@ -33,7 +26,9 @@ if [ ! -d "./target/release" ]; then
 fi
 
 repeat=20
-taskset -c 0 ./target/release/lrs_a $repeat > ./logs/lrs_a_test_$time.log 2>&1
+tree_height_low=3
+tree_height_high=20
+taskset -c 0 ./target/release/lrs_a $repeat $tree_height_low $tree_height_high > ./logs/lrs_a_test_$time.log 2>&1
 if [ $? -ne 0 ]; then
     echo "lrs_a_test failed"
     exit 1
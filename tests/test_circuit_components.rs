@@ -26,6 +26,7 @@ where
         ioputs_name: ioputs_name.iter().map(|s| s.to_string()).collect(),
         path_prefix: path_prefix.to_string(),
         circuit_name: circuit_name.to_string(),
+        hashed_pub_io: None,
     };
 
     let lrs_circ = LRSCirc::<E::ScalarField>::construct(&circ_desc).unwrap();
@@ -88,6 +89,7 @@ where
         ioputs_name: IOPUTS_NAME_MERKLE.iter().map(|s| s.to_string()).collect(),
         path_prefix: PATH_PREFIX_MERKLE.to_string(),
         circuit_name: CIRCUIT_NAME_MERKLE.to_string(),
+        hashed_pub_io: None,
     };
 
     let lrs_circ = LRSCirc::<E::ScalarField>::construct(&circ_desc).unwrap();
@@ -129,6 +131,7 @@ where
             .collect(),
         path_prefix: PATH_PREFIX_SCHNORR_SIGN.to_string(),
         circuit_name: CIRCUIT_NAME_SCHNORR_SIGN.to_string(),
+        hashed_pub_io: None,
     };
 
     let lrs_circ = LRSCirc::<E::ScalarField>::construct(&circ_desc).unwrap();
@@ -169,6 +172,7 @@ where
             .collect(),
         path_prefix: PATH_PREFIX_SCHNORR_VERIFY.to_string(),
         circuit_name: CIRCUIT_NAME_SCHNORR_VERIFY.to_string(),
+        hashed_pub_io: None,
     };
 
     let lrs_circ = LRSCirc::<E::ScalarField>::construct(&circ_desc).unwrap();
@@ -240,6 +244,7 @@ where
         ioputs_name: IOPUTS_NAME_LRS_A.iter().map(|s| s.to_string()).collect(),
         path_prefix: PATH_PREFIX_LRS_A.to_string(),
         circuit_name: CIRCUIT_NAME_LRS_A.to_string(),
+        hashed_pub_io: None,
     };
 
     let lrs_circ = LRSCirc::<E::ScalarField>::construct(&circ_desc).unwrap();